@@ -0,0 +1,315 @@
+use crate::tcg::*;
+use hashbrown::HashMap;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha3::{Sha3_256, Sha3_384, Sha3_512};
+use std::sync::{Arc, RwLock};
+
+/***
+    Trait describing a TCG hash algorithm, i.e. the mapping of the TCG algorithm ID
+    defined in TCG Algorithm Registry to its name, digest size and hash function.
+
+    Event log parsing uses the digest size to walk the digests of algorithms not
+    declared in the Specification ID event, and replay uses the hash function to
+    extend the IMRs. Applications can register more algorithms with register_algorithm().
+*/
+pub trait TcgHashAlgorithm: Send + Sync {
+    fn algo_id(&self) -> u16;
+    fn name(&self) -> &str;
+    fn digest_size(&self) -> usize;
+    fn hash(&self, data: &[u8]) -> Vec<u8>;
+}
+
+// hash algorithm defined by a hash function
+pub struct HashAlgorithm {
+    pub algo_id: u16,
+    pub name: &'static str,
+    pub digest_size: usize,
+    pub hash_fn: fn(&[u8]) -> Vec<u8>,
+}
+
+impl TcgHashAlgorithm for HashAlgorithm {
+    fn algo_id(&self) -> u16 {
+        self.algo_id
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn digest_size(&self) -> usize {
+        self.digest_size
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        (self.hash_fn)(data)
+    }
+}
+
+// built-in hash algorithms
+lazy_static! {
+    static ref ALGORITHM_REGISTRY: RwLock<HashMap<u16, Arc<dyn TcgHashAlgorithm>>> = {
+        let builtins = [
+            HashAlgorithm {
+                algo_id: TPM_ALG_SHA1,
+                name: "TPM_ALG_SHA1",
+                digest_size: 20,
+                hash_fn: |data| Sha1::digest(data).to_vec(),
+            },
+            HashAlgorithm {
+                algo_id: TPM_ALG_SHA256,
+                name: "TPM_ALG_SHA256",
+                digest_size: 32,
+                hash_fn: |data| Sha256::digest(data).to_vec(),
+            },
+            HashAlgorithm {
+                algo_id: TPM_ALG_SHA384,
+                name: "TPM_ALG_SHA384",
+                digest_size: 48,
+                hash_fn: |data| Sha384::digest(data).to_vec(),
+            },
+            HashAlgorithm {
+                algo_id: TPM_ALG_SHA512,
+                name: "TPM_ALG_SHA512",
+                digest_size: 64,
+                hash_fn: |data| Sha512::digest(data).to_vec(),
+            },
+            HashAlgorithm {
+                algo_id: TPM_ALG_SM3_256,
+                name: "TPM_ALG_SM3_256",
+                digest_size: 32,
+                hash_fn: sm3,
+            },
+            HashAlgorithm {
+                algo_id: TPM_ALG_SHA3_256,
+                name: "TPM_ALG_SHA3_256",
+                digest_size: 32,
+                hash_fn: |data| Sha3_256::digest(data).to_vec(),
+            },
+            HashAlgorithm {
+                algo_id: TPM_ALG_SHA3_384,
+                name: "TPM_ALG_SHA3_384",
+                digest_size: 48,
+                hash_fn: |data| Sha3_384::digest(data).to_vec(),
+            },
+            HashAlgorithm {
+                algo_id: TPM_ALG_SHA3_512,
+                name: "TPM_ALG_SHA3_512",
+                digest_size: 64,
+                hash_fn: |data| Sha3_512::digest(data).to_vec(),
+            },
+        ];
+        let mut map: HashMap<u16, Arc<dyn TcgHashAlgorithm>> = HashMap::new();
+        for algorithm in builtins {
+            map.insert(algorithm.algo_id, Arc::new(algorithm));
+        }
+        RwLock::new(map)
+    };
+}
+
+/***
+    Register a hash algorithm, replacing the one registered with the same algorithm ID.
+    Returns:
+        The algorithm replaced if any
+*/
+pub fn register_algorithm(
+    algorithm: Arc<dyn TcgHashAlgorithm>,
+) -> Option<Arc<dyn TcgHashAlgorithm>> {
+    let mut registry = match ALGORITHM_REGISTRY.write() {
+        Ok(registry) => registry,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    registry.insert(algorithm.algo_id(), algorithm)
+}
+
+// get the hash algorithm registered with the algorithm ID
+pub fn get_algorithm(algo_id: u16) -> Option<Arc<dyn TcgHashAlgorithm>> {
+    let registry = match ALGORITHM_REGISTRY.read() {
+        Ok(registry) => registry,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    registry.get(&algo_id).cloned()
+}
+
+/***
+    Get the registered hash algorithms, ordered by algorithm ID.
+*/
+pub fn get_algorithms() -> Vec<Arc<dyn TcgHashAlgorithm>> {
+    let registry = match ALGORITHM_REGISTRY.read() {
+        Ok(registry) => registry,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut algorithms: Vec<Arc<dyn TcgHashAlgorithm>> = registry.values().cloned().collect();
+    algorithms.sort_by_key(|algorithm| algorithm.algo_id());
+    algorithms
+}
+
+/***
+    Get the registered hash algorithm producing digests of the size. When several
+    algorithms share the size, e.g. SHA256, SM3_256 and SHA3_256, the one with the
+    lowest algorithm ID is returned, i.e. SHA1 and SHA2 are preferred.
+*/
+pub fn get_algorithm_by_digest_size(digest_size: usize) -> Option<Arc<dyn TcgHashAlgorithm>> {
+    get_algorithms()
+        .into_iter()
+        .find(|algorithm| algorithm.digest_size() == digest_size)
+}
+
+/***
+    SM3 hash defined in GB/T 32905-2016, see
+    https://datatracker.ietf.org/doc/html/draft-sca-cfrg-sm3-02
+*/
+pub fn sm3(data: &[u8]) -> Vec<u8> {
+    let mut v: [u32; 8] = [
+        0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d,
+        0xb0fb0e4e,
+    ];
+
+    // pad the message to a multiple of 64 bytes with 0x80, zeros and the bit length
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(((data.len() as u64) << 3).to_be_bytes());
+
+    let p0 = |x: u32| x ^ x.rotate_left(9) ^ x.rotate_left(17);
+    let p1 = |x: u32| x ^ x.rotate_left(15) ^ x.rotate_left(23);
+
+    for block in message.chunks(64) {
+        let mut w = [0_u32; 68];
+        for (j, word) in block.chunks(4).enumerate() {
+            w[j] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for j in 16..68 {
+            w[j] = p1(w[j - 16] ^ w[j - 9] ^ w[j - 3].rotate_left(15))
+                ^ w[j - 13].rotate_left(7)
+                ^ w[j - 6];
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = v;
+        for j in 0..64 {
+            let (t, ff, gg) = if j < 16 {
+                (0x79cc4519_u32, a ^ b ^ c, e ^ f ^ g)
+            } else {
+                (
+                    0x7a879d8a_u32,
+                    (a & b) | (a & c) | (b & c),
+                    (e & f) | (!e & g),
+                )
+            };
+            let ss1 = a
+                .rotate_left(12)
+                .wrapping_add(e)
+                .wrapping_add(t.rotate_left(j as u32 % 32))
+                .rotate_left(7);
+            let ss2 = ss1 ^ a.rotate_left(12);
+            let tt1 = ff
+                .wrapping_add(d)
+                .wrapping_add(ss2)
+                .wrapping_add(w[j] ^ w[j + 4]);
+            let tt2 = gg.wrapping_add(h).wrapping_add(ss1).wrapping_add(w[j]);
+            d = c;
+            c = b.rotate_left(9);
+            b = a;
+            a = tt1;
+            h = g;
+            g = f.rotate_left(19);
+            f = e;
+            e = p0(tt2);
+        }
+
+        for (state, value) in v.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state ^= value;
+        }
+    }
+
+    v.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+#[cfg(test)]
+mod test_algorithm {
+    use super::*;
+
+    #[test]
+    //SM3 sample from GB/T 32905-2016
+    fn test_sm3() {
+        assert_eq!(
+            hex::encode(sm3(b"abc")),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+        assert_eq!(
+            hex::encode(sm3(&b"abcd".repeat(16))),
+            "debe9ff92275b8a138604889c18e5a4d6fdb70e5387e5765293dcba39c0c5732"
+        );
+    }
+
+    #[test]
+    //built-in algorithms are registered
+    fn test_builtin_algorithms() {
+        let sha3 = get_algorithm(TPM_ALG_SHA3_384).unwrap();
+        assert_eq!(sha3.name(), "TPM_ALG_SHA3_384");
+        assert_eq!(sha3.hash(b"abc").len(), sha3.digest_size());
+        assert_eq!(get_algorithm(TPM_ALG_SM3_256).unwrap().digest_size(), 32);
+        assert!(get_algorithm(TPM_ALG_RSA).is_none());
+    }
+
+    #[test]
+    //applications can register more algorithms
+    fn test_register_algorithm() {
+        let algo_id = 0x7f01;
+        assert!(get_algorithm(algo_id).is_none());
+        let replaced = register_algorithm(Arc::new(HashAlgorithm {
+            algo_id,
+            name: "TEST_ALG_XOR",
+            digest_size: 3,
+            hash_fn: |data| vec![data.iter().fold(0, |acc, v| acc ^ v); 3],
+        }));
+        assert!(replaced.is_none());
+        assert_eq!(get_algorithm(algo_id).unwrap().hash(&[1, 2, 4]), vec![7; 3]);
+        assert_eq!(TcgDigest::get_algorithm_name(algo_id), "TEST_ALG_XOR");
+        assert_eq!(TcgDigest::get_digest_size_from_algorithm_id(algo_id), 3);
+        assert_eq!(TcgDigest::get_algorithm_id_from_digest_size(3), algo_id);
+    }
+
+    #[test]
+    //digest sizes resolve to the preferred registered algorithm
+    fn test_algorithm_by_digest_size() {
+        assert_eq!(
+            TcgDigest::get_algorithm_id_from_digest_size(20),
+            TPM_ALG_SHA1
+        );
+        assert_eq!(
+            TcgDigest::get_algorithm_id_from_digest_size(32),
+            TPM_ALG_SHA256
+        );
+        assert_eq!(
+            TcgDigest::get_algorithm_id_from_digest_size(48),
+            TPM_ALG_SHA384
+        );
+        assert_eq!(
+            TcgDigest::get_algorithm_id_from_digest_size(64),
+            TPM_ALG_SHA512
+        );
+        assert_eq!(
+            TcgDigest::get_algorithm_id_from_digest_size(33),
+            TPM_ALG_ERROR
+        );
+        assert!(get_algorithms()
+            .windows(2)
+            .all(|pair| pair[0].algo_id() < pair[1].algo_id()));
+    }
+
+    #[test]
+    //digest sizes not fitting in a byte are reported as unknown
+    fn test_large_digest_size() {
+        let algo_id = 0x7f02;
+        register_algorithm(Arc::new(HashAlgorithm {
+            algo_id,
+            name: "TEST_ALG_LARGE",
+            digest_size: 256,
+            hash_fn: |_| vec![0; 256],
+        }));
+        assert_eq!(TcgDigest::get_digest_size_from_algorithm_id(algo_id), 0);
+    }
+}
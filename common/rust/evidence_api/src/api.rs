@@ -60,7 +60,8 @@ pub trait EvidenceApi {
         registers, the count is update to ``get_measurement_count()``. And for each
         measurement register, it may provides multiple digest for different algorithms.
         Args:
-            index (u8): the index of measurement register,
+            index (u8): the CC MR index of measurement register, see CcMrIndex for
+                        the mapping to TDX registers and TPM PCRs
             algo_id (u8): the alrogithms ID
         Returns:
             TcgDigest struct
@@ -114,14 +115,12 @@ pub trait EvidenceApi {
            Layer 1 key of the struct is the IMR index, the value is another dict which using the
            hash algorithm as the key and the replayed measurement as value.
            Sample value:
-               [
-                   0: [{ 4: <measurement_replayed>},{ 12: <measurement_replayed>},]
-                   1: { 12: <measurement_replayed>},
-               ]
+               {
+                   0: { 4: <measurement_replayed>, 12: <measurement_replayed> },
+                   1: { 12: <measurement_replayed> },
+               }
     */
-    fn replay_cc_eventlog(
-        eventlogs: Vec<EventLogEntry>,
-    ) -> Result<Vec<ReplayResult>, anyhow::Error> {
+    fn replay_cc_eventlog(eventlogs: Vec<EventLogEntry>) -> Result<ReplayResults, anyhow::Error> {
         EventLogs::replay(eventlogs)
     }
 }
@@ -1,5 +1,6 @@
 use crate::cc_type::TeeType;
 use crate::tcg::TcgDigest;
+use std::collections::BTreeMap;
 
 /***
  ************************************
@@ -76,3 +77,9 @@ pub struct ReplayResult {
     pub imr_index: u32,
     pub digests: Vec<TcgDigest>,
 }
+
+// replayed measurements keyed by IMR index, then by hash algorithm
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ReplayResults {
+    pub imrs: BTreeMap<u32, BTreeMap<u16, Vec<u8>>>,
+}
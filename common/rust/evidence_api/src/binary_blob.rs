@@ -69,3 +69,7 @@ pub fn get_u16(data: Vec<u8>) -> u16 {
 pub fn get_u32(data: Vec<u8>) -> u32 {
     u32::from_le_bytes(data[0..4].try_into().unwrap())
 }
+
+pub fn get_u64(data: Vec<u8>) -> u64 {
+    u64::from_le_bytes(data[0..8].try_into().unwrap())
+}
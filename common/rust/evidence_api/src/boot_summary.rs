@@ -0,0 +1,438 @@
+use crate::eventlog::EventLogs;
+use crate::tcg::*;
+use crate::uefi::firmware::description_to_string;
+use crate::uefi::utf16_to_string;
+use log::info;
+
+/***
+    Summary of the boot chain recorded in the boot time event logs, i.e. the
+    information usually looked at when a measurement changes.
+
+    Attributes:
+        firmware_versions: strings of EV_S_CRTM_VERSION and EV_POST_CODE events
+        bootloaders: EFI applications loaded by the firmware, in load order
+        grub_commands: commands executed by grub, without the "grub_cmd: " prefix
+        kernel_cmdline: command line measured by grub, systemd-boot or systemd-stub
+        kernel: kernel image loaded by grub or the .linux section of an UKI
+        initrds: initrd images loaded by grub or the .initrd section of an UKI
+        uki_sections: PE sections of an UKI measured by systemd-stub
+        separators: EV_SEPARATOR events, splitting pre-boot and OS-present measurements
+*/
+#[derive(Clone, Default)]
+pub struct BootSummary {
+    pub firmware_versions: Vec<String>,
+    pub bootloaders: Vec<BootComponent>,
+    pub grub_commands: Vec<String>,
+    pub kernel_cmdline: Option<String>,
+    pub kernel: Option<BootFile>,
+    pub initrds: Vec<BootFile>,
+    pub uki_sections: Vec<UkiSection>,
+    pub separators: Vec<SeparatorPosition>,
+}
+
+// kind of the EFI application, guessed from its file name
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BootComponentKind {
+    Shim,
+    Grub,
+    SystemdBoot,
+    Uki,
+    Other,
+}
+
+/***
+    EFI application measured in an EV_EFI_BOOT_SERVICES_APPLICATION event.
+        path: file path of the image, or the whole device path if it has no file path
+        device_path: device path of the image in UEFI text format
+*/
+#[derive(Clone)]
+pub struct BootComponent {
+    pub kind: BootComponentKind,
+    pub path: String,
+    pub device_path: String,
+    pub imr_index: u32,
+    pub digests: Vec<TcgDigest>,
+}
+
+// file measured by the bootloader, the digests are the digests of the file content
+#[derive(Clone)]
+pub struct BootFile {
+    pub path: String,
+    pub imr_index: u32,
+    pub digests: Vec<TcgDigest>,
+}
+
+/***
+    UKI section measured by systemd-stub. The section name and the section content
+    are measured in two events with the section name as event data.
+*/
+#[derive(Clone)]
+pub struct UkiSection {
+    pub name: String,
+    pub imr_index: u32,
+    pub name_digests: Vec<TcgDigest>,
+    pub content_digests: Vec<TcgDigest>,
+}
+
+/***
+    Position of an EV_SEPARATOR event.
+        imr_index: the index of the register the separator is measured to
+        event_index: index of the event in the event logs
+        imr_position: number of events measured to the register before the separator
+        error: the separator value is 1, i.e. an error occurred before it
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SeparatorPosition {
+    pub imr_index: u32,
+    pub event_index: usize,
+    pub imr_position: usize,
+    pub error: bool,
+}
+
+pub const GRUB_CMD_PREFIX: &str = "grub_cmd: ";
+pub const GRUB_KERNEL_CMDLINE_PREFIX: &str = "kernel_cmdline: ";
+pub const UKI_SECTIONS: [&str; 12] = [
+    ".linux", ".osrel", ".cmdline", ".initrd", ".ucode", ".splash", ".dtb", ".uname", ".sbat",
+    ".pcrsig", ".pcrpkey", ".profile",
+];
+
+impl BootComponentKind {
+    pub fn from_path(path: &str) -> BootComponentKind {
+        let path = path.replace('/', "\\").to_lowercase();
+        let name = path.rsplit('\\').next().unwrap_or_default();
+        if name.starts_with("shim") {
+            BootComponentKind::Shim
+        } else if name.starts_with("grub") {
+            BootComponentKind::Grub
+        } else if name.starts_with("systemd-boot") {
+            BootComponentKind::SystemdBoot
+        } else if path.starts_with("\\efi\\linux\\") {
+            BootComponentKind::Uki
+        } else {
+            BootComponentKind::Other
+        }
+    }
+}
+
+// text carried by the event data, either UTF-16 or ASCII, None for binary data
+fn event_text(data: &[u8]) -> Option<String> {
+    let is_utf16 = data.len() >= 2
+        && data.chunks_exact(2).remainder().is_empty()
+        && data.chunks_exact(2).all(|c| c[1] == 0);
+    let text = if is_utf16 {
+        utf16_to_string(data)
+    } else {
+        description_to_string(data)
+    };
+    if text.is_empty()
+        || text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\t' | '\r'))
+    {
+        return None;
+    }
+    Some(text)
+}
+
+impl BootSummary {
+    /***
+        Summarize the boot chain from the boot time event logs.
+        Args:
+            event_logs: event logs in TCG_PCCLIENT_FORMAT, e.g. from EventLogs::select()
+        Returns:
+            The boot summary, or an error if the data of an image load event is malformed
+    */
+    pub fn from_event_logs(event_logs: &[EventLogEntry]) -> Result<BootSummary, anyhow::Error> {
+        let mut summary = BootSummary::default();
+        let mut imr_positions: Vec<usize> = Vec::new();
+        let mut kernel_path: Option<String> = None;
+        let mut initrd_paths: Vec<String> = Vec::new();
+
+        for (event_index, event) in event_logs.iter().enumerate() {
+            let event = match event {
+                EventLogEntry::TcgImrEvent(event) => event,
+                _ => continue,
+            };
+            let imr_index = event.imr_index;
+            if imr_positions.len() <= imr_index as usize {
+                imr_positions.resize(imr_index as usize + 1, 0);
+            }
+            let imr_position = imr_positions[imr_index as usize];
+            imr_positions[imr_index as usize] += 1;
+
+            match event.event_type {
+                EV_S_CRTM_VERSION | EV_POST_CODE => {
+                    if let Some(text) = event_text(&event.event) {
+                        summary.firmware_versions.push(text);
+                    }
+                }
+                EV_SEPARATOR => summary.separators.push(SeparatorPosition {
+                    imr_index,
+                    event_index,
+                    imr_position,
+                    error: event.event == 1_u32.to_le_bytes(),
+                }),
+                EV_EFI_BOOT_SERVICES_APPLICATION => {
+                    if let TcgEventData::UefiImageLoad(image) = event.parse_event_data()? {
+                        let device_path = image.device_path.to_string();
+                        let path = image.device_path.file_path().unwrap_or(device_path.clone());
+                        summary.bootloaders.push(BootComponent {
+                            kind: BootComponentKind::from_path(&path),
+                            path,
+                            device_path,
+                            imr_index,
+                            digests: event.digests.clone(),
+                        });
+                    }
+                }
+                EV_IPL => {
+                    let text = match event_text(&event.event) {
+                        Some(text) => text,
+                        None => continue,
+                    };
+                    if let Some(command) = text.strip_prefix(GRUB_CMD_PREFIX) {
+                        let mut args = command.split_whitespace();
+                        match args.next() {
+                            Some("linux") | Some("linuxefi") | Some("linux16") => {
+                                kernel_path = args.next().map(|v| v.to_string());
+                            }
+                            Some("initrd") | Some("initrdefi") | Some("initrd16") => {
+                                initrd_paths = args.map(|v| v.to_string()).collect();
+                                summary.initrds.clear();
+                            }
+                            _ => (),
+                        }
+                        summary.grub_commands.push(command.to_string());
+                    } else if let Some(cmdline) = text.strip_prefix(GRUB_KERNEL_CMDLINE_PREFIX) {
+                        summary.kernel_cmdline = Some(cmdline.to_string());
+                    } else if UKI_SECTIONS.contains(&text.as_str()) {
+                        summary.add_uki_section(text, imr_index, &event.digests);
+                    } else if event.event.get(1) == Some(&0) {
+                        // load options measured in UTF-16 by systemd-boot and systemd-stub
+                        summary.kernel_cmdline = Some(text);
+                    } else {
+                        let file = BootFile {
+                            path: text,
+                            imr_index,
+                            digests: event.digests.clone(),
+                        };
+                        if kernel_path
+                            .as_ref()
+                            .is_some_and(|path| file.path.ends_with(path.as_str()))
+                        {
+                            summary.kernel = Some(file);
+                        } else if initrd_paths
+                            .iter()
+                            .any(|path| file.path.ends_with(path.as_str()))
+                        {
+                            summary.initrds.push(file);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(summary)
+    }
+
+    // first event of a section carries the name digests, the second the content digests
+    fn add_uki_section(&mut self, name: String, imr_index: u32, digests: &[TcgDigest]) {
+        if let Some(section) = self.uki_sections.last_mut() {
+            if section.name == name && section.content_digests.is_empty() {
+                section.content_digests = digests.to_vec();
+                let file = BootFile {
+                    path: name.clone(),
+                    imr_index,
+                    digests: digests.to_vec(),
+                };
+                match name.as_str() {
+                    ".linux" => self.kernel = Some(file),
+                    ".initrd" => self.initrds.push(file),
+                    _ => (),
+                }
+                return;
+            }
+        }
+        self.uki_sections.push(UkiSection {
+            name,
+            imr_index,
+            name_digests: digests.to_vec(),
+            content_digests: Vec::new(),
+        });
+    }
+
+    pub fn show(&self) {
+        info!("==== Boot Summary ====");
+        for version in &self.firmware_versions {
+            info!("Firmware version  : {}", version);
+        }
+        for component in &self.bootloaders {
+            info!(
+                "Bootloader        : {:?} {} (IMR[{}])",
+                component.kind, component.path, component.imr_index
+            );
+        }
+        for command in &self.grub_commands {
+            info!("Grub command      : {}", command);
+        }
+        if let Some(cmdline) = &self.kernel_cmdline {
+            info!("Kernel cmdline    : {}", cmdline);
+        }
+        for (name, file) in self
+            .kernel
+            .iter()
+            .map(|file| ("Kernel", file))
+            .chain(self.initrds.iter().map(|file| ("Initrd", file)))
+        {
+            info!("{:<18}: {} (IMR[{}])", name, file.path, file.imr_index);
+            for digest in &file.digests {
+                info!(
+                    "    {:<14}: {}",
+                    digest.get_algorithm_id_str(),
+                    hex::encode(&digest.hash)
+                );
+            }
+        }
+        for section in &self.uki_sections {
+            info!(
+                "UKI section       : {} (IMR[{}])",
+                section.name, section.imr_index
+            );
+        }
+        for separator in &self.separators {
+            info!(
+                "Separator         : IMR[{}] position {}{}",
+                separator.imr_index,
+                separator.imr_position,
+                if separator.error { " (error)" } else { "" }
+            );
+        }
+    }
+}
+
+impl EventLogs {
+    /***
+        Summarize the boot chain recorded in the event logs, see BootSummary.
+        Unparsed data is parsed before the summary.
+    */
+    pub fn boot_summary(&mut self) -> Result<BootSummary, anyhow::Error> {
+        let event_logs = self.select(None, None)?;
+        BootSummary::from_event_logs(&event_logs)
+    }
+}
+
+#[cfg(test)]
+mod test_boot_summary {
+    use super::*;
+    use crate::ccel::CcelLoader;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    fn ipl_event(imr_index: u32, data: &[u8], hash: u8) -> EventLogEntry {
+        EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index,
+            event_type: EV_IPL,
+            digests: vec![TcgDigest {
+                algo_id: TPM_ALG_SHA384,
+                hash: vec![hash; 48],
+            }],
+            event_size: data.len() as u32,
+            event: data.to_vec(),
+        })
+    }
+
+    #[test]
+    //shim and grub boot of the TD guest
+    fn test_grub_boot_summary() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let summary = event_logs.boot_summary().unwrap();
+
+        let kinds: Vec<BootComponentKind> = summary.bootloaders.iter().map(|v| v.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                BootComponentKind::Other,
+                BootComponentKind::Other,
+                BootComponentKind::Shim,
+                BootComponentKind::Grub
+            ]
+        );
+        assert_eq!(summary.bootloaders[3].path, "\\EFI\\ubuntu\\grubx64.efi");
+        assert!(summary
+            .grub_commands
+            .contains(&"set prefix=(hd0,gpt1)/boot/grub".to_string()));
+        let kernel = summary.kernel.unwrap();
+        assert_eq!(kernel.path, "/boot/vmlinuz-6.2.16-v5.2.mvp42-generic");
+        assert_eq!(kernel.imr_index, 2);
+        assert_eq!(kernel.digests[0].hash.len(), 48);
+        assert_eq!(summary.initrds.len(), 1);
+        assert_eq!(
+            summary.initrds[0].path,
+            "/boot/initrd.img-6.2.16-v5.2.mvp42-generic"
+        );
+        assert!(summary
+            .kernel_cmdline
+            .unwrap()
+            .starts_with("/boot/vmlinuz-6.2.16-v5.2.mvp42-generic root=UUID="));
+        assert!(summary.uki_sections.is_empty());
+        assert_eq!(
+            summary.separators,
+            vec![
+                SeparatorPosition {
+                    imr_index: 0,
+                    event_index: 8,
+                    imr_position: 7,
+                    error: false
+                },
+                SeparatorPosition {
+                    imr_index: 0,
+                    event_index: 16,
+                    imr_position: 14,
+                    error: false
+                }
+            ]
+        );
+    }
+
+    #[test]
+    //UKI booted by systemd-boot, sections and load options measured by systemd-stub
+    fn test_uki_boot_summary() {
+        let version: Vec<u8> = "TDVF 1.0\0"
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        let cmdline: Vec<u8> = "console=hvc0\0"
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        let mut event_logs = vec![EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index: 0,
+            event_type: EV_S_CRTM_VERSION,
+            digests: Vec::new(),
+            event_size: version.len() as u32,
+            event: version,
+        })];
+        for (index, section) in [".linux", ".linux", ".initrd", ".initrd", ".osrel"]
+            .iter()
+            .enumerate()
+        {
+            event_logs.push(ipl_event(
+                2,
+                format!("{}\0", section).as_bytes(),
+                index as u8,
+            ));
+        }
+        event_logs.push(ipl_event(2, &cmdline, 0xff));
+
+        let summary = BootSummary::from_event_logs(&event_logs).unwrap();
+        assert_eq!(summary.firmware_versions, vec!["TDVF 1.0".to_string()]);
+        assert_eq!(summary.uki_sections.len(), 3);
+        assert_eq!(summary.uki_sections[2].name, ".osrel");
+        assert!(summary.uki_sections[2].content_digests.is_empty());
+        assert_eq!(summary.kernel.unwrap().digests[0].hash, vec![1; 48]);
+        assert_eq!(summary.initrds[0].digests[0].hash, vec![3; 48]);
+        assert_eq!(summary.kernel_cmdline.unwrap(), "console=hvc0");
+    }
+}
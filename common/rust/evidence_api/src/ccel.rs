@@ -0,0 +1,711 @@
+use crate::binary_blob::*;
+use crate::cursor::EventLogCursor;
+use crate::eventlog::EventLogs;
+use crate::ima::{
+    ima_algorithm_id, ImaByteOrder, ImaEvent, IMA_BANK_DATA_FILE_PREFIX, IMA_BINARY_DATA_FILE,
+};
+use crate::tcg::{EventLogEntry, TcgDigest};
+use crate::tdx::common::*;
+use anyhow::anyhow;
+use log::info;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub const CCEL_SIGNATURE: &[u8; 4] = b"CCEL";
+pub const CCEL_TABLE_LEN: usize = 56;
+
+/***
+    CCEL ACPI table for the CC event log.
+    see https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html#cc-event-log-acpi-table
+
+    Table layout:
+        offset, len
+        0x0,    0x4     signature, "CCEL"
+        0x4,    0x4     length
+        0x8,    0x1     revision
+        0x9,    0x1     checksum
+        0xa,    0x6     oem_id
+        0x10,   0x8     oem_table_id
+        0x18,   0x4     oem_revision
+        0x1c,   0x4     creator_id
+        0x20,   0x4     creator_revision
+        0x24,   0x1     cc_type
+        0x25,   0x1     cc_subtype
+        0x26,   0x2     reserved
+        0x28,   0x8     log_area_minimum_length
+        0x30,   0x8     log_area_start_address
+*/
+#[derive(Clone)]
+pub struct Ccel {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: u32,
+    pub creator_revision: u32,
+    pub cc_type: u8,
+    pub cc_subtype: u8,
+    pub log_area_minimum_length: u64,
+    pub log_area_start_address: u64,
+}
+
+impl Ccel {
+    /***
+        Parse the CCEL ACPI table and validate its signature, length and checksum.
+
+        Args:
+            data: raw ACPI table, e.g. content of /sys/firmware/acpi/tables/CCEL
+
+        Returns:
+            The Ccel struct or error information
+    */
+    pub fn parse(data: &[u8]) -> Result<Ccel, anyhow::Error> {
+        if data.len() < CCEL_TABLE_LEN {
+            return Err(anyhow!(
+                "[parse] CCEL table too short: {} bytes, expect at least {} bytes",
+                data.len(),
+                CCEL_TABLE_LEN
+            ));
+        }
+
+        if &data[0..4] != CCEL_SIGNATURE {
+            return Err(anyhow!(
+                "[parse] invalid CCEL signature {:02X?}",
+                &data[0..4]
+            ));
+        }
+
+        let length = get_u32(data[4..8].to_vec());
+        if (length as usize) < CCEL_TABLE_LEN || length as usize > data.len() {
+            return Err(anyhow!(
+                "[parse] invalid CCEL table length {}, {} bytes available",
+                length,
+                data.len()
+            ));
+        }
+
+        let sum = data[0..length as usize]
+            .iter()
+            .fold(0_u8, |acc, v| acc.wrapping_add(*v));
+        if sum != 0 {
+            return Err(anyhow!(
+                "[parse] CCEL checksum mismatch, byte sum is 0x{:02X}",
+                sum
+            ));
+        }
+
+        Ok(Ccel {
+            signature: data[0..4].try_into().unwrap(),
+            length,
+            revision: data[8],
+            checksum: data[9],
+            oem_id: data[10..16].try_into().unwrap(),
+            oem_table_id: data[16..24].try_into().unwrap(),
+            oem_revision: get_u32(data[24..28].to_vec()),
+            creator_id: get_u32(data[28..32].to_vec()),
+            creator_revision: get_u32(data[32..36].to_vec()),
+            cc_type: data[36],
+            cc_subtype: data[37],
+            log_area_minimum_length: get_u64(data[40..48].to_vec()),
+            log_area_start_address: get_u64(data[48..56].to_vec()),
+        })
+    }
+
+    pub fn show(&self) {
+        info!("show the data of CCEL ACPI table");
+        info!("Revision:     {}", self.revision);
+        info!("Length:       {}", self.length);
+        info!("Checksum:     {:02X}", self.checksum);
+        info!("OEM ID:       {}", String::from_utf8_lossy(&self.oem_id));
+        info!("CC Type:      {}", self.cc_type);
+        info!("CC Sub-type:  {}", self.cc_subtype);
+        info!("Log Length:   0x{:08X}", self.log_area_minimum_length);
+        info!("Log Address:  0x{:08X}", self.log_area_start_address);
+    }
+}
+
+/***
+    Get the length of the event log actually used within the log area.
+
+    The log area reserved by firmware is usually much larger than the event log,
+    the unused part is filled with 0xFF. The events are walked through according
+    to the digest sizes declared in the Specification ID header event, and the
+    walk stops at the first event with IMR index 0xFFFFFFFF or when the data ends.
+
+    Args:
+        data: raw log area, e.g. content of /sys/firmware/acpi/tables/data/CCEL
+
+    Returns:
+        The length in bytes of the used log area
+*/
+pub fn get_used_log_area_length(data: &[u8]) -> Result<usize, anyhow::Error> {
+    // TCG_PCClientPCREvent: imr(4) + type(4) + digest(20) + event_size(4) + event
+    let header_len = 32;
+    if data.len() < header_len || get_u32(data[0..4].to_vec()) == 0xFFFFFFFF {
+        return Ok(0);
+    }
+    let spec_id_size = get_u32(data[28..32].to_vec()) as usize;
+    let mut index = header_len + spec_id_size;
+    if index > data.len() || spec_id_size < 28 {
+        return Err(anyhow!(
+            "[get_used_log_area_length] truncated Specification ID header event"
+        ));
+    }
+
+    // TCG_EfiSpecIDEventStruct: signature(16) + platform_class(4) + version and
+    // errata(3) + uintn_size(1) + number_of_algorithms(4) + digest_sizes
+    let spec_id = &data[header_len..index];
+    let algo_count = get_u32(spec_id[24..28].to_vec()) as usize;
+    if 28 + algo_count * 4 > spec_id.len() {
+        return Err(anyhow!(
+            "[get_used_log_area_length] truncated digest sizes in Specification ID header event"
+        ));
+    }
+    let digest_sizes: Vec<(u16, usize)> = (0..algo_count)
+        .map(|i| {
+            let pos = 28 + i * 4;
+            (
+                get_u16(spec_id[pos..pos + 2].to_vec()),
+                get_u16(spec_id[pos + 2..pos + 4].to_vec()) as usize,
+            )
+        })
+        .collect();
+
+    // TCG_PCR_EVENT2: imr(4) + type(4) + digest count(4) + digests + event_size(4) + event
+    while index + 12 <= data.len() {
+        let start = index;
+        if get_u32(data[index..index + 4].to_vec()) == 0xFFFFFFFF {
+            break;
+        }
+        let digest_count = get_u32(data[index + 8..index + 12].to_vec());
+        index += 12;
+        for _ in 0..digest_count {
+            if index + 2 > data.len() {
+                return Err(anyhow!(
+                    "[get_used_log_area_length] truncated event at offset {}",
+                    start
+                ));
+            }
+            let algo_id = get_u16(data[index..index + 2].to_vec());
+            let digest_size = match digest_sizes.iter().find(|(id, _)| *id == algo_id) {
+                Some((_, size)) => *size,
+                None => match TcgDigest::get_digest_size_from_algorithm_id(algo_id) {
+                    0 => {
+                        return Err(anyhow!(
+                            "[get_used_log_area_length] unknown algorithm {} at offset {}",
+                            TcgDigest::get_algorithm_name(algo_id),
+                            start
+                        ))
+                    }
+                    size => size as usize,
+                },
+            };
+            index += 2 + digest_size;
+        }
+        if index + 4 > data.len() {
+            return Err(anyhow!(
+                "[get_used_log_area_length] truncated event at offset {}",
+                start
+            ));
+        }
+        index += 4 + get_u32(data[index..index + 4].to_vec()) as usize;
+        if index > data.len() {
+            return Err(anyhow!(
+                "[get_used_log_area_length] truncated event data at offset {}",
+                start
+            ));
+        }
+    }
+
+    Ok(index.min(data.len()))
+}
+
+/***
+    Loader of the CC event log exposed through the CCEL ACPI table.
+
+    Inside a VM, the table and the log area are read from /sys/firmware/acpi/tables/,
+    inside a container they are expected to be mounted under /run/firmware/acpi/tables/.
+    The IMA runtime measurements are loaded from the same environment when available.
+    All paths are resolved under the configured sysroot, which defaults to "/".
+
+    Sample usage:
+        let event_logs = CcelLoader::new().load_event_logs(TCG_PCCLIENT_FORMAT)?;
+*/
+pub struct CcelLoader {
+    sysroot: PathBuf,
+}
+
+impl Default for CcelLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CcelLoader {
+    pub fn new() -> CcelLoader {
+        CcelLoader {
+            sysroot: PathBuf::from("/"),
+        }
+    }
+
+    pub fn with_sysroot<P: AsRef<Path>>(sysroot: P) -> CcelLoader {
+        CcelLoader {
+            sysroot: sysroot.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn sysroot(&self) -> &Path {
+        &self.sysroot
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.sysroot.join(path.trim_start_matches('/'))
+    }
+
+    // pick the (table, log area, ima) paths of the VM or the container environment
+    fn locate(&self) -> Result<(PathBuf, PathBuf, PathBuf), anyhow::Error> {
+        let candidates = [
+            (
+                ACPI_TABLE_FILE_VM,
+                ACPI_TABLE_DATA_FILE_VM,
+                IMA_DATA_FILE_VM,
+            ),
+            (
+                ACPI_TABLE_FILE_CONTAINER,
+                ACPI_TABLE_DATA_FILE_CONTAINER,
+                IMA_DATA_FILE_CONTAINER,
+            ),
+        ];
+        for (table, data, ima) in candidates {
+            if self.resolve(data).exists() {
+                return Ok((self.resolve(table), self.resolve(data), self.resolve(ima)));
+            }
+        }
+        Err(anyhow!(
+            "[locate] no CCEL log area found under {}",
+            self.sysroot.display()
+        ))
+    }
+
+    /***
+        Load and validate the CCEL ACPI table.
+    */
+    pub fn load_table(&self) -> Result<Ccel, anyhow::Error> {
+        let (table_path, _, _) = self.locate()?;
+        match fs::read(&table_path) {
+            Ok(data) => Ccel::parse(&data),
+            Err(e) => Err(anyhow!(
+                "[load_table] failed to read {}: {:?}",
+                table_path.display(),
+                e
+            )),
+        }
+    }
+
+    /***
+        Load the boot time event log from the CCEL log area, with the unused
+        part of the log area trimmed.
+    */
+    pub fn load_boot_time_data(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let (table_path, data_path, _) = self.locate()?;
+        let mut data = match fs::read(&data_path) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(anyhow!(
+                    "[load_boot_time_data] failed to read {}: {:?}",
+                    data_path.display(),
+                    e
+                ))
+            }
+        };
+
+        // the table is optional in container, validate it when provided
+        if table_path.exists() {
+            let ccel = self.load_table()?;
+            if data.len() as u64 > ccel.log_area_minimum_length {
+                data.truncate(ccel.log_area_minimum_length as usize);
+            }
+        }
+
+        let used = get_used_log_area_length(&data)?;
+        data.truncate(used);
+        Ok(data)
+    }
+
+    /***
+        Load the IMA ascii runtime measurements, empty if IMA is not enabled.
+    */
+    pub fn load_run_time_data(&self) -> Result<Vec<String>, anyhow::Error> {
+        let (_, _, ima_path) = self.locate()?;
+        if !ima_path.exists() {
+            return Ok(Vec::new());
+        }
+        match fs::read_to_string(&ima_path) {
+            Ok(data) => Ok(data
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.to_string())
+                .collect()),
+            Err(e) => Err(anyhow!(
+                "[load_run_time_data] failed to read {}: {:?}",
+                ima_path.display(),
+                e
+            )),
+        }
+    }
+
+    /***
+        Load the IMA ascii runtime measurements appended since a byte offset of the
+        list. A record not terminated by a newline yet is left for the next call.
+        Args:
+            offset: number of bytes of the list already loaded
+        Returns:
+            The new records and the offset after them
+    */
+    pub fn load_run_time_data_since(
+        &self,
+        offset: u64,
+    ) -> Result<(Vec<String>, u64), anyhow::Error> {
+        let (_, _, ima_path) = self.locate()?;
+        let records = load_records_since(&ima_path, offset)?;
+        let offset = records.last().map_or(offset, |(_, end)| *end);
+        Ok((records.into_iter().map(|(line, _)| line).collect(), offset))
+    }
+
+    /***
+        Append the IMA records measured since the last poll to the event logs and
+        collect the event logs new to the cursor, see EventLogs::select_since().
+        The records of the bank lists, e.g. ascii_runtime_measurements_sha256, are
+        loaded along, as many from each list as IMA has measured to all of them.
+        The runtime data of the event logs must only be loaded through poll(), a
+        saved cursor is polled with new EventLogs of the boot time data only.
+    */
+    pub fn poll(
+        &self,
+        event_logs: &mut EventLogs,
+        cursor: &mut EventLogCursor,
+    ) -> Result<Vec<EventLogEntry>, anyhow::Error> {
+        if cursor.boot_time_offset == 0 && !event_logs.run_time_data().is_empty() {
+            return Err(anyhow!(
+                "[poll] event logs hold runtime data not loaded by poll"
+            ));
+        }
+        let (_, _, ima_path) = self.locate()?;
+        let mut records = load_records_since(&ima_path, cursor.ima_offset)?;
+        let mut banks = Vec::new();
+        for (algo_id, path) in self.ima_bank_paths()? {
+            let offset = cursor
+                .ima_bank_offsets
+                .iter()
+                .find(|(bank, _)| *bank == algo_id)
+                .map_or(0, |(_, offset)| *offset);
+            banks.push((algo_id, load_records_since(&path, offset)?, offset));
+        }
+        // IMA may measure between the reads of the lists
+        let len = banks
+            .iter()
+            .map(|(_, records, _)| records.len())
+            .fold(records.len(), usize::min);
+        records.truncate(len);
+        for (_, records, _) in banks.iter_mut() {
+            records.truncate(len);
+        }
+
+        let ima_offset = records.last().map_or(cursor.ima_offset, |(_, end)| *end);
+        let ima_bank_offsets: Vec<(u16, u64)> = banks
+            .iter()
+            .map(|(algo_id, records, offset)| {
+                (*algo_id, records.last().map_or(*offset, |(_, end)| *end))
+            })
+            .collect();
+        let bank_data = banks
+            .into_iter()
+            .map(|(algo_id, records, _)| {
+                (algo_id, records.into_iter().map(|(line, _)| line).collect())
+            })
+            .collect();
+        let run_time_data = records.into_iter().map(|(line, _)| line).collect();
+
+        let event_logs = event_logs.select_since(cursor, run_time_data, bank_data)?;
+        cursor.ima_offset = ima_offset;
+        cursor.ima_bank_offsets = ima_bank_offsets;
+        Ok(event_logs)
+    }
+
+    /***
+        Load the IMA binary runtime measurements, binary_runtime_measurements next to
+        the ascii list, empty if IMA is not enabled.
+        Args:
+            algo_id: algorithm of the template hashes of the list
+            byte_order: byte order of the list, see ImaByteOrder
+    */
+    pub fn load_binary_run_time_data(
+        &self,
+        algo_id: u16,
+        byte_order: ImaByteOrder,
+    ) -> Result<Vec<ImaEvent>, anyhow::Error> {
+        let (_, _, ima_path) = self.locate()?;
+        let binary_path = ima_path.with_file_name(IMA_BINARY_DATA_FILE);
+        if !binary_path.exists() {
+            return Ok(Vec::new());
+        }
+        match fs::read(&binary_path) {
+            Ok(data) => ImaEvent::parse_binary_list(&data, algo_id, byte_order),
+            Err(e) => Err(anyhow!(
+                "[load_binary_run_time_data] failed to read {}: {:?}",
+                binary_path.display(),
+                e
+            )),
+        }
+    }
+
+    // paths of the IMA measurement lists of the register banks of known algorithms
+    fn ima_bank_paths(&self) -> Result<Vec<(u16, PathBuf)>, anyhow::Error> {
+        let (_, _, ima_path) = self.locate()?;
+        let ima_dir = match ima_path.parent() {
+            Some(dir) if dir.is_dir() => dir,
+            _ => return Ok(Vec::new()),
+        };
+        let entries = fs::read_dir(ima_dir).map_err(|e| {
+            anyhow!(
+                "[load_ima_banks] failed to read {}: {:?}",
+                ima_dir.display(),
+                e
+            )
+        })?;
+
+        let mut paths = Vec::new();
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(algo_id) = file_name
+                .strip_prefix(IMA_BANK_DATA_FILE_PREFIX)
+                .and_then(ima_algorithm_id)
+            {
+                paths.push((algo_id, entry.path()));
+            }
+        }
+        paths.sort_by_key(|(algo_id, _)| *algo_id);
+        Ok(paths)
+    }
+
+    /***
+        Load the IMA measurement lists of the register banks, e.g.
+        ascii_runtime_measurements_sha256, in algorithm order. Lists of algorithms
+        not known are skipped.
+    */
+    pub fn load_ima_banks(&self) -> Result<Vec<(u16, Vec<String>)>, anyhow::Error> {
+        let mut banks = Vec::new();
+        for (algo_id, path) in self.ima_bank_paths()? {
+            match fs::read_to_string(&path) {
+                Ok(data) => banks.push((
+                    algo_id,
+                    data.lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(|line| line.to_string())
+                        .collect(),
+                )),
+                Err(e) => {
+                    return Err(anyhow!(
+                        "[load_ima_banks] failed to read {}: {:?}",
+                        path.display(),
+                        e
+                    ))
+                }
+            }
+        }
+        Ok(banks)
+    }
+
+    /***
+        Load the boot time and runtime event logs into EventLogs.
+        IMA may measure between the reads of the ascii measurement lists of the
+        banks, the records present in all lists are loaded.
+
+        Args:
+            parse_format: event log format used, e.g. TCG_PCCLIENT_FORMAT
+
+        Returns:
+            The EventLogs ready to be selected and replayed
+    */
+    pub fn load_event_logs(&self, parse_format: u8) -> Result<EventLogs, anyhow::Error> {
+        let boot_time_data = self.load_boot_time_data()?;
+        let mut run_time_data = self.load_run_time_data()?;
+        let banks = self.load_ima_banks()?;
+        let len = banks
+            .iter()
+            .map(|(_, lines)| lines.len())
+            .fold(run_time_data.len(), usize::min);
+        run_time_data.truncate(len);
+        let mut event_logs = EventLogs::new(boot_time_data, run_time_data, parse_format);
+        for (algo_id, mut run_time_data) in banks {
+            run_time_data.truncate(len);
+            event_logs.add_ima_bank(algo_id, run_time_data);
+        }
+        Ok(event_logs)
+    }
+
+    /***
+        Load the boot time event logs and the IMA binary runtime measurements into
+        EventLogs, see load_binary_run_time_data().
+    */
+    pub fn load_event_logs_with_binary_list(
+        &self,
+        parse_format: u8,
+        algo_id: u16,
+        byte_order: ImaByteOrder,
+    ) -> Result<EventLogs, anyhow::Error> {
+        let boot_time_data = self.load_boot_time_data()?;
+        let mut event_logs = EventLogs::new(boot_time_data, Vec::new(), parse_format);
+        event_logs.append_ima_events(&self.load_binary_run_time_data(algo_id, byte_order)?);
+        Ok(event_logs)
+    }
+}
+
+/***
+    Load the records of an IMA ascii measurement list appended since a byte offset.
+    A record not terminated by a newline yet is left for the next call.
+    Returns:
+        The new records, each with the offset after it
+*/
+fn load_records_since(path: &Path, offset: u64) -> Result<Vec<(String, u64)>, anyhow::Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut data = Vec::new();
+    fs::File::open(path)
+        .and_then(|mut file| {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_to_end(&mut data)
+        })
+        .map_err(|e| {
+            anyhow!(
+                "[load_run_time_data_since] failed to read {}: {:?}",
+                path.display(),
+                e
+            )
+        })?;
+
+    let mut records = Vec::new();
+    let mut end = 0;
+    while let Some(position) = data[end..].iter().position(|v| *v == b'\n') {
+        let line = String::from_utf8_lossy(&data[end..end + position]);
+        end += position + 1;
+        let line = line.trim_end_matches('\r');
+        if !line.trim().is_empty() {
+            records.push((line.to_string(), offset + end as u64));
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+pub(crate) mod test_ccel {
+    use super::*;
+    use crate::tcg::TCG_PCCLIENT_FORMAT;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    fn table_data() -> Vec<u8> {
+        fs::read(format!("{}{}", SYSROOT, ACPI_TABLE_FILE_VM)).unwrap()
+    }
+
+    #[test]
+    //parse the fixture CCEL table
+    fn test_ccel_parse() {
+        let ccel = Ccel::parse(&table_data()).unwrap();
+        assert_eq!(ccel.length as usize, CCEL_TABLE_LEN);
+        assert_eq!(ccel.revision, 1);
+        assert_eq!(&ccel.oem_id, b"INTEL ");
+        assert_eq!(ccel.cc_type, 2);
+        assert_eq!(ccel.log_area_minimum_length, 0x10000);
+    }
+
+    #[test]
+    //checksum mismatch is detected
+    fn test_ccel_parse_bad_checksum() {
+        let mut data = table_data();
+        data[9] = data[9].wrapping_add(1);
+        assert!(Ccel::parse(&data).is_err());
+    }
+
+    #[test]
+    //table with wrong signature or truncated table is rejected
+    fn test_ccel_parse_invalid_table() {
+        let mut data = table_data();
+        assert!(Ccel::parse(&data[..40]).is_err());
+        data[0] = b'X';
+        assert!(Ccel::parse(&data).is_err());
+    }
+
+    #[test]
+    //loader trims the unused log area
+    fn test_ccel_loader_trim_log_area() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let boot_time_data = loader.load_boot_time_data().unwrap();
+        assert_eq!(boot_time_data.len(), 15812);
+        assert!(
+            boot_time_data.len() < loader.load_table().unwrap().log_area_minimum_length as usize
+        );
+    }
+
+    #[test]
+    //loader provides event logs from the fixture sysroot
+    fn test_ccel_loader_load_event_logs() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let mut event_logs = loader.load_event_logs(TCG_PCCLIENT_FORMAT).unwrap();
+        assert_eq!(event_logs.run_time_data().len(), 1);
+        let events = event_logs.select(None, None).unwrap();
+        assert!(events.len() > 1);
+    }
+
+    // sysroot with the CCEL of the fixture and an IMA list of the content
+    pub(crate) fn temp_sysroot(name: &str, ima_data: &str) -> PathBuf {
+        let sysroot = std::env::temp_dir().join(format!("ccel-{}-{}", name, std::process::id()));
+        for path in [ACPI_TABLE_FILE_VM, ACPI_TABLE_DATA_FILE_VM] {
+            let target = sysroot.join(path.trim_start_matches('/'));
+            fs::create_dir_all(target.parent().unwrap()).unwrap();
+            fs::copy(format!("{}{}", SYSROOT, path), target).unwrap();
+        }
+        let ima_path = sysroot.join(IMA_DATA_FILE_VM.trim_start_matches('/'));
+        fs::create_dir_all(ima_path.parent().unwrap()).unwrap();
+        fs::write(ima_path, ima_data).unwrap();
+        sysroot
+    }
+
+    #[test]
+    //records measured between the reads of the bank lists are left out
+    fn test_ccel_loader_bank_list_longer() {
+        let ima_file = format!("{}{}", SYSROOT, IMA_DATA_FILE_VM);
+        let record = fs::read_to_string(&ima_file).unwrap();
+        let bank_record = fs::read_to_string(format!("{}_sha256", ima_file)).unwrap();
+        let sysroot = temp_sysroot("banks", &record.repeat(2));
+        let ima_path = sysroot.join(IMA_DATA_FILE_VM.trim_start_matches('/'));
+        fs::write(
+            format!("{}_sha256", ima_path.display()),
+            bank_record.repeat(3),
+        )
+        .unwrap();
+
+        let loader = CcelLoader::with_sysroot(&sysroot);
+        let mut event_logs = loader.load_event_logs(TCG_PCCLIENT_FORMAT).unwrap();
+        assert_eq!(event_logs.run_time_data().len(), 2);
+        let events = event_logs.select(None, None).unwrap();
+        assert_eq!(events.len(), event_logs.count as usize);
+        match events.last().unwrap() {
+            EventLogEntry::TcgImrEvent(event) => assert_eq!(event.digests.len(), 2),
+            _ => panic!("unexpected event log entry"),
+        }
+        fs::remove_dir_all(&sysroot).unwrap();
+    }
+
+    #[test]
+    //loader fails without log area under the sysroot
+    fn test_ccel_loader_no_log_area() {
+        let loader = CcelLoader::with_sysroot(concat!(env!("CARGO_MANIFEST_DIR"), "/src"));
+        assert!(loader.load_boot_time_data().is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use crate::api_data::ReplayResults;
+use hashbrown::HashMap;
+
+/***
+    Resumable position of a consumer of event logs, for agents polling the event
+    logs instead of selecting and replaying them all each time. The cursor does
+    not refer to the event logs it is used with, it can be saved and polled with
+    new EventLogs after a restart. See EventLogs::select_since() and CcelLoader::poll().
+
+    Attributes:
+        boot_time_offset: number of bytes of boot time data consumed
+        ima_offset: number of bytes of the IMA ascii measurement list consumed
+        ima_bank_offsets: number of bytes of the IMA measurement list of each register
+                          bank consumed, e.g. ascii_runtime_measurements_sha256
+        replay_results: replay of the event logs consumed
+        ima_bank: the bank IMA extends its template hashes to, see
+                  EventLogs::replay_with_ima_bank()
+
+    Sample usage:
+        let loader = CcelLoader::new();
+        let mut event_logs = EventLogs::new(loader.load_boot_time_data()?, Vec::new(), TCG_PCCLIENT_FORMAT);
+        let mut cursor = EventLogCursor::with_ima_bank(TPM_ALG_SHA384);
+        loop {
+            let new_events = loader.poll(&mut event_logs, &mut cursor)?;
+            ...
+        }
+*/
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct EventLogCursor {
+    pub boot_time_offset: usize,
+    pub ima_offset: u64,
+    pub ima_bank_offsets: Vec<(u16, u64)>,
+    pub replay_results: ReplayResults,
+    pub ima_bank: Option<u16>,
+    pub(crate) startup_localities: HashMap<u32, u8>,
+}
+
+impl EventLogCursor {
+    pub fn new() -> EventLogCursor {
+        EventLogCursor::default()
+    }
+
+    pub fn with_ima_bank(algo_id: u16) -> EventLogCursor {
+        EventLogCursor {
+            ima_bank: Some(algo_id),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_cursor {
+    use super::*;
+    use crate::ccel::test_ccel::temp_sysroot;
+    use crate::ccel::CcelLoader;
+    use crate::eventlog::{EventLogs, ImrIndexFlavour};
+    use crate::tcg::*;
+    use crate::tdx::common::*;
+    use std::fs;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    #[test]
+    //events are returned once and replayed incrementally
+    fn test_select_since() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let run_time_data = loader.load_run_time_data().unwrap();
+        let mut event_logs = EventLogs::new(
+            loader.load_boot_time_data().unwrap(),
+            Vec::new(),
+            TCG_PCCLIENT_FORMAT,
+        );
+        let mut cursor = EventLogCursor::with_ima_bank(TPM_ALG_SHA384);
+        let boot_events = event_logs
+            .select_since(&mut cursor, Vec::new(), Vec::new())
+            .unwrap();
+        assert_eq!(boot_events.len(), event_logs.count as usize);
+        assert_eq!(cursor.boot_time_offset, event_logs.boot_time_data().len());
+        assert!(event_logs
+            .select_since(&mut cursor, Vec::new(), Vec::new())
+            .unwrap()
+            .is_empty());
+
+        let new_events = event_logs
+            .select_since(&mut cursor, run_time_data.clone(), Vec::new())
+            .unwrap();
+        assert_eq!(new_events.len(), 1);
+        let all_events = event_logs.select(None, None).unwrap();
+        assert_eq!(
+            cursor.replay_results,
+            EventLogs::replay_with_ima_bank(all_events, Some(TPM_ALG_SHA384)).unwrap()
+        );
+
+        // parsing again from the same data keeps the cursor valid
+        event_logs.set_index_flavour(ImrIndexFlavour::TpmPcr);
+        event_logs.set_index_flavour(ImrIndexFlavour::CcMr);
+        assert!(event_logs
+            .select_since(&mut cursor, Vec::new(), Vec::new())
+            .unwrap()
+            .is_empty());
+
+        // neither the cursor nor the event logs change on error
+        let saved = cursor.clone();
+        let count = event_logs.count;
+        assert!(event_logs
+            .select_since(&mut cursor, vec!["10 zz".to_string()], Vec::new())
+            .is_err());
+        assert_eq!(cursor, saved);
+        assert_eq!(event_logs.select(None, None).unwrap().len(), count as usize);
+        let mut other = EventLogs::new(vec![0xff; 8], Vec::new(), TCG_PCCLIENT_FORMAT);
+        assert!(other
+            .select_since(&mut cursor, Vec::new(), Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    //a new cursor collects and replays the runtime data already in the event logs
+    fn test_select_since_run_time_data() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let mut event_logs = loader.load_event_logs(TCG_PCCLIENT_FORMAT).unwrap();
+        let mut cursor = EventLogCursor::with_ima_bank(TPM_ALG_SHA384);
+        let events = event_logs
+            .select_since(&mut cursor, Vec::new(), Vec::new())
+            .unwrap();
+        let all_events = event_logs.select(None, None).unwrap();
+        assert_eq!(events.len(), all_events.len());
+        assert_eq!(
+            cursor.replay_results,
+            EventLogs::replay_with_ima_bank(all_events, Some(TPM_ALG_SHA384)).unwrap()
+        );
+        assert!(event_logs
+            .select_since(&mut cursor, Vec::new(), Vec::new())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    //records of the IMA banks are appended along with the runtime data
+    fn test_select_since_ima_banks() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let mut event_logs = loader.load_event_logs(TCG_PCCLIENT_FORMAT).unwrap();
+        let run_time_data = loader.load_run_time_data().unwrap();
+        let banks = loader.load_ima_banks().unwrap();
+        assert_eq!(banks[0].0, TPM_ALG_SHA256);
+        let mut cursor = EventLogCursor::with_ima_bank(TPM_ALG_SHA384);
+        let count = event_logs
+            .select_since(&mut cursor, Vec::new(), Vec::new())
+            .unwrap()
+            .len();
+
+        // a bank missing its records fails and leaves the event logs as is
+        let saved = cursor.clone();
+        assert!(event_logs
+            .select_since(&mut cursor, run_time_data.clone(), Vec::new())
+            .is_err());
+        assert_eq!(cursor, saved);
+        assert_eq!(event_logs.run_time_data().len(), 1);
+
+        let new_events = event_logs
+            .select_since(&mut cursor, run_time_data, banks)
+            .unwrap();
+        assert_eq!(new_events.len(), 1);
+        match &new_events[0] {
+            EventLogEntry::TcgImrEvent(event) => assert_eq!(event.digests.len(), 2),
+            _ => panic!("unexpected event log entry"),
+        }
+        let all_events = event_logs.select(None, None).unwrap();
+        assert_eq!(all_events.len(), count + 1);
+        assert_eq!(
+            cursor.replay_results,
+            EventLogs::replay_with_ima_bank(all_events, Some(TPM_ALG_SHA384)).unwrap()
+        );
+    }
+
+    fn ima_record() -> String {
+        fs::read_to_string(format!("{}{}", SYSROOT, IMA_DATA_FILE_VM)).unwrap()
+    }
+
+    #[test]
+    //only complete IMA records appended since the last poll are loaded
+    fn test_poll() {
+        let record = ima_record();
+        let sysroot = temp_sysroot("poll", &record[..20]);
+        let ima_path = sysroot.join(IMA_DATA_FILE_VM.trim_start_matches('/'));
+
+        let loader = CcelLoader::with_sysroot(&sysroot);
+        let mut event_logs = EventLogs::new(
+            loader.load_boot_time_data().unwrap(),
+            Vec::new(),
+            TCG_PCCLIENT_FORMAT,
+        );
+        let mut cursor = EventLogCursor::new();
+        let boot_count = loader.poll(&mut event_logs, &mut cursor).unwrap().len();
+        assert_eq!(cursor.ima_offset, 0);
+
+        fs::write(&ima_path, record.repeat(2)).unwrap();
+        let new_events = loader.poll(&mut event_logs, &mut cursor).unwrap();
+        assert_eq!(new_events.len(), 2);
+        assert_eq!(cursor.ima_offset as usize, record.len() * 2);
+        assert!(loader
+            .poll(&mut event_logs, &mut cursor)
+            .unwrap()
+            .is_empty());
+        assert_eq!(event_logs.count as usize, boot_count + 2);
+
+        // the offset is not moved past records failing to parse
+        fs::write(&ima_path, record.repeat(2) + "10 zz\n").unwrap();
+        assert!(loader.poll(&mut event_logs, &mut cursor).is_err());
+        assert_eq!(cursor.ima_offset as usize, record.len() * 2);
+        assert_eq!(event_logs.count as usize, boot_count + 2);
+        fs::remove_dir_all(&sysroot).unwrap();
+    }
+
+    #[test]
+    //bank lists are polled up to the records measured to every list
+    fn test_poll_ima_banks() {
+        let record = ima_record();
+        let bank_record =
+            fs::read_to_string(format!("{}{}_sha256", SYSROOT, IMA_DATA_FILE_VM)).unwrap();
+        let sysroot = temp_sysroot("poll-banks", &record);
+        let ima_path = sysroot.join(IMA_DATA_FILE_VM.trim_start_matches('/'));
+        let bank_path = format!("{}_sha256", ima_path.display());
+        fs::write(&bank_path, bank_record.repeat(2)).unwrap();
+
+        let loader = CcelLoader::with_sysroot(&sysroot);
+        let mut event_logs = EventLogs::new(
+            loader.load_boot_time_data().unwrap(),
+            Vec::new(),
+            TCG_PCCLIENT_FORMAT,
+        );
+        let mut cursor = EventLogCursor::new();
+        let boot_count = loader.poll(&mut event_logs, &mut cursor).unwrap().len() - 1;
+        assert_eq!(cursor.ima_offset as usize, record.len());
+        assert_eq!(
+            cursor.ima_bank_offsets,
+            vec![(TPM_ALG_SHA256, bank_record.len() as u64)]
+        );
+
+        fs::write(&ima_path, record.repeat(3)).unwrap();
+        let new_events = loader.poll(&mut event_logs, &mut cursor).unwrap();
+        assert_eq!(new_events.len(), 1);
+        assert_eq!(cursor.ima_offset as usize, record.len() * 2);
+        assert_eq!(
+            cursor.ima_bank_offsets,
+            vec![(TPM_ALG_SHA256, bank_record.len() as u64 * 2)]
+        );
+        assert_eq!(event_logs.count as usize, boot_count + 2);
+
+        // runtime data not loaded by poll would be loaded twice
+        let mut loaded = loader.load_event_logs(TCG_PCCLIENT_FORMAT).unwrap();
+        assert!(loader
+            .poll(&mut loaded, &mut EventLogCursor::new())
+            .is_err());
+        fs::remove_dir_all(&sysroot).unwrap();
+    }
+
+    #[test]
+    //a saved cursor resumes on new event logs
+    fn test_resume() {
+        let record = ima_record();
+        let sysroot = temp_sysroot("resume", &record);
+        let loader = CcelLoader::with_sysroot(&sysroot);
+        let new_event_logs = || {
+            EventLogs::new(
+                loader.load_boot_time_data().unwrap(),
+                Vec::new(),
+                TCG_PCCLIENT_FORMAT,
+            )
+        };
+        let mut cursor = EventLogCursor::with_ima_bank(TPM_ALG_SHA384);
+        let boot_count = loader
+            .poll(&mut new_event_logs(), &mut cursor)
+            .unwrap()
+            .len()
+            - 1;
+
+        let mut saved = cursor.clone();
+        assert!(loader
+            .poll(&mut new_event_logs(), &mut saved)
+            .unwrap()
+            .is_empty());
+        let ima_path = sysroot.join(IMA_DATA_FILE_VM.trim_start_matches('/'));
+        fs::write(&ima_path, record.repeat(3)).unwrap();
+        let mut event_logs = new_event_logs();
+        assert_eq!(loader.poll(&mut event_logs, &mut saved).unwrap().len(), 2);
+        assert_eq!(event_logs.count as usize, boot_count + 2);
+
+        let all_events = loader
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap()
+            .select(None, None)
+            .unwrap();
+        assert_eq!(all_events.len(), boot_count + 3);
+        assert_eq!(
+            saved.replay_results,
+            EventLogs::replay_with_ima_bank(all_events, Some(TPM_ALG_SHA384)).unwrap()
+        );
+        fs::remove_dir_all(&sysroot).unwrap();
+    }
+}
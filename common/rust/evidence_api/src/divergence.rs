@@ -0,0 +1,304 @@
+use crate::algorithm::get_algorithm;
+use crate::api_data::ReplayResults;
+use crate::event_digest::digest_input;
+use crate::eventlog::EventLogs;
+use crate::mr_index::{CcMrIndex, CC_MR_COUNT};
+use crate::tcg::*;
+use crate::tdx::quote::TdxQuoteBody;
+use hashbrown::HashMap;
+use log::info;
+
+/***
+    How the events logged to an IMR bank diverge from the register.
+        Consistent: the replay of all the events matches the register
+        ExtraEvents: the register matches the replay up to last_consistent, the events
+                     from first_divergent on were logged but not extended to the register
+        AlteredEvent: the register matches no replay of the log, and the digest of the
+                      event first_divergent is not the hash of its event data
+        MissingEvents: the register matches no replay of the log, it was extended with
+                       events missing from the log, e.g. after its last event
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DivergenceKind {
+    Consistent,
+    ExtraEvents,
+    AlteredEvent,
+    MissingEvents,
+}
+
+/***
+    Divergence diagnostic of an IMR bank.
+        event_count: number of events logged to the bank
+        last_consistent: index in the event logs of the last event the register is
+                         consistent with, None if it is consistent with no event
+        first_divergent: index in the event logs of the first event after which the
+                         register can no longer match, None if no event can be blamed
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ImrDivergence {
+    pub imr_index: u32,
+    pub algo_id: u16,
+    pub kind: DivergenceKind,
+    pub event_count: usize,
+    pub last_consistent: Option<usize>,
+    pub first_divergent: Option<usize>,
+}
+
+impl ImrDivergence {
+    pub fn is_consistent(&self) -> bool {
+        self.kind == DivergenceKind::Consistent
+    }
+
+    pub fn show(&self) {
+        let event = |index: Option<usize>| match index {
+            Some(index) => format!("event {}", index),
+            None => "none".to_string(),
+        };
+        info!(
+            "IMR[{}] {}: {:?} ({} events), last consistent: {}, first divergent: {}",
+            self.imr_index,
+            TcgDigest::get_algorithm_name(self.algo_id),
+            self.kind,
+            self.event_count,
+            event(self.last_consistent),
+            event(self.first_divergent)
+        );
+    }
+}
+
+// the digest of the bank is not the hash of the event data
+fn is_altered(event: &TcgImrEvent, algo_id: u16) -> bool {
+    let (input, algorithm) = match (digest_input(event), get_algorithm(algo_id)) {
+        (Some(input), Some(algorithm)) => (input, algorithm),
+        _ => return false,
+    };
+    event
+        .digests
+        .iter()
+        .any(|digest| digest.algo_id == algo_id && digest.hash != algorithm.hash(&input))
+}
+
+/***
+    Walk the events logged to an IMR bank and find where they stop agreeing with the
+    register, see DivergenceKind.
+    Args:
+        event_logs: event logs keyed as the register, e.g. the RTMR index for CCEL
+        imr_index: index of the register in the event logs
+        register: value of the register, its algorithm selects the bank
+    Returns:
+        The diagnostic of the bank
+*/
+pub fn diagnose_divergence(
+    event_logs: &[EventLogEntry],
+    imr_index: u32,
+    register: &TcgDigest,
+) -> Result<ImrDivergence, anyhow::Error> {
+    let algo_id = register.algo_id;
+    let mut replay_results = ReplayResults::default();
+    let mut startup_localities = HashMap::new();
+    let mut events: Vec<(usize, &TcgImrEvent)> = Vec::new();
+    // an initial value of zeros is consistent with no event
+    let mut last_match = register.hash.iter().all(|v| *v == 0).then_some(None);
+
+    for (event_index, event) in event_logs.iter().enumerate() {
+        let event = match event {
+            EventLogEntry::TcgImrEvent(event) if event.imr_index == imr_index => event,
+            _ => continue,
+        };
+        // only the bank of the register is replayed, IMA may pad another bank into it
+        let mut bank_event = event.clone();
+        if event.event_type != IMA_MEASUREMENT_EVENT {
+            bank_event
+                .digests
+                .retain(|digest| digest.algo_id == algo_id);
+        }
+        EventLogs::replay_event(
+            &mut replay_results,
+            &mut startup_localities,
+            &bank_event,
+            Some(algo_id),
+        )?;
+        if event.event_type == EV_NO_ACTION || bank_event.digests.is_empty() {
+            continue;
+        }
+        events.push((event_index, event));
+        if replay_results.get(imr_index, algo_id) == Some(register.hash.as_slice()) {
+            last_match = Some(Some(event_index));
+        }
+    }
+
+    let last_event = events.last().map(|(index, _)| *index);
+    let (kind, last_consistent, first_divergent) = match last_match {
+        Some(index) if index == last_event => (DivergenceKind::Consistent, index, None),
+        Some(index) => (
+            DivergenceKind::ExtraEvents,
+            index,
+            events
+                .iter()
+                .map(|(event_index, _)| *event_index)
+                .find(|event_index| Some(*event_index) > index),
+        ),
+        None => match events
+            .iter()
+            .position(|(_, event)| is_altered(event, algo_id))
+        {
+            Some(position) => (
+                DivergenceKind::AlteredEvent,
+                position.checked_sub(1).map(|v| events[v].0),
+                Some(events[position].0),
+            ),
+            None => (DivergenceKind::MissingEvents, last_event, None),
+        },
+    };
+
+    Ok(ImrDivergence {
+        imr_index,
+        algo_id,
+        kind,
+        event_count: events.len(),
+        last_consistent,
+        first_divergent,
+    })
+}
+
+/***
+    Diagnose the divergence of event logs keyed by RTMR index, e.g. CCEL, from RTMR0
+    to RTMR3 of a TD quote.
+    Returns:
+        A diagnostic per RTMR, in RTMR index order
+*/
+pub fn diagnose_quote_divergence(
+    event_logs: &[EventLogEntry],
+    body: &TdxQuoteBody,
+) -> Result<Vec<ImrDivergence>, anyhow::Error> {
+    let mut divergences = Vec::new();
+    for index in (1..CC_MR_COUNT).map(CcMrIndex) {
+        divergences.push(diagnose_divergence(
+            event_logs,
+            index.rtmr_index()? as u32,
+            &body.cc_measurement(index)?,
+        )?);
+    }
+    Ok(divergences)
+}
+
+#[cfg(test)]
+mod test_divergence {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::replay::test_replay::quote_body;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    fn action_event(action: &str) -> EventLogEntry {
+        let data = action.as_bytes().to_vec();
+        EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index: 0,
+            event_type: EV_EFI_ACTION,
+            digests: vec![TcgDigest {
+                algo_id: TPM_ALG_SHA384,
+                hash: get_algorithm(TPM_ALG_SHA384).unwrap().hash(&data),
+            }],
+            event_size: data.len() as u32,
+            event: data,
+        })
+    }
+
+    fn register(event_logs: Vec<EventLogEntry>) -> TcgDigest {
+        TcgDigest {
+            algo_id: TPM_ALG_SHA384,
+            hash: EventLogs::replay(event_logs)
+                .unwrap()
+                .get(0, TPM_ALG_SHA384)
+                .unwrap()
+                .to_vec(),
+        }
+    }
+
+    #[test]
+    //extra, altered and missing events are located
+    fn test_divergence_kinds() {
+        let mut event_logs = vec![
+            action_event("first"),
+            action_event("second"),
+            action_event("third"),
+        ];
+        let full = register(event_logs.clone());
+        let divergence = diagnose_divergence(&event_logs, 0, &full).unwrap();
+        assert!(divergence.is_consistent());
+        assert_eq!(divergence.last_consistent, Some(2));
+
+        let prefix = register(event_logs[..1].to_vec());
+        let divergence = diagnose_divergence(&event_logs, 0, &prefix).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::ExtraEvents);
+        assert_eq!(
+            (divergence.last_consistent, divergence.first_divergent),
+            (Some(0), Some(1))
+        );
+
+        let mut extended = event_logs.clone();
+        extended.push(action_event("fourth"));
+        let divergence = diagnose_divergence(&event_logs, 0, &register(extended)).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::MissingEvents);
+        assert_eq!(
+            (divergence.last_consistent, divergence.first_divergent),
+            (Some(2), None)
+        );
+
+        if let EventLogEntry::TcgImrEvent(event) = &mut event_logs[1] {
+            event.digests[0].hash[0] ^= 0xff;
+        }
+        let divergence = diagnose_divergence(&event_logs, 0, &full).unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::AlteredEvent);
+        assert_eq!(
+            (divergence.last_consistent, divergence.first_divergent),
+            (Some(0), Some(1))
+        );
+        assert_eq!(divergence.event_count, 3);
+    }
+
+    #[test]
+    //CCEL diagnosed against the RTMRs of a quote
+    fn test_quote_divergence() {
+        let event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap()
+            .select(None, None)
+            .unwrap();
+        let replay = |event_logs: Vec<EventLogEntry>, imr_index: u32| -> [u8; 48] {
+            EventLogs::replay(event_logs)
+                .unwrap()
+                .get(imr_index, TPM_ALG_SHA384)
+                .unwrap()
+                .try_into()
+                .unwrap()
+        };
+        // RTMR1 is quoted before the last event of IMR 1
+        let last_imr1 = event_logs
+            .iter()
+            .rposition(
+                |event| matches!(event, EventLogEntry::TcgImrEvent(event) if event.imr_index == 1),
+            )
+            .unwrap();
+        let body = quote_body([
+            replay(event_logs.clone(), 0),
+            replay(event_logs[..last_imr1].to_vec(), 1),
+            [1; 48],
+            [0; 48],
+        ]);
+
+        let divergences = diagnose_quote_divergence(&event_logs, &body).unwrap();
+        let kinds: Vec<DivergenceKind> = divergences.iter().map(|v| v.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DivergenceKind::Consistent,
+                DivergenceKind::ExtraEvents,
+                DivergenceKind::MissingEvents,
+                DivergenceKind::Consistent
+            ]
+        );
+        assert_eq!(divergences[1].first_divergent, Some(last_imr1));
+        assert_eq!(divergences[3].event_count, 0);
+    }
+}
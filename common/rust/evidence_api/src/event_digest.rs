@@ -0,0 +1,280 @@
+use crate::algorithm::get_algorithm;
+use crate::boot_summary::{GRUB_CMD_PREFIX, GRUB_KERNEL_CMDLINE_PREFIX};
+use crate::eventlog::EventLogs;
+use crate::ima::ImaEvent;
+use crate::tcg::*;
+use crate::uefi::variable::UefiVariableData;
+use log::info;
+
+/***
+    Result of recomputing one digest of an event.
+        Match: the digest is the hash of the event data
+        Mismatch: the digest differs from the hash of the event data, which is kept
+        UnsupportedAlgorithm: the algorithm is not registered in crate::algorithm
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DigestCheckResult {
+    Match,
+    Mismatch { computed: Vec<u8> },
+    UnsupportedAlgorithm,
+}
+
+/***
+    Check of one bank of an event whose digest is a hash of the event data.
+        event_index: index of the event in the event logs
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EventDigestCheck {
+    pub event_index: usize,
+    pub imr_index: u32,
+    pub event_type: u32,
+    pub algo_id: u16,
+    pub result: DigestCheckResult,
+}
+
+impl EventDigestCheck {
+    pub fn is_match(&self) -> bool {
+        self.result == DigestCheckResult::Match
+    }
+
+    pub fn show(&self) {
+        let result = match &self.result {
+            DigestCheckResult::Match => "match".to_string(),
+            DigestCheckResult::Mismatch { computed } => {
+                format!("MISMATCH, event data hashes to {}", hex::encode(computed))
+            }
+            DigestCheckResult::UnsupportedAlgorithm => "unsupported algorithm".to_string(),
+        };
+        info!(
+            "Event {} IMR[{}] {} {}: {}",
+            self.event_index,
+            self.imr_index,
+            TcgEventType::get_event_type_string(self.event_type),
+            TcgDigest::get_algorithm_name(self.algo_id),
+            result
+        );
+    }
+}
+
+/***
+    The data hashed into the digests of the event, for event types whose digest is
+    defined as a hash of the event data.
+    Returns:
+        The hashed data, None if the digest is not a hash of the event data, e.g. the
+        digest of an EFI application is its Authenticode hash. None for IMA events as
+        their template name is not kept in the event, see ima_digest_input()
+*/
+pub fn digest_input(event: &TcgImrEvent) -> Option<Vec<u8>> {
+    match event.event_type {
+        EV_SEPARATOR
+        | EV_ACTION
+        | EV_EFI_ACTION
+        | EV_S_CRTM_VERSION
+        | EV_OMIT_BOOT_DEVICE_EVENTS
+        | EV_EFI_VARIABLE_DRIVER_CONFIG
+        | EV_EFI_VARIABLE_BOOT2
+        | EV_EFI_VARIABLE_AUTHORITY
+        | EV_EFI_GPT_EVENT
+        | EV_EFI_GPT_EVENT2 => Some(event.event.clone()),
+        // only the variable data is hashed for EV_EFI_VARIABLE_BOOT
+        EV_EFI_VARIABLE_BOOT => UefiVariableData::parse(&event.event)
+            .ok()
+            .map(|variable| variable.variable_data),
+        // grub hashes the command or command line without the prefix and the NUL
+        EV_IPL => {
+            let text = std::str::from_utf8(&event.event).ok()?;
+            let text = text.strip_suffix('\0').unwrap_or(text);
+            text.strip_prefix(GRUB_CMD_PREFIX)
+                .or_else(|| text.strip_prefix(GRUB_KERNEL_CMDLINE_PREFIX))
+                .map(|v| v.as_bytes().to_vec())
+        }
+        _ => None,
+    }
+}
+
+// template data hashed into the template hash, None for violations as they are
+// extended as 0xFF instead of their template hash of zeros
+fn ima_digest_input(ima_event: &ImaEvent) -> Option<Vec<u8>> {
+    match ima_event.is_violation() {
+        true => None,
+        false => Some(ima_event.template_data()),
+    }
+}
+
+/***
+    Recompute the digests of the events whose digest is a hash of the event data.
+    Events of other types are not checked, nor are IMA events as the template data
+    cannot be rebuilt without the template name, see EventLogs::check_event_digests().
+    Args:
+        event_logs: event logs in TCG_PCCLIENT_FORMAT, e.g. from EventLogs::select()
+    Returns:
+        A check per bank of each checked event
+*/
+pub fn check_event_digests(event_logs: &[EventLogEntry]) -> Vec<EventDigestCheck> {
+    check_digests(event_logs, &[])
+}
+
+// check the IMA events against the IMA records they are parsed from, in order
+fn check_digests(event_logs: &[EventLogEntry], ima_events: &[ImaEvent]) -> Vec<EventDigestCheck> {
+    let mut checks = Vec::new();
+    let mut ima_events = ima_events.iter();
+    for (event_index, event) in event_logs.iter().enumerate() {
+        let event = match event {
+            EventLogEntry::TcgImrEvent(event) => event,
+            _ => continue,
+        };
+        let input = match event.event_type {
+            IMA_MEASUREMENT_EVENT => ima_events.next().and_then(ima_digest_input),
+            _ => digest_input(event),
+        };
+        let input = match input {
+            Some(input) => input,
+            None => continue,
+        };
+        for digest in &event.digests {
+            let result = match get_algorithm(digest.algo_id) {
+                Some(algorithm) => {
+                    let computed = algorithm.hash(&input);
+                    if computed == digest.hash {
+                        DigestCheckResult::Match
+                    } else {
+                        DigestCheckResult::Mismatch { computed }
+                    }
+                }
+                None => DigestCheckResult::UnsupportedAlgorithm,
+            };
+            checks.push(EventDigestCheck {
+                event_index,
+                imr_index: event.imr_index,
+                event_type: event.event_type,
+                algo_id: digest.algo_id,
+                result,
+            });
+        }
+    }
+    checks
+}
+
+impl EventLogs {
+    /***
+        Recompute the digests of the event logs from the event data, see
+        check_event_digests(). The IMA events are also checked, each bank against the
+        template data of the template of its record as ImaEvent::check_template_hash(),
+        except for violations. Unparsed data is parsed before the check.
+    */
+    pub fn check_event_digests(&mut self) -> Result<Vec<EventDigestCheck>, anyhow::Error> {
+        let event_logs = self.select(None, None)?;
+        Ok(check_digests(&event_logs, &self.ima_events()?))
+    }
+}
+
+#[cfg(test)]
+mod test_event_digest {
+    use super::*;
+    use crate::ccel::CcelLoader;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    #[test]
+    //digests of the TDVF, grub and IMA events match their event data
+    fn test_tdvf_event_digests() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let checks = event_logs.check_event_digests().unwrap();
+        assert!(checks.iter().all(|check| check.is_match()));
+        for event_type in [
+            EV_SEPARATOR,
+            EV_EFI_ACTION,
+            EV_EFI_VARIABLE_BOOT,
+            EV_EFI_VARIABLE_AUTHORITY,
+            EV_IPL,
+            IMA_MEASUREMENT_EVENT,
+        ] {
+            assert!(checks.iter().any(|check| check.event_type == event_type));
+        }
+        assert!(!checks
+            .iter()
+            .any(|check| check.event_type == EV_EFI_BOOT_SERVICES_APPLICATION));
+    }
+
+    #[test]
+    //tampered event data is flagged per bank
+    fn test_event_digest_mismatch() {
+        let action = b"Calling EFI Application from Boot Option".to_vec();
+        let sha256 = get_algorithm(TPM_ALG_SHA256).unwrap().hash(&action);
+        let event = EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index: 1,
+            event_type: EV_EFI_ACTION,
+            digests: vec![
+                TcgDigest {
+                    algo_id: TPM_ALG_SHA256,
+                    hash: sha256,
+                },
+                TcgDigest {
+                    algo_id: TPM_ALG_SHA384,
+                    hash: vec![0; 48],
+                },
+                TcgDigest {
+                    algo_id: 0x0001,
+                    hash: vec![0; 32],
+                },
+            ],
+            event_size: action.len() as u32,
+            event: action.clone(),
+        });
+        let checks = check_event_digests(&[event]);
+        assert_eq!(checks.len(), 3);
+        assert!(checks[0].is_match());
+        assert_eq!(
+            checks[1].result,
+            DigestCheckResult::Mismatch {
+                computed: get_algorithm(TPM_ALG_SHA384).unwrap().hash(&action)
+            }
+        );
+        assert_eq!(checks[2].result, DigestCheckResult::UnsupportedAlgorithm);
+    }
+
+    #[test]
+    //IMA events are checked with their template, violations are skipped
+    fn test_ima_event_digests() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let mut event_logs = EventLogs::new(
+            loader.load_boot_time_data().unwrap(),
+            loader.load_run_time_data().unwrap(),
+            TCG_PCCLIENT_FORMAT,
+        );
+        let mut signed = ImaEvent::parse(&format!(
+            "10 {} ima-sig sha256:{} /usr/bin/kmod 030204",
+            "00".repeat(32),
+            "ab".repeat(32)
+        ))
+        .unwrap();
+        signed.template_hash = get_algorithm(TPM_ALG_SHA256)
+            .unwrap()
+            .hash(&signed.template_data());
+        assert!(signed.check_template_hash().unwrap());
+        let violation = format!(
+            "10 {} ima-ng sha256:{} /var/log/audit.log",
+            "00".repeat(32),
+            "00".repeat(32)
+        );
+        event_logs.append_run_time_data(vec![signed.ascii_record(), violation]);
+
+        let event_count = event_logs.select(None, None).unwrap().len();
+        let checks = event_logs.check_event_digests().unwrap();
+        assert!(checks.iter().all(|check| check.is_match()));
+        let ima_checks: Vec<&EventDigestCheck> = checks
+            .iter()
+            .filter(|check| check.event_type == IMA_MEASUREMENT_EVENT)
+            .collect();
+        assert_eq!(ima_checks.len(), 2);
+        assert_eq!(ima_checks[1].event_index, event_count - 2);
+        assert_eq!(ima_checks[1].algo_id, TPM_ALG_SHA256);
+
+        let event_logs = event_logs.select(None, None).unwrap();
+        assert!(!check_event_digests(&event_logs)
+            .iter()
+            .any(|check| check.event_type == IMA_MEASUREMENT_EVENT));
+    }
+}
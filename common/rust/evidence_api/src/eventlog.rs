@@ -1,12 +1,17 @@
-use crate::api_data::ReplayResult;
+use crate::algorithm::get_algorithm;
+use crate::api_data::{ReplayResult, ReplayResults};
 use crate::binary_blob::*;
+use crate::cursor::EventLogCursor;
+use crate::ima::{ima_extend_value, ImaEvent};
+use crate::mr_index::CcMrIndex;
+use crate::no_action::*;
 use crate::tcg::*;
+use crate::tcgcel::*;
+use crate::uefi::check_len;
 use anyhow::anyhow;
 use hashbrown::HashMap;
-use hex;
 use log::info;
-use sha1::Sha1;
-use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::btree_map::Entry;
 
 /***
 *  This is the common struct for tcg event logs to be delivered in different formats.
@@ -31,6 +36,30 @@ pub const TCG_FORMAT_CEL_TLV: u32 = 2;
 pub const TCG_FORMAT_CEL_JSON: u32 = 3;
 pub const TCG_FORMAT_CEL_CBOR: u32 = 4;
 
+/***
+    Flavour of the register index carried by the boot time event logs.
+
+    CcMr: CC measurement register index used by CCEL, 0 is MRTD and 1..4 are the
+          RTMRs. Event logs keep the RTMR index, i.e. the CC MR index minus 1.
+    TpmPcr: 0 based TPM PCR index used by TPM event logs, kept as it is.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImrIndexFlavour {
+    CcMr,
+    TpmPcr,
+}
+
+// bytes of the event log data at the index, error rather than panic on truncated data
+fn read_bytes<'a>(
+    data: &'a [u8],
+    index: usize,
+    len: usize,
+    name: &str,
+) -> Result<&'a [u8], anyhow::Error> {
+    check_len(data, index, len, name)?;
+    Ok(&data[index..index + len])
+}
+
 #[derive(Clone)]
 pub struct TcgEventLog {
     pub rec_num: u32,
@@ -43,11 +72,26 @@ pub struct TcgEventLog {
 }
 
 impl TcgEventLog {
-    fn format_event_log(&self, parse_format: u8) -> EventLogEntry {
+    /***
+        Convert the event log to the entry of the format.
+        Args:
+            parse_format: TCG_PCCLIENT_FORMAT or TCG_CANONICAL_FORMAT
+            seq_num: position of the event in the whole event log, the CEL record number
+            ima_event: the IMA event the event log is converted from, if any
+    */
+    fn format_event_log(
+        &self,
+        parse_format: u8,
+        seq_num: u32,
+        ima_event: Option<&ImaEvent>,
+    ) -> Result<EventLogEntry, anyhow::Error> {
         match parse_format {
-            TCG_PCCLIENT_FORMAT => self.to_tcg_pcclient_format(),
-            TCG_CANONICAL_FORMAT => self.to_tcg_canonical_format(),
-            0_u8 | 3_u8..=u8::MAX => todo!(),
+            TCG_PCCLIENT_FORMAT => Ok(self.to_tcg_pcclient_format()),
+            TCG_CANONICAL_FORMAT => Ok(self.to_tcg_canonical_format(seq_num, ima_event)),
+            _ => Err(anyhow!(
+                "[format_event_log] unsupported event log format {}",
+                parse_format
+            )),
         }
     }
 
@@ -71,8 +115,39 @@ impl TcgEventLog {
         })
     }
 
-    fn to_tcg_canonical_format(&self) -> EventLogEntry {
-        todo!()
+    /***
+        Convert the event log to a TCG canonical event log (CEL) record. IMA events
+        have IMA_TEMPLATE content with the template name and the binary template
+        data hashed into the template hash, the other events have PCCLIENT_STD content
+        with their event type and event data. The record keeps the IMR index of the
+        event log.
+    */
+    fn to_tcg_canonical_format(&self, seq_num: u32, ima_event: Option<&ImaEvent>) -> EventLogEntry {
+        let (content_type, content) = match ima_event {
+            Some(ima_event) => (
+                TcgCelTypes::CEL_IMA_TEMPLATE,
+                TcgTpmuEventContent::TcgTpmsEventImaTemplate(TcgTpmsEventImaTemplate::new(
+                    ima_event.template_data(),
+                    ima_event.template_name.clone(),
+                )),
+            ),
+            None => (
+                TcgCelTypes::CEL_PCCLIENT_STD,
+                TcgTpmuEventContent::TcgTpmsEventPcClientStd(TcgTpmsEventPcClientStd::new(
+                    self.event_type as i32,
+                    self.event.clone(),
+                )),
+            ),
+        };
+
+        EventLogEntry::TcgTpmsCelEvent(TcgTpmsCelEvent::new(
+            seq_num as i32,
+            self.digests.clone(),
+            Some(content_type),
+            Some(self.imr_index as i32),
+            None,
+            Some(content),
+        ))
     }
 
     pub fn show(&self) {
@@ -96,22 +171,45 @@ impl TcgEventLog {
     EventLogs struct.
     This struct contains the all event logs available on the system.
 
+    The raw data is parsed only once, the first time the event logs are selected.
+    Runtime data appended afterwards with append_run_time_data() is parsed
+    incrementally on the next selection, so repeated selections always return the
+    same events with the same record numbers. Once parsed, the struct can be shared
+    across threads (e.g. within an Arc) and read with select_parsed().
+
     Attributes:
-        boot_time_data: raw data containing all boot time event logs
-        runtime_data: raw data containing runtime event logs(now IMA events)
+        boot_time_data: raw data containing all boot time event logs, see
+            set_boot_time_data()
+        run_time_data: raw data containing runtime event logs(now IMA events), see
+            append_run_time_data(), set_run_time_data() and append_ima_events()
+        run_time_events: IMA events appended as parsed, e.g. from a binary measurement
+            list, at the position of their ascii record in run_time_data, as the ascii
+            record loses the byte order of the template data and may be ambiguous,
+            e.g. for a path ending with a newline
         event_logs: all parsed event logs
         count: total number of event logs
         parse_format: event log format used
+        index_flavour: flavour of the index in boot time data, CcMr by default
+        ima_banks: IMA measurement lists of other register banks, see add_ima_bank()
+        boot_time_count, boot_time_length: number of event logs and of bytes parsed
+            from the boot time data, see select_since()
 */
 #[derive(Clone)]
 pub struct EventLogs {
     pub spec_id_header_event: TcgEfiSpecIdEvent,
-    pub boot_time_data: Vec<u8>,
-    pub run_time_data: Vec<String>,
+    boot_time_data: Vec<u8>,
+    run_time_data: Vec<String>,
+    run_time_events: Vec<Option<Box<ImaEvent>>>,
     pub event_logs: Vec<EventLogEntry>,
     pub count: u32,
     pub parse_format: u8,
     pub event_logs_record_number_list: [u32; 24],
+    boot_time_parsed: bool,
+    run_time_parsed: usize,
+    index_flavour: ImrIndexFlavour,
+    ima_banks: Vec<(u16, Vec<String>)>,
+    boot_time_count: usize,
+    boot_time_length: usize,
 }
 
 impl EventLogs {
@@ -119,16 +217,101 @@ impl EventLogs {
         EventLogs {
             spec_id_header_event: TcgEfiSpecIdEvent::new(),
             boot_time_data,
+            run_time_events: vec![None; run_time_data.len()],
             run_time_data,
             event_logs: Vec::new(),
             count: 0,
             parse_format,
             event_logs_record_number_list: [0; 24],
+            boot_time_parsed: false,
+            run_time_parsed: 0,
+            index_flavour: ImrIndexFlavour::CcMr,
+            ima_banks: Vec::new(),
+            boot_time_count: 0,
+            boot_time_length: 0,
+        }
+    }
+
+    pub fn boot_time_data(&self) -> &[u8] {
+        &self.boot_time_data
+    }
+
+    // replace the boot time data, the event logs are parsed again on next selection
+    pub fn set_boot_time_data(&mut self, boot_time_data: Vec<u8>) {
+        self.boot_time_data = boot_time_data;
+        self.reset();
+    }
+
+    // runtime data as ascii records, see ImaEvent::ascii_record() for IMA events
+    pub fn run_time_data(&self) -> &[String] {
+        &self.run_time_data
+    }
+
+    // IMA event of the runtime record, as appended or parsed from its ascii record
+    pub(crate) fn run_time_ima_event(&self, index: usize) -> Result<ImaEvent, anyhow::Error> {
+        match &self.run_time_events[index] {
+            Some(event) => Ok(event.as_ref().clone()),
+            None => ImaEvent::parse(&self.run_time_data[index]),
+        }
+    }
+
+    // the IMA event of the runtime record and its event data, the template fields
+    fn parse_run_time_record(&self, index: usize) -> Result<(ImaEvent, Vec<u8>), anyhow::Error> {
+        match &self.run_time_events[index] {
+            Some(event) => Ok((event.as_ref().clone(), event.ascii_fields().into_bytes())),
+            None => {
+                let line = &self.run_time_data[index];
+                let fields = line
+                    .trim_start_matches(' ')
+                    .trim_end_matches(['\r', '\n'])
+                    .splitn(4, ' ')
+                    .nth(3)
+                    .unwrap_or_default();
+                Ok((ImaEvent::parse(line)?, fields.as_bytes().to_vec()))
+            }
+        }
+    }
+
+    pub(crate) fn append_run_time_events(&mut self, events: &[ImaEvent]) {
+        for event in events {
+            self.run_time_data.push(event.ascii_record());
+            self.run_time_events.push(Some(Box::new(event.clone())));
+        }
+    }
+
+    fn truncate_run_time_data(&mut self, len: usize) {
+        self.run_time_data.truncate(len);
+        self.run_time_events.truncate(len);
+    }
+
+    /***
+        Replace the runtime data, the event logs are parsed again on next selection.
+        Use append_run_time_data() to add runtime data parsed incrementally.
+    */
+    pub fn set_run_time_data(&mut self, run_time_data: Vec<String>) {
+        self.run_time_events = vec![None; run_time_data.len()];
+        self.run_time_data = run_time_data;
+        self.reset();
+    }
+
+    pub fn index_flavour(&self) -> ImrIndexFlavour {
+        self.index_flavour
+    }
+
+    /***
+        Set the flavour of the index in boot time data.
+        Event logs already parsed with another flavour are parsed again on next selection.
+    */
+    pub fn set_index_flavour(&mut self, index_flavour: ImrIndexFlavour) {
+        if self.index_flavour != index_flavour {
+            self.index_flavour = index_flavour;
+            self.reset();
         }
     }
 
     /***
         Collect selected event logs according to user input.
+        Unparsed data is parsed before the selection.
         Args:
             start: index of the first event log to collect, 0 stands for the first event log
             count: total number of event logs to collect
@@ -145,6 +328,22 @@ impl EventLogs {
             }
         }
 
+        self.select_parsed(start, count)
+    }
+
+    /***
+        Collect selected event logs from the already parsed event logs.
+        Data not parsed yet, e.g. runtime data appended after last parse(), is not
+        taken into account.
+        Args:
+            start: index of the first event log to collect, 0 stands for the first event log
+            count: total number of event logs to collect
+    */
+    pub fn select_parsed(
+        &self,
+        start: Option<u32>,
+        count: Option<u32>,
+    ) -> Result<Vec<EventLogEntry>, anyhow::Error> {
         let begin = match start {
             Some(s) => {
                 if s > self.count {
@@ -164,16 +363,195 @@ impl EventLogs {
                     return Err(anyhow!(
                         "[select] Invalid input count. count must be number larger than 0!"
                     ));
-                } else if c + begin > self.count {
+                } else if c as u64 + begin as u64 > self.count as u64 {
                     self.event_logs.len()
                 } else {
-                    (c + begin).try_into().unwrap()
+                    (c + begin) as usize
                 }
             }
             None => self.event_logs.len(),
         };
 
-        Ok((self.event_logs[begin as usize..end as usize]).to_vec())
+        Ok((self.event_logs[begin as usize..end]).to_vec())
+    }
+
+    /***
+        Append runtime event logs, e.g. IMA events measured after last selection.
+        The appended data is parsed incrementally on next parse() or select().
+        Args:
+            run_time_data: runtime event log lines to be appended
+    */
+    pub fn append_run_time_data(&mut self, run_time_data: Vec<String>) {
+        self.run_time_events
+            .resize(self.run_time_events.len() + run_time_data.len(), None);
+        self.run_time_data.extend(run_time_data);
+    }
+
+    /***
+        Append the runtime data measured since the last call with the cursor, collect
+        the event logs new to the cursor and extend its replay state with them. The
+        position is kept as an offset in the boot time data and the runtime data is
+        passed in by the caller, so a saved cursor resumes on new EventLogs of the
+        same boot time data without the runtime data already consumed. Record numbers
+        of the runtime events start over on such EventLogs.
+        A new cursor collects all the event logs, including the runtime data already
+        in the event logs, a cursor already used collects the appended data only.
+        Args:
+            cursor: position of the caller, EventLogCursor::new() to start over
+            run_time_data: runtime event log lines measured since the last call
+            bank_data: IMA records of the register banks measured since the last
+                       call, as many as run_time_data for each bank added with
+                       add_ima_bank(), see add_ima_bank()
+        Returns:
+            The event logs new to the cursor, the cursor and the event logs are left
+            as is on error
+    */
+    pub fn select_since(
+        &mut self,
+        cursor: &mut EventLogCursor,
+        run_time_data: Vec<String>,
+        bank_data: Vec<(u16, Vec<String>)>,
+    ) -> Result<Vec<EventLogEntry>, anyhow::Error> {
+        if let Err(e) = self.parse() {
+            return Err(anyhow!("[select_since] error in parse function {:?}", e));
+        }
+        let start = match cursor.boot_time_offset {
+            0 => 0,
+            offset if offset == self.boot_time_length => self.event_logs.len(),
+            offset => {
+                return Err(anyhow!(
+                    "[select_since] boot time data changed since offset {}",
+                    offset
+                ))
+            }
+        };
+
+        let run_time_len = self.run_time_data.len();
+        let bank_lens: Vec<(u16, usize)> = self
+            .ima_banks
+            .iter()
+            .map(|(algo_id, lines)| (*algo_id, lines.len()))
+            .collect();
+        self.append_run_time_data(run_time_data);
+        for (algo_id, lines) in bank_data {
+            self.add_ima_bank(algo_id, lines);
+        }
+        // parse() restores the parsed state on error, the appended data is dropped
+        if let Err(e) = self.parse() {
+            self.truncate_run_time(run_time_len, &bank_lens);
+            return Err(anyhow!("[select_since] error in parse function {:?}", e));
+        }
+
+        let event_logs = self.event_logs[start..].to_vec();
+        let (replay_results, startup_localities) = match self.replay_since(cursor, &event_logs) {
+            Ok(v) => v,
+            Err(e) => {
+                self.truncate_run_time(run_time_len, &bank_lens);
+                self.reset();
+                return Err(anyhow!("[select_since] error in replay {:?}", e));
+            }
+        };
+
+        cursor.boot_time_offset = self.boot_time_length;
+        cursor.replay_results = replay_results;
+        cursor.startup_localities = startup_localities;
+        Ok(event_logs)
+    }
+
+    // drop the runtime data and the IMA bank records appended after the lengths
+    fn truncate_run_time(&mut self, run_time_len: usize, bank_lens: &[(u16, usize)]) {
+        self.truncate_run_time_data(run_time_len);
+        self.ima_banks
+            .retain(|(algo_id, _)| bank_lens.iter().any(|(bank, _)| bank == algo_id));
+        for (algo_id, lines) in self.ima_banks.iter_mut() {
+            if let Some((_, len)) = bank_lens.iter().find(|(bank, _)| bank == algo_id) {
+                lines.truncate(*len);
+            }
+        }
+    }
+
+    // replay of the event logs from the replay state of the cursor
+    fn replay_since(
+        &self,
+        cursor: &EventLogCursor,
+        event_logs: &[EventLogEntry],
+    ) -> Result<(ReplayResults, HashMap<u32, u8>), anyhow::Error> {
+        let mut replay_results = cursor.replay_results.clone();
+        let mut startup_localities = cursor.startup_localities.clone();
+        for event_log in event_logs {
+            if let EventLogEntry::TcgImrEvent(tcg_imr_event) = event_log {
+                EventLogs::replay_event(
+                    &mut replay_results,
+                    &mut startup_localities,
+                    tcg_imr_event,
+                    cursor.ima_bank,
+                )?;
+            }
+        }
+        Ok((replay_results, startup_localities))
+    }
+
+    /***
+       Add the IMA measurement list of a register bank, e.g. the content of
+       ascii_runtime_measurements_sha256. Its template hashes are added as digests of
+       the runtime events at the same position, so parsing fails unless each list has
+       as many records as the runtime data. The list is appended if the bank is
+       already added, a new bank drops the parsed runtime events to parse them again
+       with its digests.
+       Args:
+           algo_id: algorithm of the bank
+           run_time_data: IMA ascii records of the bank
+    */
+    pub fn add_ima_bank(&mut self, algo_id: u16, run_time_data: Vec<String>) {
+        match self.ima_banks.iter_mut().find(|(bank, _)| *bank == algo_id) {
+            Some((_, lines)) => lines.extend(run_time_data),
+            None => {
+                self.ima_banks.push((algo_id, run_time_data));
+                if self.run_time_parsed > 0 {
+                    self.reset();
+                }
+            }
+        }
+    }
+
+    // add the template hashes of the other banks to an IMA event
+    fn add_ima_bank_digests(
+        &self,
+        event_log: &mut TcgEventLog,
+        index: usize,
+    ) -> Result<(), anyhow::Error> {
+        for (algo_id, lines) in &self.ima_banks {
+            let line = match lines.get(index) {
+                Some(line) => line,
+                None => {
+                    return Err(anyhow!(
+                        "[add_ima_bank_digests] no IMA record {} in {} list",
+                        index,
+                        TcgDigest::get_algorithm_name(*algo_id)
+                    ))
+                }
+            };
+            if event_log
+                .digests
+                .iter()
+                .any(|digest| digest.algo_id == *algo_id)
+            {
+                continue;
+            }
+            let ima_event = ImaEvent::parse(line)?;
+            if ima_event.imr_index != event_log.imr_index {
+                return Err(anyhow!(
+                    "[add_ima_bank_digests] IMA record {} of {} is logged to another IMR",
+                    index,
+                    TcgDigest::get_algorithm_name(*algo_id)
+                ));
+            }
+            event_log.digests.push(TcgDigest {
+                algo_id: *algo_id,
+                hash: ima_event.template_hash,
+            });
+        }
+        Ok(())
     }
 
     /***
@@ -186,18 +564,123 @@ impl EventLogs {
        Returns:
            The record number
     */
-    fn get_record_number(&mut self, imr_index: u32) -> u32 {
+    fn get_record_number(&mut self, imr_index: u32) -> Result<u32, anyhow::Error> {
+        if imr_index as usize >= self.event_logs_record_number_list.len() {
+            return Err(anyhow!(
+                "[get_record_number] invalid imr index {}",
+                imr_index
+            ));
+        }
         let rec_num = self.event_logs_record_number_list[imr_index as usize];
         self.event_logs_record_number_list[imr_index as usize] += 1;
-        rec_num
+        Ok(rec_num)
+    }
+
+    // convert the index in boot time data into the imr index kept in event logs
+    fn get_imr_index(&self, index: u32) -> Result<u32, anyhow::Error> {
+        match self.index_flavour {
+            ImrIndexFlavour::CcMr => match CcMrIndex::new(index)?.rtmr_index() {
+                Ok(rtmr_index) => Ok(rtmr_index as u32),
+                Err(_) => Err(anyhow!(
+                    "[get_imr_index] CC MR index {} is not a RTMR",
+                    index
+                )),
+            },
+            ImrIndexFlavour::TpmPcr => Ok(index),
+        }
+    }
+
+    // drop all parsed state so that the raw data is parsed again from the beginning
+    fn reset(&mut self) {
+        self.spec_id_header_event = TcgEfiSpecIdEvent::new();
+        self.event_logs.clear();
+        self.count = 0;
+        self.event_logs_record_number_list = [0; 24];
+        self.boot_time_parsed = false;
+        self.run_time_parsed = 0;
+        self.boot_time_count = 0;
+        self.boot_time_length = 0;
     }
 
     /***
         Parse event log data into TCG compatible forms.
-        Go through all event log data and parse the contents accordingly
-        Save the parsed event logs into EventLogs.
+        Go through the event log data not parsed yet and parse the contents accordingly.
+        Save the parsed event logs into EventLogs. The boot time data is parsed once,
+        the runtime data is parsed from where last parse stopped. On error, the
+        parsed state is restored as before the call so that parse can be retried.
+
+        Returns:
+            The number of event logs newly parsed
     */
-    fn parse(&mut self) -> Result<bool, anyhow::Error> {
+    pub fn parse(&mut self) -> Result<u32, anyhow::Error> {
+        let parsed_count = self.event_logs.len();
+        let spec_id_header_event = self.spec_id_header_event.clone();
+        let record_numbers = self.event_logs_record_number_list;
+        let boot_time_parsed = self.boot_time_parsed;
+        let run_time_parsed = self.run_time_parsed;
+        let boot_time_count = self.boot_time_count;
+        let boot_time_length = self.boot_time_length;
+
+        let result = self.parse_unparsed();
+        if result.is_err() {
+            self.event_logs.truncate(parsed_count);
+            self.count = parsed_count as u32;
+            self.spec_id_header_event = spec_id_header_event;
+            self.event_logs_record_number_list = record_numbers;
+            self.boot_time_parsed = boot_time_parsed;
+            self.run_time_parsed = run_time_parsed;
+            self.boot_time_count = boot_time_count;
+            self.boot_time_length = boot_time_length;
+        }
+        result?;
+
+        Ok((self.event_logs.len() - parsed_count) as u32)
+    }
+
+    fn parse_unparsed(&mut self) -> Result<(), anyhow::Error> {
+        if !self.boot_time_parsed {
+            self.parse_boot_time_data()?;
+            self.boot_time_parsed = true;
+            self.boot_time_count = self.event_logs.len();
+        }
+
+        for (algo_id, lines) in &self.ima_banks {
+            if lines.len() != self.run_time_data.len() {
+                return Err(anyhow!(
+                    "[parse] {} IMA records of {} for {} runtime records",
+                    lines.len(),
+                    TcgDigest::get_algorithm_name(*algo_id),
+                    self.run_time_data.len()
+                ));
+            }
+        }
+
+        while self.run_time_parsed < self.run_time_data.len() {
+            match self
+                .parse_run_time_record(self.run_time_parsed)
+                .and_then(|(ima_event, event)| {
+                    let mut event_log = self.parse_ima_event_log(&ima_event, event)?;
+                    self.add_ima_bank_digests(&mut event_log, self.run_time_parsed)?;
+                    event_log.format_event_log(self.parse_format, self.count, Some(&ima_event))
+                }) {
+                Ok(event_log) => {
+                    self.event_logs.push(event_log);
+                    self.count += 1;
+                    self.run_time_parsed += 1;
+                }
+                Err(e) => {
+                    return Err(anyhow!(
+                        "[parse] error in parse_ima_event_log function {:?}",
+                        e
+                    ));
+                }
+            };
+        }
+
+        Ok(())
+    }
+
+    fn parse_boot_time_data(&mut self) -> Result<(), anyhow::Error> {
         if self.boot_time_data.is_empty() {
             return Err(anyhow!("[parse] no boot time eventlog provided"));
         }
@@ -205,19 +688,22 @@ impl EventLogs {
         let mut index = 0;
         while index < self.boot_time_data.len() {
             let start = index;
-            let imr = get_u32(self.boot_time_data[index..index + 4].to_vec());
-            index += 4;
-            let event_type = get_u32(self.boot_time_data[index..index + 4].to_vec());
+            let imr = get_u32(read_bytes(&self.boot_time_data, index, 4, "parse")?.to_vec());
             if imr == 0xFFFFFFFF {
                 break;
             }
+            index += 4;
+            let event_type = get_u32(read_bytes(&self.boot_time_data, index, 4, "parse")?.to_vec());
 
             if event_type == EV_NO_ACTION && self.count == 0 {
                 match self.parse_spec_id_event_log(self.boot_time_data[start..].to_vec()) {
                     Ok((spec_id_event, event_len)) => {
                         index = start + event_len as usize;
-                        self.event_logs
-                            .push(spec_id_event.format_event_log(self.parse_format));
+                        self.event_logs.push(spec_id_event.format_event_log(
+                            self.parse_format,
+                            self.count,
+                            None,
+                        )?);
                         self.count += 1;
                     }
                     Err(e) => {
@@ -231,8 +717,11 @@ impl EventLogs {
                 match self.parse_event_log(self.boot_time_data[start..].to_vec()) {
                     Ok((event_log, event_len)) => {
                         index = start + event_len as usize;
-                        self.event_logs
-                            .push(event_log.format_event_log(self.parse_format));
+                        self.event_logs.push(event_log.format_event_log(
+                            self.parse_format,
+                            self.count,
+                            None,
+                        )?);
                         self.count += 1;
                     }
                     Err(e) => {
@@ -242,25 +731,8 @@ impl EventLogs {
             }
         }
 
-        if !self.run_time_data.is_empty() {
-            for index in 0..self.run_time_data.len() {
-                match self.parse_ima_event_log(&self.run_time_data[index].clone()) {
-                    Ok(event_log) => {
-                        self.event_logs
-                            .push(event_log.format_event_log(self.parse_format));
-                        self.count += 1;
-                    }
-                    Err(e) => {
-                        return Err(anyhow!(
-                            "[parse] error in parse_ima_event_log function {:?}",
-                            e
-                        ));
-                    }
-                };
-            }
-        }
-
-        Ok(true)
+        self.boot_time_length = index;
+        Ok(())
     }
 
     /***
@@ -286,15 +758,16 @@ impl EventLogs {
     ) -> Result<(TcgEventLog, u32), anyhow::Error> {
         let mut index = 0;
 
-        let imr_index = get_u32(data[index..index + 4].to_vec());
+        let imr_index = get_u32(read_bytes(&data, index, 4, "parse_spec_id_event_log")?.to_vec());
         index += 4;
-        let header_imr = imr_index - 1;
-        let header_event_type = get_u32(data[index..index + 4].to_vec());
+        let header_imr = self.get_imr_index(imr_index)?;
+        let header_event_type =
+            get_u32(read_bytes(&data, index, 4, "parse_spec_id_event_log")?.to_vec());
         index += 4;
 
-        let rec_num = self.get_record_number(header_imr);
+        let rec_num = self.get_record_number(header_imr)?;
 
-        let digest_hash = data[index..index + 20].try_into().unwrap();
+        let digest_hash = read_bytes(&data, index, 20, "parse_spec_id_event_log")?.to_vec();
         index += 20;
         let mut digests: Vec<TcgDigest> = Vec::new();
         let digest = TcgDigest {
@@ -303,11 +776,16 @@ impl EventLogs {
         };
         digests.push(digest);
 
-        let header_event_size = get_u32(data[index..index + 4].to_vec());
+        let header_event_size =
+            get_u32(read_bytes(&data, index, 4, "parse_spec_id_event_log")?.to_vec());
         index += 4;
-        let header_event = data[index..index + header_event_size as usize]
-            .try_into()
-            .unwrap();
+        let header_event = read_bytes(
+            &data,
+            index,
+            header_event_size as usize,
+            "parse_spec_id_event_log",
+        )?
+        .to_vec();
         let specification_id_header = TcgEventLog {
             rec_num,
             imr_index: header_imr,
@@ -319,26 +797,35 @@ impl EventLogs {
         };
 
         // Parse EFI Spec Id Event structure
-        let spec_id_signature = data[index..index + 16].try_into().unwrap();
+        let spec_id_signature = read_bytes(&data, index, 16, "parse_spec_id_event_log")?
+            .try_into()
+            .unwrap();
         index += 16;
-        let spec_id_platform_cls = get_u32(data[index..index + 4].to_vec());
+        let spec_id_platform_cls =
+            get_u32(read_bytes(&data, index, 4, "parse_spec_id_event_log")?.to_vec());
         index += 4;
-        let spec_id_version_minor = get_u8(data[index..index + 1].to_vec());
+        let spec_id_version_minor =
+            get_u8(read_bytes(&data, index, 1, "parse_spec_id_event_log")?.to_vec());
         index += 1;
-        let spec_id_version_major = get_u8(data[index..index + 1].to_vec());
+        let spec_id_version_major =
+            get_u8(read_bytes(&data, index, 1, "parse_spec_id_event_log")?.to_vec());
         index += 1;
-        let spec_id_errata = get_u8(data[index..index + 1].to_vec());
+        let spec_id_errata =
+            get_u8(read_bytes(&data, index, 1, "parse_spec_id_event_log")?.to_vec());
         index += 1;
-        let spec_id_uint_size = get_u8(data[index..index + 1].to_vec());
+        let spec_id_uint_size =
+            get_u8(read_bytes(&data, index, 1, "parse_spec_id_event_log")?.to_vec());
         index += 1;
-        let spec_id_num_of_algo = get_u32(data[index..index + 4].to_vec());
+        let spec_id_num_of_algo =
+            get_u32(read_bytes(&data, index, 4, "parse_spec_id_event_log")?.to_vec());
         index += 4;
         let mut spec_id_digest_sizes: Vec<TcgEfiSpecIdEventAlgorithmSize> = Vec::new();
 
         for _ in 0..spec_id_num_of_algo {
-            let algo_id = get_u16(data[index..index + 2].to_vec());
+            let algo_id = get_u16(read_bytes(&data, index, 2, "parse_spec_id_event_log")?.to_vec());
             index += 2;
-            let digest_size = get_u16(data[index..index + 2].to_vec());
+            let digest_size =
+                get_u16(read_bytes(&data, index, 2, "parse_spec_id_event_log")?.to_vec());
             index += 2;
             spec_id_digest_sizes.push(TcgEfiSpecIdEventAlgorithmSize {
                 algo_id,
@@ -346,13 +833,18 @@ impl EventLogs {
             });
         }
 
-        let spec_id_vendor_size = get_u8(data[index..index + 1].to_vec());
+        let spec_id_vendor_size =
+            get_u8(read_bytes(&data, index, 1, "parse_spec_id_event_log")?.to_vec());
         index += 1;
         let mut spec_id_vendor_info = Vec::new();
         if spec_id_vendor_size > 0 {
-            spec_id_vendor_info = data[index..index + spec_id_vendor_size as usize]
-                .try_into()
-                .unwrap();
+            spec_id_vendor_info = read_bytes(
+                &data,
+                index,
+                spec_id_vendor_size as usize,
+                "parse_spec_id_event_log",
+            )?
+            .to_vec();
         }
         index += spec_id_vendor_size as usize;
 
@@ -391,42 +883,44 @@ impl EventLogs {
     fn parse_event_log(&mut self, data: Vec<u8>) -> Result<(TcgEventLog, u32), anyhow::Error> {
         let mut index = 0;
 
-        let mut imr_index = get_u32(data[index..index + 4].to_vec());
+        let imr_index = self.get_imr_index(get_u32(
+            read_bytes(&data, index, 4, "parse_event_log")?.to_vec(),
+        ))?;
         index += 4;
-        imr_index -= 1;
-        let event_type = get_u32(data[index..index + 4].to_vec());
+        let event_type = get_u32(read_bytes(&data, index, 4, "parse_event_log")?.to_vec());
         index += 4;
 
-        let rec_num = self.get_record_number(imr_index);
+        let rec_num = self.get_record_number(imr_index)?;
 
         // Fetch digest count and get each digest and its algorithm
-        let digest_count = get_u32(data[index..index + 4].to_vec());
+        let digest_count = get_u32(read_bytes(&data, index, 4, "parse_event_log")?.to_vec());
         index += 4;
         let mut digests: Vec<TcgDigest> = Vec::new();
         for _ in 0..digest_count {
-            let alg_id = get_u16(data[index..index + 2].to_vec());
+            let alg_id = get_u16(read_bytes(&data, index, 2, "parse_event_log")?.to_vec());
             index += 2;
-            let mut pos = 0;
 
-            while pos < self.spec_id_header_event.digest_sizes.len() {
-                if self.spec_id_header_event.digest_sizes[pos].algo_id == alg_id {
-                    break;
-                }
-                pos += 1;
-            }
-
-            if pos == self.spec_id_header_event.digest_sizes.len() {
-                return Err(anyhow!(
-                    "[parse_event_log] No algorithm with such algo_id {}",
-                    alg_id
-                ));
-            }
-
-            let alg = &self.spec_id_header_event.digest_sizes[pos];
-            let digest_size = alg.digest_size;
-            let digest_data = data[index..index + digest_size as usize]
-                .try_into()
-                .unwrap();
+            // algorithms missing in the spec id event fall back to the known digest size,
+            // the digest is kept as it is even if the algorithm is not supported
+            let digest_size = match self
+                .spec_id_header_event
+                .digest_sizes
+                .iter()
+                .find(|alg| alg.algo_id == alg_id)
+            {
+                Some(alg) => alg.digest_size,
+                None => match TcgDigest::get_digest_size_from_algorithm_id(alg_id) {
+                    0 => {
+                        return Err(anyhow!(
+                            "[parse_event_log] Unknown digest size of algorithm {}",
+                            TcgDigest::get_algorithm_name(alg_id)
+                        ))
+                    }
+                    size => size.into(),
+                },
+            };
+            let digest_data =
+                read_bytes(&data, index, digest_size as usize, "parse_event_log")?.to_vec();
             index += digest_size as usize;
             let digest = TcgDigest {
                 algo_id: alg_id,
@@ -435,9 +929,9 @@ impl EventLogs {
             digests.push(digest);
         }
 
-        let event_size = get_u32(data[index..index + 4].to_vec());
+        let event_size = get_u32(read_bytes(&data, index, 4, "parse_event_log")?.to_vec());
         index += 4;
-        let event = data[index..index + event_size as usize].try_into().unwrap();
+        let event = read_bytes(&data, index, event_size as usize, "parse_event_log")?.to_vec();
         index += event_size as usize;
 
         Ok((
@@ -455,44 +949,38 @@ impl EventLogs {
     }
 
     /***
-       Parse ascii IMA events gathered during runtime.
+       Convert IMA events gathered during runtime.
 
-       Sample event and format:
+       Sample ascii record and format:
        IMR index | Template hash | Template name | Event data according to template
        10 1e762ca412a3ef388ddcab416e2eb382d9d1e356 ima-ng sha384:74ccc46104f42db070375e6876a23aeaa3c2ae458888475baaa171c3fb7001b0fc385ed08420d5f60620924fc64d0b80 /etc/lsb-release
 
        Args:
-           event: IMA ascii raw event
+           ima_event: IMA event parsed from the record
+           event: event data according to template, the record after the template name
 
        Returns:
            A TcgEventLog object containing the ima event log
     */
-    fn parse_ima_event_log(&mut self, data: &str) -> Result<TcgEventLog, anyhow::Error> {
-        /*  after the split, the elements vec has following mapping:
-               elements[0] => IMR index
-               elements[1] => Template hash
-               elements[2] => Template name
-               elements[3] to end of vec => Event data according to template
-        */
-        let elements: Vec<&str> = data.trim_matches(' ').split(' ').collect();
-
-        let imr_index: u32 = elements[0].parse().unwrap();
-        let rec_num = self.get_record_number(imr_index);
-
-        let event = elements[3..].join(" ").as_bytes().to_vec();
+    fn parse_ima_event_log(
+        &mut self,
+        ima_event: &ImaEvent,
+        event: Vec<u8>,
+    ) -> Result<TcgEventLog, anyhow::Error> {
+        let imr_index = ima_event.imr_index;
+        let rec_num = self.get_record_number(imr_index)?;
         let event_size = event.len() as u32;
 
-        let mut digests: Vec<TcgDigest> = Vec::new();
-        let digest_size = elements[1].len() / 2;
-        let algo_id = TcgDigest::get_algorithm_id_from_digest_size(digest_size.try_into().unwrap());
-        let digest = TcgDigest {
+        let algo_id = TcgDigest::get_algorithm_id_from_digest_size(
+            ima_event.template_hash.len().try_into().unwrap_or(0),
+        );
+        let digests = vec![TcgDigest {
             algo_id,
-            hash: hex::decode(elements[1]).expect("Decoding hash string from IMA record failed"),
-        };
-        digests.push(digest);
+            hash: ima_event.template_hash.clone(),
+        }];
 
         let mut extra_info = HashMap::new();
-        extra_info.insert("template_name".to_string(), elements[2].to_string());
+        extra_info.insert("template_name".to_string(), ima_event.template_name.clone());
 
         Ok(TcgEventLog {
             rec_num,
@@ -506,99 +994,168 @@ impl EventLogs {
     }
     /***
        Replay event logs by IMR index.
+       The digests are extended with the hash algorithms registered in crate::algorithm,
+       a digest of an algorithm not registered fails the replay.
+       EV_NO_ACTION events are not extended, a StartupLocality event sets the initial
+       value of the register it is logged to (TPM PCR0). IMA violations, recorded with
+       a template hash of zeros, are extended as 0xFF.
        Returns:
            A struct containing the replay result arranged by IMR index and hash algorithm.
            Layer 1 key of the struct is the IMR index, the value is another dict which using the
            hash algorithm as the key and the replayed measurement as value.
            Sample results:
-               [
-                   0: [{ 4: <measurement_replayed>},{ 12: <measurement_replayed>},]
-                   1: { 12: <measurement_replayed>},
-               ]
+               {
+                   0: { 4: <measurement_replayed>, 12: <measurement_replayed> },
+                   1: { 12: <measurement_replayed> },
+               }
     */
-    pub fn replay(eventlogs: Vec<EventLogEntry>) -> Result<Vec<ReplayResult>, anyhow::Error> {
-        let mut replay_results: Vec<ReplayResult> = Vec::new();
+    pub fn replay(eventlogs: Vec<EventLogEntry>) -> Result<ReplayResults, anyhow::Error> {
+        EventLogs::replay_with_ima_bank(eventlogs, None)
+    }
+
+    /***
+       Replay event logs as replay(), with the bank IMA extends its template hashes to.
+       IMA events without a digest of the bank have their first template hash padded
+       with zeros to the digest size of the bank, e.g. a SHA1 template hash extended to
+       the SHA384 RTMR of a TDX TD.
+       Args:
+           eventlogs: the event logs to replay
+           ima_bank: algorithm of the register bank extended by IMA
+    */
+    pub fn replay_with_ima_bank(
+        eventlogs: Vec<EventLogEntry>,
+        ima_bank: Option<u16>,
+    ) -> Result<ReplayResults, anyhow::Error> {
+        let mut replay_results = ReplayResults::default();
+        let mut startup_localities: HashMap<u32, u8> = HashMap::new();
 
         for event_log in eventlogs {
             match event_log {
-                EventLogEntry::TcgImrEvent(tcg_imr_event) => {
-                    if tcg_imr_event.event_type == EV_NO_ACTION {
-                        continue;
+                EventLogEntry::TcgImrEvent(tcg_imr_event) => EventLogs::replay_event(
+                    &mut replay_results,
+                    &mut startup_localities,
+                    &tcg_imr_event,
+                    ima_bank,
+                )?,
+                EventLogEntry::TcgPcClientImrEvent(_) => (), // Skip TcgPcClientImrEvent during replay
+                EventLogEntry::TcgCanonicalEvent(tcg_cel_event)
+                | EventLogEntry::TcgTpmsCelEvent(tcg_cel_event) => {
+                    if let Some(tcg_imr_event) = tcg_cel_event.to_pcclient_format() {
+                        EventLogs::replay_event(
+                            &mut replay_results,
+                            &mut startup_localities,
+                            &tcg_imr_event,
+                            ima_bank,
+                        )?
                     }
-                    let imr_index = tcg_imr_event.imr_index;
-                    for digest in tcg_imr_event.digests {
-                        let algo_id = digest.algo_id;
-                        let hash = digest.hash;
-                        let digest_size = TcgDigest::get_digest_size_from_algorithm_id(algo_id);
-
-                        let mut imr_pos = usize::MAX;
-                        let mut algo_pos = usize::MAX;
-                        for index1 in 0..replay_results.len() {
-                            if replay_results[index1].imr_index == imr_index {
-                                imr_pos = index1;
-                            }
-                        }
+                }
+            }
+        }
+        Ok(replay_results)
+    }
 
-                        if imr_pos == usize::MAX {
-                            replay_results.push(ReplayResult {
-                                imr_index,
-                                digests: Vec::new(),
-                            });
-                            imr_pos = replay_results.len() - 1;
-                        } else {
-                            for index2 in 0..replay_results[imr_pos].digests.len() {
-                                if digest.algo_id == algo_id {
-                                    algo_pos = index2;
-                                }
-                            }
-                        }
+    // extend the replay results with an event, EV_NO_ACTION events only set a startup locality
+    pub(crate) fn replay_event(
+        replay_results: &mut ReplayResults,
+        startup_localities: &mut HashMap<u32, u8>,
+        tcg_imr_event: &TcgImrEvent,
+        ima_bank: Option<u16>,
+    ) -> Result<(), anyhow::Error> {
+        let imr_index = tcg_imr_event.imr_index;
+        if tcg_imr_event.event_type == EV_NO_ACTION {
+            if let Ok(NoActionEvent::StartupLocality(locality)) =
+                NoActionEvent::parse(&tcg_imr_event.event)
+            {
+                startup_localities.insert(imr_index, locality);
+            }
+            return Ok(());
+        }
+        let is_ima = tcg_imr_event.event_type == IMA_MEASUREMENT_EVENT;
+        let mut digests = tcg_imr_event.digests.clone();
+        if let (true, Some(bank), Some(first)) = (is_ima, ima_bank, digests.first()) {
+            if !digests.iter().any(|digest| digest.algo_id == bank) {
+                digests.push(TcgDigest {
+                    algo_id: bank,
+                    hash: first.hash.clone(),
+                });
+            }
+        }
 
-                        if algo_pos == usize::MAX {
-                            replay_results[imr_pos].digests.push(TcgDigest {
-                                algo_id,
-                                hash: vec![0; digest_size.into()],
-                            });
-                            algo_pos = replay_results[imr_pos].digests.len() - 1;
-                        }
+        for digest in &digests {
+            let algorithm = match get_algorithm(digest.algo_id) {
+                Some(algorithm) => algorithm,
+                None => {
+                    return Err(anyhow!(
+                        "[replay] unsupported algorithm {} in IMR[{}]",
+                        TcgDigest::get_algorithm_name(digest.algo_id),
+                        imr_index
+                    ))
+                }
+            };
 
-                        let hash_input_data =
-                            [replay_results[imr_pos].digests[algo_pos].hash.clone(), hash].concat();
-
-                        match algo_id {
-                            TPM_ALG_SHA1 => {
-                                let mut algo_hasher = Sha1::new();
-                                algo_hasher.update(hash_input_data);
-                                replay_results[imr_pos].digests[algo_pos].hash =
-                                    algo_hasher.finalize().to_vec();
-                            }
-                            TPM_ALG_SHA256 => {
-                                let mut algo_hasher = Sha256::new();
-                                algo_hasher.update(hash_input_data);
-                                replay_results[imr_pos].digests[algo_pos].hash =
-                                    algo_hasher.finalize().to_vec();
-                            }
-                            TPM_ALG_SHA384 => {
-                                let mut algo_hasher = Sha384::new();
-                                algo_hasher.update(hash_input_data);
-                                replay_results[imr_pos].digests[algo_pos].hash =
-                                    algo_hasher.finalize().to_vec();
-                            }
-                            TPM_ALG_SHA512 => {
-                                let mut algo_hasher = Sha512::new();
-                                algo_hasher.update(hash_input_data);
-                                replay_results[imr_pos].digests[algo_pos].hash =
-                                    algo_hasher.finalize().to_vec();
-                            }
-                            0_u16..=3_u16 | 5_u16..=10_u16 | 14_u16..=u16::MAX => (),
+            // each bank of an IMR is replayed from its own initial value
+            let banks = replay_results.imrs.entry(imr_index).or_default();
+            let value = match banks.entry(digest.algo_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let initial_value = match startup_localities.get(&imr_index) {
+                        Some(locality) => {
+                            startup_locality_initial_value(*locality, algorithm.digest_size())?
                         }
+                        None => vec![0; algorithm.digest_size()],
+                    };
+                    entry.insert(initial_value)
+                }
+            };
+            let hash = match is_ima {
+                true => ima_extend_value(&digest.hash, algorithm.digest_size())?,
+                false => digest.hash.clone(),
+            };
+            *value = algorithm.hash(&[value.as_slice(), &hash].concat());
+        }
+        Ok(())
+    }
+
+    /***
+       Replay event logs by the RTMR a TDX TD extends them to, so that the results can
+       be compared with the RTMRs of a quote. The index of the events is mapped with
+       CcMrIndex, events of TPM PCR0 are skipped since MRTD is not extended at runtime.
+       IMA template hashes are extended to the SHA384 bank of the RTMRs.
+       Args:
+           eventlogs: the event logs to replay
+           index_flavour: flavour of the index in the boot time data of the event logs
+       Returns:
+           The replay results keyed by RTMR index
+    */
+    pub fn replay_by_rtmr(
+        eventlogs: Vec<EventLogEntry>,
+        index_flavour: ImrIndexFlavour,
+    ) -> Result<ReplayResults, anyhow::Error> {
+        let mut rtmr_eventlogs = Vec::new();
+        for event_log in eventlogs {
+            let event_log = match event_log {
+                EventLogEntry::TcgCanonicalEvent(tcg_cel_event)
+                | EventLogEntry::TcgTpmsCelEvent(tcg_cel_event) => {
+                    match tcg_cel_event.to_pcclient_format() {
+                        Some(tcg_imr_event) => EventLogEntry::TcgImrEvent(tcg_imr_event),
+                        None => continue,
                     }
                 }
-                EventLogEntry::TcgPcClientImrEvent(_) => (), // Skip TcgPcClientImrEvent during replay
-                EventLogEntry::TcgCanonicalEvent(_) => todo!(),
-                EventLogEntry::TcgTpmsCelEvent(_) => todo!(),
+                event_log => event_log,
+            };
+            match event_log {
+                EventLogEntry::TcgImrEvent(mut tcg_imr_event) => {
+                    let index =
+                        CcMrIndex::from_event_log_index(tcg_imr_event.imr_index, index_flavour)?;
+                    if let Ok(rtmr_index) = index.rtmr_index() {
+                        tcg_imr_event.imr_index = rtmr_index as u32;
+                        rtmr_eventlogs.push(EventLogEntry::TcgImrEvent(tcg_imr_event));
+                    }
+                }
+                event_log => rtmr_eventlogs.push(event_log),
             }
         }
-        Ok(replay_results)
+        EventLogs::replay_with_ima_bank(rtmr_eventlogs, Some(TPM_ALG_SHA384))
     }
 }
 
@@ -614,3 +1171,341 @@ impl ReplayResult {
         }
     }
 }
+
+#[cfg(test)]
+mod test_eventlogs {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    const BOOT_TIME_DATA: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test_data/sysroot/sys/firmware/acpi/tables/data/CCEL"
+    );
+    const RUN_TIME_DATA: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test_data/sysroot/sys/kernel/security/integrity/ima/ascii_runtime_measurements"
+    );
+
+    fn load_event_logs() -> EventLogs {
+        let boot_time_data = std::fs::read(BOOT_TIME_DATA).unwrap();
+        let run_time_data = std::fs::read_to_string(RUN_TIME_DATA)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        EventLogs::new(boot_time_data, run_time_data, TCG_PCCLIENT_FORMAT)
+    }
+
+    // TPM event log with SHA256 and SM3_256 banks in PCR0
+    fn tpm_boot_time_data(algo_id: u16, digest: &[u8]) -> Vec<u8> {
+        let mut spec_id = b"Spec ID Event03\0".to_vec();
+        spec_id.extend(0_u32.to_le_bytes());
+        spec_id.extend([0, 2, 0, 2]);
+        spec_id.extend(2_u32.to_le_bytes());
+        spec_id.extend(TPM_ALG_SHA256.to_le_bytes());
+        spec_id.extend(32_u16.to_le_bytes());
+        spec_id.extend(TPM_ALG_SM3_256.to_le_bytes());
+        spec_id.extend(32_u16.to_le_bytes());
+        spec_id.push(0);
+
+        let mut data = Vec::new();
+        data.extend(0_u32.to_le_bytes());
+        data.extend(EV_NO_ACTION.to_le_bytes());
+        data.extend([0; 20]);
+        data.extend((spec_id.len() as u32).to_le_bytes());
+        data.extend(spec_id);
+
+        data.extend(0_u32.to_le_bytes());
+        data.extend(EV_POST_CODE.to_le_bytes());
+        data.extend(3_u32.to_le_bytes());
+        data.extend(TPM_ALG_SHA256.to_le_bytes());
+        data.extend([0x11; 32]);
+        data.extend(TPM_ALG_SM3_256.to_le_bytes());
+        data.extend([0x22; 32]);
+        data.extend(algo_id.to_le_bytes());
+        data.extend(digest);
+        data.extend(4_u32.to_le_bytes());
+        data.extend(b"test");
+        data
+    }
+
+    fn event_types(events: &[EventLogEntry]) -> Vec<u32> {
+        events
+            .iter()
+            .map(|event| match event {
+                EventLogEntry::TcgImrEvent(e) => e.event_type,
+                EventLogEntry::TcgPcClientImrEvent(e) => e.event_type,
+                _ => u32::MAX,
+            })
+            .collect()
+    }
+
+    #[test]
+    //selecting twice returns the same events
+    fn test_select_is_idempotent() {
+        let mut event_logs = load_event_logs();
+        let first = event_logs.select(None, None).unwrap();
+        let record_numbers = event_logs.event_logs_record_number_list;
+        let second = event_logs.select(None, None).unwrap();
+        assert_eq!(first.len(), second.len());
+        assert_eq!(event_types(&first), event_types(&second));
+        assert_eq!(event_logs.count as usize, first.len());
+        assert_eq!(event_logs.event_logs_record_number_list, record_numbers);
+    }
+
+    #[test]
+    //appended runtime data is parsed incrementally
+    fn test_append_run_time_data() {
+        let mut event_logs = load_event_logs();
+        let before = event_logs.select(None, None).unwrap().len();
+        let record_number = event_logs.event_logs_record_number_list[2];
+        let line = event_logs.run_time_data()[0].clone();
+        event_logs.append_run_time_data(vec![line]);
+        assert_eq!(event_logs.parse().unwrap(), 1);
+        let after = event_logs.select(None, None).unwrap();
+        assert_eq!(after.len(), before + 1);
+        assert_eq!(
+            event_logs.event_logs_record_number_list[2],
+            record_number + 1
+        );
+    }
+
+    #[test]
+    //parsed event logs can be shared across threads
+    fn test_select_parsed_across_threads() {
+        let mut event_logs = load_event_logs();
+        let expected = event_logs.select(None, None).unwrap().len();
+        let shared = Arc::new(event_logs);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let event_logs = Arc::clone(&shared);
+                thread::spawn(move || event_logs.select_parsed(None, None).unwrap().len())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    //0 based TPM PCR index is kept, banks missing in spec id event are kept
+    fn test_tpm_pcr_index_flavour() {
+        let boot_time_data = tpm_boot_time_data(TPM_ALG_SHA3_384, &[0x33; 48]);
+        let mut event_logs = EventLogs::new(boot_time_data, Vec::new(), TCG_PCCLIENT_FORMAT);
+        event_logs.set_index_flavour(ImrIndexFlavour::TpmPcr);
+        let events = event_logs.select(None, None).unwrap();
+        assert_eq!(events.len(), 2);
+        match &events[1] {
+            EventLogEntry::TcgImrEvent(event) => {
+                assert_eq!(event.imr_index, 0);
+                let algo_ids: Vec<u16> = event.digests.iter().map(|d| d.algo_id).collect();
+                assert_eq!(
+                    algo_ids,
+                    vec![TPM_ALG_SHA256, TPM_ALG_SM3_256, TPM_ALG_SHA3_384]
+                );
+                assert_eq!(event.digests[2].hash, vec![0x33; 48]);
+                assert_eq!(event.event, b"test");
+            }
+            _ => panic!("unexpected event log entry"),
+        }
+    }
+
+    #[test]
+    //0 based index is rejected rather than wrapped in CC MR flavour
+    fn test_cc_mr_index_flavour_rejects_mrtd() {
+        let boot_time_data = tpm_boot_time_data(TPM_ALG_SHA3_384, &[0x33; 48]);
+        let mut event_logs = EventLogs::new(boot_time_data, Vec::new(), TCG_PCCLIENT_FORMAT);
+        assert_eq!(event_logs.index_flavour(), ImrIndexFlavour::CcMr);
+        assert!(event_logs.select(None, None).is_err());
+        assert_eq!(event_logs.count, 0);
+    }
+
+    #[test]
+    //digest of unknown size can not be skipped
+    fn test_unknown_digest_size() {
+        let boot_time_data = tpm_boot_time_data(0x1234, &[0x44; 32]);
+        let mut event_logs = EventLogs::new(boot_time_data, Vec::new(), TCG_PCCLIENT_FORMAT);
+        event_logs.set_index_flavour(ImrIndexFlavour::TpmPcr);
+        assert!(event_logs.select(None, None).is_err());
+        assert_eq!(TcgDigest::get_algorithm_name(0x1234), "UNKNOWN(0x1234)");
+
+        // the spec id event parsed before the failure is rolled back
+        assert_eq!(event_logs.spec_id_header_event.number_of_algorithms, 0);
+        assert_eq!(event_logs.count, 0);
+        event_logs.set_boot_time_data(tpm_boot_time_data(TPM_ALG_SHA384, &[0x44; 48]));
+        assert_eq!(event_logs.select(None, None).unwrap().len(), 2);
+        assert_eq!(event_logs.spec_id_header_event.number_of_algorithms, 2);
+    }
+
+    #[test]
+    //event log truncated in the middle of an event is an error rather than a panic
+    fn test_truncated_event_log() {
+        let boot_time_data = tpm_boot_time_data(TPM_ALG_SHA3_384, &[0x33; 48]);
+        let mut event_logs =
+            EventLogs::new(boot_time_data.clone(), Vec::new(), TCG_PCCLIENT_FORMAT);
+        event_logs.set_index_flavour(ImrIndexFlavour::TpmPcr);
+        assert_eq!(event_logs.select(None, None).unwrap().len(), 2);
+
+        for len in 1..boot_time_data.len() {
+            event_logs.set_boot_time_data(boot_time_data[..len].to_vec());
+            match event_logs.select(None, None) {
+                // cut right after the spec id event
+                Ok(events) => assert_eq!(events.len(), 1),
+                Err(_) => assert_eq!(event_logs.count, 0),
+            }
+        }
+
+        // cut in the middle of the digests and of the event data
+        event_logs.set_boot_time_data(boot_time_data[..boot_time_data.len() - 60].to_vec());
+        assert!(event_logs.select(None, None).is_err());
+        event_logs.set_boot_time_data(boot_time_data[..boot_time_data.len() - 2].to_vec());
+        assert!(event_logs.select(None, None).is_err());
+    }
+
+    fn imr_event(algo_id: u16, hash: Vec<u8>) -> EventLogEntry {
+        EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index: 0,
+            event_type: EV_POST_CODE,
+            digests: vec![TcgDigest { algo_id, hash }],
+            event_size: 0,
+            event: Vec::new(),
+        })
+    }
+
+    #[test]
+    //SM3 digests are extended with the registered SM3 algorithm
+    fn test_replay_sm3() {
+        let replay_results = EventLogs::replay(vec![imr_event(TPM_ALG_SM3_256, vec![0x22; 32])]);
+        let replay_results = replay_results.unwrap();
+        assert_eq!(replay_results.imr_indexes(), vec![0]);
+        assert_eq!(
+            replay_results.get(0, TPM_ALG_SM3_256).unwrap(),
+            crate::algorithm::sm3(&[[0; 32], [0x22; 32]].concat())
+        );
+    }
+
+    #[test]
+    //algorithms not registered are reported instead of skipped
+    fn test_replay_unsupported_algorithm() {
+        assert!(EventLogs::replay(vec![imr_event(0x1234, vec![0x44; 32])]).is_err());
+    }
+
+    #[test]
+    //StartupLocality sets the initial value of PCR0
+    fn test_replay_startup_locality() {
+        let mut locality = STARTUP_LOCALITY_SIGNATURE.to_vec();
+        locality.push(3);
+        let no_action = EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index: 0,
+            event_type: EV_NO_ACTION,
+            digests: vec![TcgDigest {
+                algo_id: TPM_ALG_SHA256,
+                hash: vec![0; 32],
+            }],
+            event_size: locality.len() as u32,
+            event: locality,
+        });
+        let replay_results =
+            EventLogs::replay(vec![no_action, imr_event(TPM_ALG_SHA256, vec![0x11; 32])]).unwrap();
+
+        let mut initial_value = vec![0; 32];
+        initial_value[31] = 3;
+        let expected = crate::algorithm::get_algorithm(TPM_ALG_SHA256)
+            .unwrap()
+            .hash(&[initial_value, vec![0x11; 32]].concat());
+        assert_eq!(replay_results.get(0, TPM_ALG_SHA256).unwrap(), expected);
+    }
+
+    #[test]
+    //canonical format converts firmware and IMA events into CEL records
+    fn test_canonical_format() {
+        let pcclient_events = load_event_logs().select(None, None).unwrap();
+        let mut event_logs = load_event_logs();
+        event_logs.parse_format = TCG_CANONICAL_FORMAT;
+        let events = event_logs.select(None, None).unwrap();
+        assert_eq!(events.len(), pcclient_events.len());
+
+        let records: Vec<&TcgTpmsCelEvent> = events
+            .iter()
+            .map(|event| match event {
+                EventLogEntry::TcgTpmsCelEvent(record) => record,
+                _ => panic!("unexpected event log entry"),
+            })
+            .collect();
+        for (seq_num, record) in records.iter().enumerate() {
+            assert_eq!(record.rec_num(), seq_num as i32);
+        }
+        match records[1].content() {
+            Some(TcgTpmuEventContent::TcgTpmsEventPcClientStd(content)) => {
+                assert_eq!(
+                    content.event_type() as u32,
+                    event_types(&pcclient_events)[1]
+                )
+            }
+            _ => panic!("unexpected firmware event content"),
+        }
+        let ima = records.last().unwrap();
+        assert_eq!(ima.content_type(), Some(TcgCelTypes::CEL_IMA_TEMPLATE));
+        assert_eq!(ima.index(), Some(2));
+        let ima_event = &load_event_logs().ima_events().unwrap()[0];
+        match ima.content() {
+            Some(TcgTpmuEventContent::TcgTpmsEventImaTemplate(content)) => {
+                assert_eq!(content.template_name(), "ima-ng");
+                assert_eq!(content.template_data(), &ima_event.template_data());
+                assert_eq!(
+                    get_algorithm(TPM_ALG_SHA384)
+                        .unwrap()
+                        .hash(content.template_data()),
+                    ima.digests()[0].hash
+                );
+            }
+            _ => panic!("unexpected IMA event content"),
+        }
+        match ima.to_pcclient_format() {
+            Some(event) => assert_eq!(event.event, ima_event.template_data()),
+            None => panic!("unexpected IMA event content"),
+        }
+
+        assert_eq!(
+            EventLogs::replay(events.clone()).unwrap(),
+            EventLogs::replay(pcclient_events.clone()).unwrap()
+        );
+        assert_eq!(
+            EventLogs::replay_by_rtmr(events, ImrIndexFlavour::CcMr).unwrap(),
+            EventLogs::replay_by_rtmr(pcclient_events, ImrIndexFlavour::CcMr).unwrap()
+        );
+
+        let mut event_logs = load_event_logs();
+        event_logs.parse_format = 0;
+        assert!(event_logs.select(None, None).is_err());
+    }
+
+    #[test]
+    //CEL records not measured to an IMR are skipped rather than panicking in replay
+    fn test_replay_nv_index_record() {
+        let record = TcgTpmsCelEvent::new(
+            0,
+            vec![TcgDigest {
+                algo_id: TPM_ALG_SHA384,
+                hash: vec![0x11; 48],
+            }],
+            Some(TcgCelTypes::CEL_PCCLIENT_STD),
+            None,
+            Some(0x1800001),
+            Some(TcgTpmuEventContent::TcgTpmsEventPcClientStd(
+                TcgTpmsEventPcClientStd::new(EV_POST_CODE as i32, Vec::new()),
+            )),
+        );
+        assert!(record.to_pcclient_format().is_none());
+        let events = vec![
+            EventLogEntry::TcgTpmsCelEvent(record.clone()),
+            EventLogEntry::TcgCanonicalEvent(record),
+        ];
+        assert!(EventLogs::replay(events.clone())
+            .unwrap()
+            .imr_indexes()
+            .is_empty());
+        assert!(EventLogs::replay_by_rtmr(events, ImrIndexFlavour::CcMr).is_ok());
+    }
+}
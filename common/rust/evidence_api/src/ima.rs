@@ -0,0 +1,978 @@
+use crate::algorithm::get_algorithm;
+use crate::eventlog::EventLogs;
+use crate::tcg::*;
+use anyhow::anyhow;
+use log::info;
+
+pub const IMA_TEMPLATE_IMA: &str = "ima";
+pub const IMA_TEMPLATE_IMA_NG: &str = "ima-ng";
+pub const IMA_TEMPLATE_IMA_SIG: &str = "ima-sig";
+pub const IMA_TEMPLATE_IMA_BUF: &str = "ima-buf";
+pub const IMA_TEMPLATE_IMA_MODSIG: &str = "ima-modsig";
+
+// the n field of the ima template is padded to IMA_EVENT_NAME_LEN_MAX + 1
+const IMA_EVENT_NAME_LEN: usize = 256;
+
+// measurement list of a register bank, e.g. ascii_runtime_measurements_sha256
+pub const IMA_BANK_DATA_FILE_PREFIX: &str = "ascii_runtime_measurements_";
+pub const IMA_BINARY_DATA_FILE: &str = "binary_runtime_measurements";
+
+/***
+    Byte order of the length fields of the binary measurement list and of the
+    template data. It is little endian with the ima_canonical_fmt option, the byte
+    order of the host otherwise.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImaByteOrder {
+    Little,
+    Big,
+}
+
+impl ImaByteOrder {
+    fn u32_from_bytes(&self, bytes: [u8; 4]) -> u32 {
+        match self {
+            ImaByteOrder::Little => u32::from_le_bytes(bytes),
+            ImaByteOrder::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32_to_bytes(&self, value: u32) -> [u8; 4] {
+        match self {
+            ImaByteOrder::Little => value.to_le_bytes(),
+            ImaByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+// algorithm of an IMA hash algorithm name, e.g. sha256
+pub fn ima_algorithm_id(name: &str) -> Option<u16> {
+    match name {
+        "sha1" => Some(TPM_ALG_SHA1),
+        "sha256" => Some(TPM_ALG_SHA256),
+        "sha384" => Some(TPM_ALG_SHA384),
+        "sha512" => Some(TPM_ALG_SHA512),
+        "sm3" | "sm3-256" => Some(TPM_ALG_SM3_256),
+        _ => None,
+    }
+}
+
+/***
+    Value IMA extends to a register bank for a template hash. A violation is recorded
+    with a template hash of zeros but extended as 0xFF, a template hash shorter than
+    the digest of the bank is padded with zeros, e.g. a SHA1 or SHA256 template hash
+    extended to the SHA384 RTMR of a TDX TD.
+*/
+pub fn ima_extend_value(
+    template_hash: &[u8],
+    digest_size: usize,
+) -> Result<Vec<u8>, anyhow::Error> {
+    if template_hash.len() > digest_size {
+        return Err(anyhow!(
+            "[ima_extend_value] template hash of {} bytes exceeds the bank digest of {} bytes",
+            template_hash.len(),
+            digest_size
+        ));
+    }
+    if template_hash.iter().all(|v| *v == 0) {
+        return Ok(vec![0xff; digest_size]);
+    }
+    let mut value = template_hash.to_vec();
+    value.resize(digest_size, 0);
+    Ok(value)
+}
+
+/***
+    File data hash of a d or d-ng field, shown as <algorithm>:<hex> in the ascii
+    measurement list, e.g. sha256:<hex>. The d field of the ima template is a SHA1
+    digest without the algorithm.
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ImaDigest {
+    pub algorithm: String,
+    pub digest: Vec<u8>,
+}
+
+impl ImaDigest {
+    fn parse(field: &str) -> Result<ImaDigest, anyhow::Error> {
+        let (algorithm, digest) = match field.split_once(':') {
+            Some(v) => v,
+            None => {
+                return Err(anyhow!(
+                    "[ImaDigest::parse] missing algorithm in digest {}",
+                    field
+                ))
+            }
+        };
+        Ok(ImaDigest {
+            algorithm: algorithm.to_string(),
+            digest: decode_hex(digest, "ImaDigest::parse")?,
+        })
+    }
+
+    // template data of a d-ng field: <algorithm>:\0 followed by the digest
+    fn template_data(&self) -> Vec<u8> {
+        let mut data = format!("{}:\0", self.algorithm).into_bytes();
+        data.extend(&self.digest);
+        data
+    }
+
+    fn parse_template_data(field: &[u8]) -> Result<ImaDigest, anyhow::Error> {
+        let separator = match field.windows(2).position(|v| v == b":\0") {
+            Some(v) => v,
+            None => {
+                return Err(anyhow!(
+                    "[ImaDigest::parse_template_data] missing algorithm in digest field"
+                ))
+            }
+        };
+        Ok(ImaDigest {
+            algorithm: String::from_utf8_lossy(&field[..separator]).to_string(),
+            digest: field[separator + 2..].to_vec(),
+        })
+    }
+
+    fn ascii(&self) -> String {
+        format!("{}:{}", self.algorithm, hex::encode(&self.digest))
+    }
+}
+
+fn decode_hex(field: &str, caller: &str) -> Result<Vec<u8>, anyhow::Error> {
+    hex::decode(field).map_err(|e| anyhow!("[{}] invalid hex {}: {}", caller, field, e))
+}
+
+// optional hex field, empty when the measurement has no such data
+fn optional_hex(field: &str) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    match field {
+        "" => Ok(None),
+        _ => decode_hex(field, "ImaEvent::parse").map(Some),
+    }
+}
+
+// split the trailing hex fields from the path, which may contain spaces
+fn split_path(fields: &str, count: usize) -> Result<(&str, Vec<&str>), anyhow::Error> {
+    let mut tail: Vec<&str> = fields.rsplitn(count + 1, ' ').collect();
+    if tail.len() != count + 1 {
+        return Err(anyhow!(
+            "[ImaEvent::parse] expected {} fields after the path in {}",
+            count,
+            fields
+        ));
+    }
+    let path = tail.pop().unwrap();
+    tail.reverse();
+    Ok((path, tail))
+}
+
+// reader of the binary measurement list
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    byte_order: ImaByteOrder,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], anyhow::Error> {
+        if len > self.data.len() - self.offset {
+            return Err(anyhow!(
+                "[ImaEvent::parse_binary] truncated IMA record, {} bytes expected at offset {}",
+                len,
+                self.offset
+            ));
+        }
+        let bytes = &self.data[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, anyhow::Error> {
+        let bytes = self.bytes(4)?;
+        Ok(self.byte_order.u32_from_bytes(bytes.try_into().unwrap()))
+    }
+
+    // a field prefixed with its length
+    fn field(&mut self) -> Result<&'a [u8], anyhow::Error> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+}
+
+// n-ng field, the NUL terminated file path or buffer name
+fn name_field(field: &[u8]) -> Result<String, anyhow::Error> {
+    let name = field.strip_suffix(b"\0").unwrap_or(field);
+    String::from_utf8(name.to_vec())
+        .map_err(|_| anyhow!("[ImaEvent::parse_binary] name is not valid UTF-8"))
+}
+
+fn optional_field(field: &[u8]) -> Option<Vec<u8>> {
+    match field.is_empty() {
+        true => None,
+        false => Some(field.to_vec()),
+    }
+}
+
+/***
+    Entry of the IMA ascii runtime measurement list, e.g.
+        10 <template hash> ima-ng sha256:<hex> /usr/bin/kmod
+    Template fields:
+        ima:        d | n
+        ima-ng:     d-ng | n-ng
+        ima-sig:    d-ng | n-ng | sig
+        ima-buf:    d-ng | n-ng | buf
+        ima-modsig: d-ng | n-ng | sig | d-modsig | modsig
+
+    Attributes:
+        template_hash: hash of the template data extended to the register
+        file_digest: the d or d-ng field
+        path: the n or n-ng field, the file path or the buffer name
+        signature, buffer, modsig_digest, modsig: the other fields, None if the
+            template does not have them or they are empty
+        byte_order: byte order of the field lengths in the template data, big endian
+            only for the binary list of a big endian host, see ImaByteOrder
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ImaEvent {
+    pub imr_index: u32,
+    pub template_hash: Vec<u8>,
+    pub template_name: String,
+    pub file_digest: ImaDigest,
+    pub path: String,
+    pub signature: Option<Vec<u8>>,
+    pub buffer: Option<Vec<u8>>,
+    pub modsig_digest: Option<ImaDigest>,
+    pub modsig: Option<Vec<u8>>,
+    pub byte_order: ImaByteOrder,
+}
+
+impl ImaEvent {
+    pub fn parse(line: &str) -> Result<ImaEvent, anyhow::Error> {
+        let line = line.trim_start_matches(' ').trim_end_matches(['\r', '\n']);
+        let mut elements = line.splitn(4, ' ');
+        let (imr_index, template_hash, template_name, fields) = match (
+            elements.next(),
+            elements.next(),
+            elements.next(),
+            elements.next(),
+        ) {
+            (Some(imr_index), Some(template_hash), Some(template_name), Some(fields)) => {
+                (imr_index, template_hash, template_name, fields)
+            }
+            _ => return Err(anyhow!("[ImaEvent::parse] incomplete IMA record {}", line)),
+        };
+        let imr_index = imr_index
+            .parse::<u32>()
+            .map_err(|_| anyhow!("[ImaEvent::parse] invalid IMR index {}", imr_index))?;
+        let template_hash = decode_hex(template_hash, "ImaEvent::parse")?;
+        ImaEvent::parse_template(imr_index, template_hash, template_name, fields)
+    }
+
+    /***
+        Parse the template fields of an IMA record.
+        Args:
+            fields: the ascii fields after the template name
+    */
+    pub fn parse_template(
+        imr_index: u32,
+        template_hash: Vec<u8>,
+        template_name: &str,
+        fields: &str,
+    ) -> Result<ImaEvent, anyhow::Error> {
+        let (digest, fields) = match fields.split_once(' ') {
+            Some(v) => v,
+            None => {
+                return Err(anyhow!(
+                    "[ImaEvent::parse] missing path in IMA record {}",
+                    fields
+                ))
+            }
+        };
+        let mut event = ImaEvent {
+            imr_index,
+            template_hash,
+            template_name: template_name.to_string(),
+            file_digest: match template_name {
+                IMA_TEMPLATE_IMA => ImaDigest {
+                    algorithm: "sha1".to_string(),
+                    digest: decode_hex(digest, "ImaEvent::parse")?,
+                },
+                _ => ImaDigest::parse(digest)?,
+            },
+            path: fields.to_string(),
+            signature: None,
+            buffer: None,
+            modsig_digest: None,
+            modsig: None,
+            byte_order: ImaByteOrder::Little,
+        };
+
+        match template_name {
+            IMA_TEMPLATE_IMA | IMA_TEMPLATE_IMA_NG => (),
+            IMA_TEMPLATE_IMA_SIG => {
+                let (path, tail) = split_path(fields, 1)?;
+                event.path = path.to_string();
+                event.signature = optional_hex(tail[0])?;
+            }
+            IMA_TEMPLATE_IMA_BUF => {
+                let (path, tail) = split_path(fields, 1)?;
+                event.path = path.to_string();
+                event.buffer = optional_hex(tail[0])?;
+            }
+            IMA_TEMPLATE_IMA_MODSIG => {
+                let (path, tail) = split_path(fields, 3)?;
+                event.path = path.to_string();
+                event.signature = optional_hex(tail[0])?;
+                event.modsig_digest = match tail[1] {
+                    "" => None,
+                    field => Some(ImaDigest::parse(field)?),
+                };
+                event.modsig = optional_hex(tail[2])?;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "[ImaEvent::parse] unsupported IMA template {}",
+                    template_name
+                ))
+            }
+        }
+        Ok(event)
+    }
+
+    /***
+        Template data hashed into the template hash. Each field is prefixed with its
+        length, except for the ima template whose n field is padded to 256 bytes.
+    */
+    pub fn template_data(&self) -> Vec<u8> {
+        let mut path = self.path.as_bytes().to_vec();
+        path.push(0);
+        if self.template_name == IMA_TEMPLATE_IMA {
+            path.resize(IMA_EVENT_NAME_LEN, 0);
+            return [self.file_digest.digest.clone(), path].concat();
+        }
+
+        let mut fields = vec![self.file_digest.template_data(), path];
+        match self.template_name.as_str() {
+            IMA_TEMPLATE_IMA_SIG => fields.push(self.signature.clone().unwrap_or_default()),
+            IMA_TEMPLATE_IMA_BUF => fields.push(self.buffer.clone().unwrap_or_default()),
+            IMA_TEMPLATE_IMA_MODSIG => {
+                fields.push(self.signature.clone().unwrap_or_default());
+                fields.push(
+                    self.modsig_digest
+                        .as_ref()
+                        .map(|digest| digest.template_data())
+                        .unwrap_or_default(),
+                );
+                fields.push(self.modsig.clone().unwrap_or_default());
+            }
+            _ => (),
+        }
+        let mut data = Vec::new();
+        for field in fields {
+            data.extend(self.byte_order.u32_to_bytes(field.len() as u32));
+            data.extend(field);
+        }
+        data
+    }
+
+    // a violation is recorded with a template hash of zeros
+    pub fn is_violation(&self) -> bool {
+        self.template_hash.iter().all(|v| *v == 0)
+    }
+
+    /***
+        Recompute the template hash from the template fields, with the algorithm of
+        the size of the recorded template hash.
+        Returns:
+            true if it is the recorded template hash, violations never match
+    */
+    pub fn check_template_hash(&self) -> Result<bool, anyhow::Error> {
+        let algo_id = u8::try_from(self.template_hash.len())
+            .map(TcgDigest::get_algorithm_id_from_digest_size)
+            .unwrap_or(0);
+        match get_algorithm(algo_id) {
+            Some(algorithm) => Ok(algorithm.hash(&self.template_data()) == self.template_hash),
+            None => Err(anyhow!(
+                "[check_template_hash] no algorithm for a template hash of {} bytes",
+                self.template_hash.len()
+            )),
+        }
+    }
+
+    /***
+        Parse a record of the IMA binary runtime measurement list, e.g.
+        binary_runtime_measurements. Record layout:
+            PCR index (4) | template hash | template name length (4) | template name
+            | template data length (4) | template data
+        The template data of the ima template is the d field and the n field prefixed
+        with its length, without the template data length.
+        Args:
+            data: the binary list, the record starts at its beginning
+            algo_id: algorithm of the template hashes of the list, e.g. TPM_ALG_SHA1
+                     for binary_runtime_measurements
+            byte_order: byte order of the list, see ImaByteOrder
+        Returns:
+            The event and the length of the record
+    */
+    pub fn parse_binary(
+        data: &[u8],
+        algo_id: u16,
+        byte_order: ImaByteOrder,
+    ) -> Result<(ImaEvent, usize), anyhow::Error> {
+        let digest_size = match get_algorithm(algo_id) {
+            Some(algorithm) => algorithm.digest_size(),
+            None => {
+                return Err(anyhow!(
+                    "[ImaEvent::parse_binary] unsupported template hash algorithm {}",
+                    TcgDigest::get_algorithm_name(algo_id)
+                ))
+            }
+        };
+        let mut reader = BinaryReader {
+            data,
+            offset: 0,
+            byte_order,
+        };
+        let imr_index = reader.u32()?;
+        let template_hash = reader.bytes(digest_size)?.to_vec();
+        let template_name = String::from_utf8_lossy(reader.field()?).to_string();
+
+        let mut event = ImaEvent {
+            imr_index,
+            template_hash,
+            template_name,
+            file_digest: ImaDigest {
+                algorithm: "sha1".to_string(),
+                digest: Vec::new(),
+            },
+            path: String::new(),
+            signature: None,
+            buffer: None,
+            modsig_digest: None,
+            modsig: None,
+            byte_order,
+        };
+
+        if event.template_name == IMA_TEMPLATE_IMA {
+            event.file_digest.digest = reader.bytes(20)?.to_vec();
+            event.path = name_field(reader.field()?)?;
+            return Ok((event, reader.offset));
+        }
+
+        let mut template = BinaryReader {
+            data: reader.field()?,
+            offset: 0,
+            byte_order,
+        };
+        let field_count = match event.template_name.as_str() {
+            IMA_TEMPLATE_IMA_NG => 2,
+            IMA_TEMPLATE_IMA_SIG | IMA_TEMPLATE_IMA_BUF => 3,
+            IMA_TEMPLATE_IMA_MODSIG => 5,
+            _ => {
+                return Err(anyhow!(
+                    "[ImaEvent::parse_binary] unsupported IMA template {}",
+                    event.template_name
+                ))
+            }
+        };
+        let mut fields = Vec::new();
+        for _ in 0..field_count {
+            fields.push(template.field()?);
+        }
+        if template.offset != template.data.len() {
+            return Err(anyhow!(
+                "[ImaEvent::parse_binary] {} bytes left in the template data of {}",
+                template.data.len() - template.offset,
+                event.template_name
+            ));
+        }
+
+        event.file_digest = ImaDigest::parse_template_data(fields[0])?;
+        event.path = name_field(fields[1])?;
+        match event.template_name.as_str() {
+            IMA_TEMPLATE_IMA_SIG => event.signature = optional_field(fields[2]),
+            IMA_TEMPLATE_IMA_BUF => event.buffer = optional_field(fields[2]),
+            IMA_TEMPLATE_IMA_MODSIG => {
+                event.signature = optional_field(fields[2]);
+                event.modsig_digest = match fields[3].is_empty() {
+                    true => None,
+                    false => Some(ImaDigest::parse_template_data(fields[3])?),
+                };
+                event.modsig = optional_field(fields[4]);
+            }
+            _ => (),
+        }
+        Ok((event, reader.offset))
+    }
+
+    // parse all the records of a binary runtime measurement list, see parse_binary()
+    pub fn parse_binary_list(
+        data: &[u8],
+        algo_id: u16,
+        byte_order: ImaByteOrder,
+    ) -> Result<Vec<ImaEvent>, anyhow::Error> {
+        let mut events = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let (event, len) = ImaEvent::parse_binary(&data[offset..], algo_id, byte_order)
+                .map_err(|e| anyhow!("{} in IMA record {}", e, events.len()))?;
+            events.push(event);
+            offset += len;
+        }
+        Ok(events)
+    }
+
+    /***
+        The record of the event in the ascii runtime measurement list, e.g. to show
+        the runtime data of EventLogs.
+    */
+    pub fn ascii_record(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.imr_index,
+            hex::encode(&self.template_hash),
+            self.template_name,
+            self.ascii_fields()
+        )
+    }
+
+    // the template fields of the ascii record, after the template name
+    pub fn ascii_fields(&self) -> String {
+        let file_digest = match self.template_name.as_str() {
+            IMA_TEMPLATE_IMA => hex::encode(&self.file_digest.digest),
+            _ => self.file_digest.ascii(),
+        };
+        let optional_hex = |field: &Option<Vec<u8>>| field.as_ref().map(hex::encode);
+        let mut fields = vec![file_digest, self.path.clone()];
+        match self.template_name.as_str() {
+            IMA_TEMPLATE_IMA_SIG => fields.push(optional_hex(&self.signature).unwrap_or_default()),
+            IMA_TEMPLATE_IMA_BUF => fields.push(optional_hex(&self.buffer).unwrap_or_default()),
+            IMA_TEMPLATE_IMA_MODSIG => {
+                fields.push(optional_hex(&self.signature).unwrap_or_default());
+                fields.push(
+                    self.modsig_digest
+                        .as_ref()
+                        .map(|digest| digest.ascii())
+                        .unwrap_or_default(),
+                );
+                fields.push(optional_hex(&self.modsig).unwrap_or_default());
+            }
+            _ => (),
+        }
+        fields.join(" ")
+    }
+
+    pub fn show(&self) {
+        info!(
+            "IMA[{}] {} {}:{} {}",
+            self.imr_index,
+            self.template_name,
+            self.file_digest.algorithm,
+            hex::encode(&self.file_digest.digest),
+            self.path
+        );
+    }
+}
+
+impl EventLogs {
+    // IMA runtime measurements with their template fields
+    pub fn ima_events(&self) -> Result<Vec<ImaEvent>, anyhow::Error> {
+        (0..self.run_time_data().len())
+            .map(|index| self.run_time_ima_event(index))
+            .collect()
+    }
+
+    /***
+        Append IMA events parsed from a binary measurement list as runtime data, they
+        are converted incrementally as append_run_time_data() but kept as parsed, so
+        their template fields and byte order are not lost to the ascii record.
+    */
+    pub fn append_ima_events(&mut self, events: &[ImaEvent]) {
+        self.append_run_time_events(events);
+    }
+}
+
+#[cfg(test)]
+mod test_ima {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::eventlog::ImrIndexFlavour;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    const RUN_TIME_DATA: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test_data/sysroot/sys/kernel/security/integrity/ima/ascii_runtime_measurements"
+    );
+
+    // ascii record of the fields with the template hash computed over them
+    fn record(template_name: &str, fields: &str) -> String {
+        let mut event = ImaEvent::parse_template(10, vec![0; 32], template_name, fields).unwrap();
+        event.template_hash = get_algorithm(TPM_ALG_SHA256)
+            .unwrap()
+            .hash(&event.template_data());
+        format!(
+            "10 {} {} {}",
+            hex::encode(&event.template_hash),
+            template_name,
+            fields
+        )
+    }
+
+    #[test]
+    //fields of each template are typed and the template hash is recomputed
+    fn test_ima_templates() {
+        let run_time_data = std::fs::read_to_string(RUN_TIME_DATA).unwrap();
+        let event = ImaEvent::parse(run_time_data.lines().next().unwrap()).unwrap();
+        assert_eq!(event.template_name, IMA_TEMPLATE_IMA_NG);
+        assert_eq!(event.file_digest.algorithm, "sha384");
+        assert_eq!(event.path, "boot_aggregate");
+        assert!(event.check_template_hash().unwrap());
+
+        let digest = format!("sha256:{}", "ab".repeat(32));
+        let sig = ImaEvent::parse(&record(
+            IMA_TEMPLATE_IMA_SIG,
+            &format!("{} /usr/bin/my tool 030204", digest),
+        ))
+        .unwrap();
+        assert_eq!(sig.path, "/usr/bin/my tool");
+        assert_eq!(sig.signature, Some(vec![0x03, 0x02, 0x04]));
+        assert!(sig.check_template_hash().unwrap());
+
+        let buf = ImaEvent::parse(&record(
+            IMA_TEMPLATE_IMA_BUF,
+            &format!("{} kexec-cmdline 726f6f74", digest),
+        ))
+        .unwrap();
+        assert_eq!(buf.buffer, Some(b"root".to_vec()));
+        assert!(buf.check_template_hash().unwrap());
+
+        let modsig = ImaEvent::parse(&record(
+            IMA_TEMPLATE_IMA_MODSIG,
+            &format!("{} /lib/modules/a.ko  {} 3082", digest, digest),
+        ))
+        .unwrap();
+        assert_eq!(modsig.signature, None);
+        assert_eq!(
+            modsig.modsig_digest.as_ref().unwrap().digest,
+            vec![0xab; 32]
+        );
+        assert_eq!(modsig.modsig, Some(vec![0x30, 0x82]));
+        assert!(modsig.check_template_hash().unwrap());
+
+        let ima = ImaEvent::parse(&record(
+            IMA_TEMPLATE_IMA,
+            &format!("{} /init", "cd".repeat(20)),
+        ))
+        .unwrap();
+        assert_eq!(ima.template_data().len(), 20 + IMA_EVENT_NAME_LEN);
+        assert!(ima.check_template_hash().unwrap());
+
+        let mut tampered = sig.clone();
+        tampered.path = "/usr/bin/other".to_string();
+        assert!(!tampered.check_template_hash().unwrap());
+    }
+
+    fn ima_event(imr_index: u32, template_hash: Vec<u8>) -> EventLogEntry {
+        EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index,
+            event_type: IMA_MEASUREMENT_EVENT,
+            digests: vec![TcgDigest {
+                algo_id: TcgDigest::get_algorithm_id_from_digest_size(template_hash.len() as u8),
+                hash: template_hash,
+            }],
+            event_size: 0,
+            event: Vec::new(),
+        })
+    }
+
+    #[test]
+    //violations are extended as 0xFF and SHA1 template hashes padded to the RTMR bank
+    fn test_ima_replay_semantics() {
+        let sha384 = get_algorithm(TPM_ALG_SHA384).unwrap();
+        let event_logs = vec![ima_event(2, vec![0x11; 20]), ima_event(2, vec![0; 20])];
+
+        let mut padded = vec![0x11; 20];
+        padded.resize(48, 0);
+        let expected =
+            sha384.hash(&[sha384.hash(&[vec![0; 48], padded].concat()), vec![0xff; 48]].concat());
+        let replay_results =
+            EventLogs::replay_with_ima_bank(event_logs.clone(), Some(TPM_ALG_SHA384)).unwrap();
+        assert_eq!(replay_results.get(2, TPM_ALG_SHA384).unwrap(), expected);
+        assert_eq!(
+            EventLogs::replay_by_rtmr(event_logs.clone(), ImrIndexFlavour::CcMr)
+                .unwrap()
+                .get(2, TPM_ALG_SHA384)
+                .unwrap(),
+            expected
+        );
+
+        let sha1 = get_algorithm(TPM_ALG_SHA1).unwrap();
+        let replay_results = EventLogs::replay(event_logs).unwrap();
+        assert!(replay_results.get(2, TPM_ALG_SHA384).is_none());
+        assert_eq!(
+            replay_results.get(2, TPM_ALG_SHA1).unwrap(),
+            sha1.hash(
+                &[
+                    sha1.hash(&[vec![0; 20], vec![0x11; 20]].concat()),
+                    vec![0xff; 20]
+                ]
+                .concat()
+            )
+        );
+        assert!(ima_extend_value(&[1; 48], 32).is_err());
+    }
+
+    #[test]
+    //template hashes of the per algorithm lists are replayed to their banks
+    fn test_ima_bank_lists() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let ima_events: Vec<TcgImrEvent> = event_logs
+            .select(None, None)
+            .unwrap()
+            .into_iter()
+            .filter_map(|event| match event {
+                EventLogEntry::TcgImrEvent(event) if event.event_type == IMA_MEASUREMENT_EVENT => {
+                    Some(event)
+                }
+                _ => None,
+            })
+            .collect();
+        let algorithms: Vec<u16> = ima_events[0].digests.iter().map(|v| v.algo_id).collect();
+        assert_eq!(algorithms, vec![TPM_ALG_SHA384, TPM_ALG_SHA256]);
+
+        let sha256 = get_algorithm(TPM_ALG_SHA256).unwrap();
+        let replay_results = EventLogs::replay(event_logs.select(None, None).unwrap()).unwrap();
+        assert_eq!(
+            replay_results.get(2, TPM_ALG_SHA256).unwrap(),
+            sha256.hash(&[vec![0; 32], ima_events[0].digests[1].hash.clone()].concat())
+        );
+
+        // the lists must record the same measurements
+        let boot_time_data = CcelLoader::with_sysroot(SYSROOT)
+            .load_boot_time_data()
+            .unwrap();
+        let record =
+            |imr_index: u32, hash: &str| format!("{} {} ima-ng sha1:00 /a", imr_index, hash);
+        let mut event_logs = EventLogs::new(
+            boot_time_data,
+            vec![record(2, &"11".repeat(20))],
+            TCG_PCCLIENT_FORMAT,
+        );
+        event_logs.add_ima_bank(TPM_ALG_SHA256, vec![record(2, &"22".repeat(32))]);
+        let count = event_logs.clone().select(None, None).unwrap().len() - 1;
+        event_logs.set_run_time_data(vec![record(3, &"11".repeat(20))]);
+        assert!(event_logs.select(None, None).is_err());
+
+        // the lists must have as many records
+        event_logs.set_run_time_data(vec![record(2, &"11".repeat(20))]);
+        event_logs.append_run_time_data(vec![record(2, &"33".repeat(20))]);
+        assert!(event_logs.select(None, None).is_err());
+        event_logs.add_ima_bank(TPM_ALG_SHA256, vec![record(2, &"44".repeat(32))]);
+        assert_eq!(event_logs.select(None, None).unwrap().len(), count + 2);
+
+        // a bank added after parsing is added to the parsed events
+        let mut event_logs = EventLogs::new(
+            event_logs.boot_time_data().to_vec(),
+            vec![record(2, &"11".repeat(20))],
+            TCG_PCCLIENT_FORMAT,
+        );
+        assert_eq!(event_logs.select(None, None).unwrap().len(), count + 1);
+        event_logs.add_ima_bank(TPM_ALG_SHA256, vec![record(2, &"22".repeat(32))]);
+        let events = event_logs.select(None, None).unwrap();
+        assert_eq!(events.len(), count + 1);
+        match events.last() {
+            Some(EventLogEntry::TcgImrEvent(event)) => assert_eq!(event.digests.len(), 2),
+            _ => panic!("unexpected event"),
+        }
+    }
+
+    // binary record of an event in its byte order
+    fn binary_record(event: &ImaEvent) -> Vec<u8> {
+        let order = event.byte_order;
+        let mut data = order.u32_to_bytes(event.imr_index).to_vec();
+        data.extend(&event.template_hash);
+        data.extend(order.u32_to_bytes(event.template_name.len() as u32));
+        data.extend(event.template_name.as_bytes());
+        if event.template_name == IMA_TEMPLATE_IMA {
+            data.extend(&event.file_digest.digest);
+            data.extend(order.u32_to_bytes(event.path.len() as u32));
+            data.extend(event.path.as_bytes());
+        } else {
+            let template_data = event.template_data();
+            data.extend(order.u32_to_bytes(template_data.len() as u32));
+            data.extend(template_data);
+        }
+        data
+    }
+
+    #[test]
+    //the binary list of the fixture feeds the same events as the ascii list
+    fn test_ima_binary_list() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let ima_events = loader
+            .load_binary_run_time_data(TPM_ALG_SHA384, ImaByteOrder::Little)
+            .unwrap();
+        let run_time_data = loader.load_run_time_data().unwrap();
+        assert_eq!(ima_events.len(), run_time_data.len());
+        assert_eq!(ima_events[0], ImaEvent::parse(&run_time_data[0]).unwrap());
+        assert!(ima_events[0].check_template_hash().unwrap());
+
+        let ascii_events = EventLogs::new(
+            loader.load_boot_time_data().unwrap(),
+            run_time_data,
+            TCG_PCCLIENT_FORMAT,
+        )
+        .select(None, None)
+        .unwrap();
+        let binary_events = loader
+            .load_event_logs_with_binary_list(
+                TCG_PCCLIENT_FORMAT,
+                TPM_ALG_SHA384,
+                ImaByteOrder::Little,
+            )
+            .unwrap()
+            .select(None, None)
+            .unwrap();
+        assert_eq!(binary_events.len(), ascii_events.len());
+        assert_eq!(
+            EventLogs::replay(binary_events).unwrap(),
+            EventLogs::replay(ascii_events).unwrap()
+        );
+        assert!(loader
+            .load_binary_run_time_data(TPM_ALG_SHA256, ImaByteOrder::Little)
+            .is_err());
+    }
+
+    #[test]
+    //binary records of each template are parsed in both byte orders
+    fn test_ima_binary_templates() {
+        let digest = format!("sha256:{}", "ab".repeat(32));
+        let records = [
+            record(
+                IMA_TEMPLATE_IMA_SIG,
+                &format!("{} /usr/bin/my tool 030204", digest),
+            ),
+            record(
+                IMA_TEMPLATE_IMA_BUF,
+                &format!("{} kexec-cmdline 726f6f74", digest),
+            ),
+            record(
+                IMA_TEMPLATE_IMA_MODSIG,
+                &format!("{} /lib/modules/a.ko  {} 3082", digest, digest),
+            ),
+            record(IMA_TEMPLATE_IMA, &format!("{} /init", "cd".repeat(20))),
+        ];
+        for byte_order in [ImaByteOrder::Little, ImaByteOrder::Big] {
+            let mut events = Vec::new();
+            let mut data = Vec::new();
+            for line in &records {
+                let mut event = ImaEvent::parse(line).unwrap();
+                event.byte_order = byte_order;
+                event.template_hash = get_algorithm(TPM_ALG_SHA256)
+                    .unwrap()
+                    .hash(&event.template_data());
+                data.extend(binary_record(&event));
+                events.push(event);
+            }
+            let parsed = ImaEvent::parse_binary_list(&data, TPM_ALG_SHA256, byte_order).unwrap();
+            assert_eq!(parsed, events);
+            for event in parsed {
+                assert!(event.check_template_hash().unwrap());
+                let mut ascii = ImaEvent::parse(&event.ascii_record()).unwrap();
+                ascii.byte_order = byte_order;
+                assert_eq!(ascii, event);
+            }
+        }
+        assert_eq!(
+            ImaEvent::parse(&records[0]).unwrap().ascii_record(),
+            records[0]
+        );
+    }
+
+    #[test]
+    //appended events are kept as parsed instead of their ascii records
+    fn test_append_ima_events() {
+        let loader = CcelLoader::with_sysroot(SYSROOT);
+        let mut events = Vec::new();
+        for path in ["/usr/bin/my tool", "/tmp/name\n"] {
+            let mut event = ImaEvent::parse(&record(
+                IMA_TEMPLATE_IMA_SIG,
+                &format!("sha256:{} {} 030204", "ab".repeat(32), path),
+            ))
+            .unwrap();
+            event.path = path.to_string();
+            event.byte_order = ImaByteOrder::Big;
+            event.template_hash = get_algorithm(TPM_ALG_SHA256)
+                .unwrap()
+                .hash(&event.template_data());
+            events.push(event);
+        }
+        let mut event_logs = EventLogs::new(
+            loader.load_boot_time_data().unwrap(),
+            Vec::new(),
+            TCG_PCCLIENT_FORMAT,
+        );
+        event_logs.append_ima_events(&events);
+        assert_eq!(event_logs.ima_events().unwrap(), events);
+        assert!(ImaEvent::parse(&event_logs.run_time_data()[1]).unwrap() != events[1]);
+
+        let selected = event_logs.select(None, None).unwrap();
+        match selected.last() {
+            Some(EventLogEntry::TcgImrEvent(event)) => {
+                assert_eq!(event.digests[0].hash, events[1].template_hash);
+                assert_eq!(event.event, events[1].ascii_fields().into_bytes());
+            }
+            _ => panic!("unexpected event"),
+        }
+        let checks = event_logs.check_event_digests().unwrap();
+        assert_eq!(checks.len(), checks.iter().filter(|v| v.is_match()).count());
+        assert!(checks
+            .iter()
+            .any(|check| check.event_index == selected.len() - 1));
+    }
+
+    #[test]
+    //malformed records are reported instead of panicking
+    fn test_ima_malformed() {
+        let digest = format!("sha256:{}", "ab".repeat(32));
+        for line in [
+            "",
+            "10",
+            "x 00 ima-ng sha256:00 /a",
+            "10 zz ima-ng sha256:00 /a",
+            "10 00 ima-ng 00 /a",
+            "10 00 ima-ng sha256:00",
+            "10 00 ima-unknown sha256:00 /a",
+            &format!("10 00 ima-sig {}", digest),
+            &format!("10 00 ima-modsig {} /a 00", digest),
+        ] {
+            assert!(ImaEvent::parse(line).is_err(), "{}", line);
+        }
+        let event = ImaEvent::parse("10 00 ima-ng sha256:00 /a").unwrap();
+        assert!(event.is_violation() && event.check_template_hash().is_err());
+
+        let event_logs = EventLogs::new(Vec::new(), vec!["10 zz".to_string()], TCG_PCCLIENT_FORMAT);
+        assert!(event_logs.ima_events().is_err());
+
+        let mut event = ImaEvent::parse(&record(IMA_TEMPLATE_IMA_NG, "sha256:00 /a")).unwrap();
+        let data = binary_record(&event);
+        assert!(ImaEvent::parse_binary(&data, TPM_ALG_SHA256, ImaByteOrder::Little).is_ok());
+        for len in [0, 3, 40, data.len() - 1] {
+            assert!(
+                ImaEvent::parse_binary(&data[..len], TPM_ALG_SHA256, ImaByteOrder::Little).is_err()
+            );
+        }
+        assert!(ImaEvent::parse_binary(&data, TPM_ALG_SHA256, ImaByteOrder::Big).is_err());
+        assert!(ImaEvent::parse_binary(&data, 0, ImaByteOrder::Little).is_err());
+        let mut extra = data.clone();
+        extra.push(0);
+        assert!(ImaEvent::parse_binary_list(&extra, TPM_ALG_SHA256, ImaByteOrder::Little).is_err());
+        event.template_name = "ima-unknown".to_string();
+        assert!(ImaEvent::parse_binary(
+            &binary_record(&event),
+            TPM_ALG_SHA256,
+            ImaByteOrder::Little
+        )
+        .is_err());
+    }
+}
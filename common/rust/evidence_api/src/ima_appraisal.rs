@@ -0,0 +1,361 @@
+use crate::ima::ImaEvent;
+use anyhow::anyhow;
+use log::info;
+
+/***
+    Match a path with a glob pattern.
+        *:  any characters except /
+        **: any characters, including /
+        ?:  one character except /
+    Other characters match themselves.
+*/
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+            [b'*', rest @ ..] => {
+                let segment = path.iter().position(|v| *v == b'/').unwrap_or(path.len());
+                (0..=segment).any(|i| matches(rest, &path[i..]))
+            }
+            [b'?', rest @ ..] => match path {
+                [first, tail @ ..] if *first != b'/' => matches(rest, tail),
+                _ => false,
+            },
+            [first, rest @ ..] => match path {
+                [v, tail @ ..] if v == first => matches(rest, tail),
+                _ => false,
+            },
+        }
+    }
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+/***
+    Policy of the files under a directory, the rule of the deepest directory applies.
+        Exclude: the files are not appraised
+        AllowUnknown: files without digest in the allowlist are accepted, the files with
+                      one must still match it
+        RequireSignature: the files are appraised and must carry a signature, e.g. the
+                          sig field of the ima-sig template
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DirectoryPolicy {
+    Exclude,
+    AllowUnknown,
+    RequireSignature,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DirectoryRule {
+    pub directory: String,
+    pub policy: DirectoryPolicy,
+}
+
+impl DirectoryRule {
+    fn contains(&self, path: &str) -> bool {
+        let directory = self.directory.trim_end_matches('/');
+        path.strip_prefix(directory)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/***
+    Why an IMA measurement is not acceptable.
+        UnknownFile: no digest of the allowlist applies to the path
+        HashMismatch: the file digest is none of the digests allowed for the path
+        UnsignedExecutable: the file is under a directory requiring signatures and
+                            the measurement has no signature
+        Violation: the measurement is a violation, e.g. a file opened for write while
+                   being measured
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AppraisalFailure {
+    UnknownFile,
+    HashMismatch,
+    UnsignedExecutable,
+    Violation,
+}
+
+/***
+    Measurement failing the appraisal.
+        index: index of the measurement in the appraised events
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AppraisalIssue {
+    pub index: usize,
+    pub path: String,
+    pub failure: AppraisalFailure,
+}
+
+/***
+    Result of the appraisal of an IMA measurement list.
+        appraised: number of measurements appraised
+        excluded: number of measurements skipped by an exclude or a directory rule
+        issues: measurements failing the appraisal, in measurement order
+*/
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct AppraisalReport {
+    pub appraised: usize,
+    pub excluded: usize,
+    pub issues: Vec<AppraisalIssue>,
+}
+
+impl AppraisalReport {
+    pub fn is_acceptable(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn show(&self) {
+        info!(
+            "IMA appraisal: {} appraised, {} excluded, {} issues",
+            self.appraised,
+            self.excluded,
+            self.issues.len()
+        );
+        for issue in &self.issues {
+            info!("IMA[{}] {:?}: {}", issue.index, issue.failure, issue.path);
+        }
+    }
+}
+
+/***
+    File hash allowlist to appraise IMA measurements, in the manner of the Keylime
+    runtime policy.
+        digests: path or path glob, see glob_match(), with the file digests allowed
+        excludes: path globs of the measurements not appraised
+        directory_rules: policies of directories, see DirectoryPolicy
+
+    Sample usage:
+        let mut allowlist = ImaAllowlist::new();
+        allowlist.add_digest("/usr/bin/kmod", digest);
+        allowlist.add_exclude("*.log");
+        allowlist.add_directory_rule("/usr/sbin", DirectoryPolicy::RequireSignature);
+        let report = allowlist.appraise(&event_logs.ima_events()?);
+*/
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ImaAllowlist {
+    pub digests: Vec<(String, Vec<Vec<u8>>)>,
+    pub excludes: Vec<String>,
+    pub directory_rules: Vec<DirectoryRule>,
+}
+
+impl ImaAllowlist {
+    pub fn new() -> ImaAllowlist {
+        ImaAllowlist::default()
+    }
+
+    /***
+        Parse a digest list in the output format of sha256sum, each line is a hex
+        digest followed by the path, e.g.
+            <hex>  /usr/bin/kmod
+        Empty lines and lines starting with # are skipped.
+    */
+    pub fn parse_digest_list(data: &str) -> Result<ImaAllowlist, anyhow::Error> {
+        let mut allowlist = ImaAllowlist::new();
+        for (number, line) in data.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (digest, path) = match line.split_once(' ') {
+                Some((digest, path)) => (digest, path.strip_prefix([' ', '*']).unwrap_or(path)),
+                None => {
+                    return Err(anyhow!(
+                        "[parse_digest_list] missing path at line {}",
+                        number + 1
+                    ))
+                }
+            };
+            let digest = hex::decode(digest).map_err(|e| {
+                anyhow!(
+                    "[parse_digest_list] invalid digest at line {}: {}",
+                    number + 1,
+                    e
+                )
+            })?;
+            allowlist.add_digest(path, digest);
+        }
+        Ok(allowlist)
+    }
+
+    // allow a file digest for a path or path glob
+    pub fn add_digest(&mut self, pattern: &str, digest: Vec<u8>) {
+        match self.digests.iter_mut().find(|(v, _)| v == pattern) {
+            Some((_, digests)) => digests.push(digest),
+            None => self.digests.push((pattern.to_string(), vec![digest])),
+        }
+    }
+
+    pub fn add_exclude(&mut self, pattern: &str) {
+        self.excludes.push(pattern.to_string());
+    }
+
+    pub fn add_directory_rule(&mut self, directory: &str, policy: DirectoryPolicy) {
+        self.directory_rules.push(DirectoryRule {
+            directory: directory.to_string(),
+            policy,
+        });
+    }
+
+    // policy of the deepest directory rule containing the path
+    fn directory_policy(&self, path: &str) -> Option<DirectoryPolicy> {
+        self.directory_rules
+            .iter()
+            .filter(|rule| rule.contains(path))
+            .max_by_key(|rule| rule.directory.trim_end_matches('/').len())
+            .map(|rule| rule.policy)
+    }
+
+    // digests allowed for the path by all the matching patterns, None if no pattern matches
+    fn allowed_digests(&self, path: &str) -> Option<Vec<&[u8]>> {
+        let mut matched = false;
+        let mut allowed = Vec::new();
+        for (pattern, digests) in &self.digests {
+            if glob_match(pattern, path) {
+                matched = true;
+                allowed.extend(digests.iter().map(|v| v.as_slice()));
+            }
+        }
+        matched.then_some(allowed)
+    }
+
+    /***
+        Appraise IMA measurements, e.g. EventLogs::ima_events(). The path of an
+        ima-buf measurement is the buffer name, e.g. kexec-cmdline.
+        Returns:
+            The report of the measurements failing the allowlist
+    */
+    pub fn appraise(&self, events: &[ImaEvent]) -> AppraisalReport {
+        let mut report = AppraisalReport::default();
+        for (index, event) in events.iter().enumerate() {
+            let policy = self.directory_policy(&event.path);
+            if policy == Some(DirectoryPolicy::Exclude)
+                || self
+                    .excludes
+                    .iter()
+                    .any(|pattern| glob_match(pattern, &event.path))
+            {
+                report.excluded += 1;
+                continue;
+            }
+            report.appraised += 1;
+
+            let mut issue = |failure: AppraisalFailure| {
+                report.issues.push(AppraisalIssue {
+                    index,
+                    path: event.path.clone(),
+                    failure,
+                })
+            };
+            if event.is_violation() {
+                issue(AppraisalFailure::Violation);
+                continue;
+            }
+            match self.allowed_digests(&event.path) {
+                Some(allowed) if !allowed.contains(&event.file_digest.digest.as_slice()) => {
+                    issue(AppraisalFailure::HashMismatch)
+                }
+                None if policy != Some(DirectoryPolicy::AllowUnknown) => {
+                    issue(AppraisalFailure::UnknownFile)
+                }
+                _ => (),
+            }
+            if policy == Some(DirectoryPolicy::RequireSignature)
+                && event.signature.is_none()
+                && event.modsig.is_none()
+            {
+                issue(AppraisalFailure::UnsignedExecutable);
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod test_ima_appraisal {
+    use super::*;
+
+    fn event(path: &str, digest: u8, signed: bool) -> ImaEvent {
+        let signature = match signed {
+            true => " 030204",
+            false => " ",
+        };
+        ImaEvent::parse(&format!(
+            "10 {} ima-sig sha256:{} {}{}",
+            "11".repeat(32),
+            hex::encode([digest; 32]),
+            path,
+            signature
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    //globs match within and across path segments
+    fn test_glob_match() {
+        assert!(glob_match("/usr/bin/kmod", "/usr/bin/kmod"));
+        assert!(glob_match("/usr/bin/*", "/usr/bin/kmod"));
+        assert!(!glob_match("/usr/bin/*", "/usr/bin/x/kmod"));
+        assert!(glob_match("/usr/lib/**", "/usr/lib/x/y.so"));
+        assert!(glob_match("/usr/lib/**/*.so", "/usr/lib/x/y.so"));
+        assert!(glob_match("/lib/ld-?.so", "/lib/ld-2.so"));
+        assert!(!glob_match("/lib/ld-?.so", "/lib/ld-22.so"));
+        assert!(!glob_match("/usr/bin/kmod", "/usr/bin/kmod2"));
+    }
+
+    #[test]
+    //unknown files, mismatches, unsigned executables and violations are reported
+    fn test_appraise() {
+        let mut allowlist = ImaAllowlist::parse_digest_list(&format!(
+            "# allowlist\n{}  /usr/bin/kmod\n\n{} *boot_aggregate\n",
+            "aa".repeat(32),
+            "bb".repeat(32)
+        ))
+        .unwrap();
+        allowlist.add_digest("/usr/lib/**", vec![0xcc; 32]);
+        allowlist.add_digest("/usr/sbin/init", vec![0xdd; 32]);
+        allowlist.add_exclude("/tmp/**");
+        allowlist.add_directory_rule("/var", DirectoryPolicy::Exclude);
+        allowlist.add_directory_rule("/var/lib/app/", DirectoryPolicy::AllowUnknown);
+        allowlist.add_directory_rule("/usr/sbin", DirectoryPolicy::RequireSignature);
+
+        let events = vec![
+            event("boot_aggregate", 0xbb, false),
+            event("/usr/bin/kmod", 0xaa, false),
+            event("/usr/lib/x/libc.so", 0xcc, false),
+            event("/usr/bin/kmod", 0x01, false),
+            event("/usr/bin/unknown", 0xaa, false),
+            event("/tmp/a b", 0x01, false),
+            event("/var/log/x", 0x01, false),
+            event("/var/lib/app/cache", 0x01, false),
+            event("/usr/sbin/init", 0xdd, true),
+            event("/usr/sbin/init", 0xdd, false),
+            ImaEvent::parse(&format!(
+                "10 {} ima-ng sha256:00 /usr/bin/kmod",
+                "00".repeat(32)
+            ))
+            .unwrap(),
+        ];
+        let report = allowlist.appraise(&events);
+        assert_eq!((report.appraised, report.excluded), (9, 2));
+        let issues: Vec<(usize, AppraisalFailure)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.index, issue.failure))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (3, AppraisalFailure::HashMismatch),
+                (4, AppraisalFailure::UnknownFile),
+                (9, AppraisalFailure::UnsignedExecutable),
+                (10, AppraisalFailure::Violation)
+            ]
+        );
+        assert!(!report.is_acceptable());
+        assert!(allowlist.appraise(&events[..3]).is_acceptable());
+        assert!(ImaAllowlist::parse_digest_list("zz /a").is_err());
+        assert!(ImaAllowlist::parse_digest_list("aa").is_err());
+    }
+}
@@ -1,12 +1,27 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod algorithm;
 pub mod api;
 pub mod api_data;
 pub mod binary_blob;
+pub mod boot_summary;
 pub mod cc_type;
+pub mod ccel;
+pub mod cursor;
+pub mod divergence;
+pub mod event_digest;
 pub mod eventlog;
+pub mod ima;
+pub mod ima_appraisal;
+pub mod mr_index;
+pub mod no_action;
+pub mod replay;
+pub mod revocation;
+pub mod secureboot;
 pub mod tcg;
 pub mod tdx;
 pub mod tpm;
+pub mod uefi;
+pub mod x509;
 pub mod tcgcel;
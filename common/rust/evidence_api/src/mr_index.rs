@@ -0,0 +1,216 @@
+use crate::eventlog::ImrIndexFlavour;
+use crate::tcg::*;
+use crate::tdx::quote::TdxQuoteBody;
+use crate::tdx::rtmr::TdxRTMR;
+use anyhow::anyhow;
+
+pub const CC_MR_COUNT: u8 = 5;
+
+/***
+    TDX measurement registers. MRTD is measured by the TDX module when the TD is
+    built, RTMR0 to RTMR3 are extended at runtime.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TdxRegister {
+    Mrtd,
+    Rtmr(u8),
+}
+
+/***
+    CC measurement register index defined by the UEFI CC measurement protocol
+    (EFI_CC_MEASUREMENT_PROTOCOL.MapPcrToMrIndex) and the TCG CC event log (CCEL).
+    The TPM PCRs measured by the firmware are mapped to the CC MRs as:
+        TPM PCR     CC MR   TDX register
+        0           0       MRTD
+        1, 7        1       RTMR0
+        2 - 6       2       RTMR1
+        8 - 15      3       RTMR2
+        -           4       RTMR3
+    CCEL logs the CC MR index, the event logs keep the RTMR index, see ImrIndexFlavour.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CcMrIndex(pub u8);
+
+impl CcMrIndex {
+    pub fn new(index: u32) -> Result<CcMrIndex, anyhow::Error> {
+        if index >= CC_MR_COUNT as u32 {
+            return Err(anyhow!("[CcMrIndex::new] invalid CC MR index {}", index));
+        }
+        Ok(CcMrIndex(index as u8))
+    }
+
+    pub fn from_tpm_pcr(pcr: u32) -> Result<CcMrIndex, anyhow::Error> {
+        match pcr {
+            0 => Ok(CcMrIndex(0)),
+            1 | 7 => Ok(CcMrIndex(1)),
+            2..=6 => Ok(CcMrIndex(2)),
+            8..=15 => Ok(CcMrIndex(3)),
+            _ => Err(anyhow!(
+                "[CcMrIndex::from_tpm_pcr] PCR {} is not mapped to a CC MR",
+                pcr
+            )),
+        }
+    }
+
+    // TPM PCRs mapped to the CC MR, empty for RTMR3
+    pub fn tpm_pcrs(&self) -> Vec<u32> {
+        match self.0 {
+            0 => vec![0],
+            1 => vec![1, 7],
+            2 => (2..=6).collect(),
+            3 => (8..=15).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn from_tdx_register(register: TdxRegister) -> Result<CcMrIndex, anyhow::Error> {
+        match register {
+            TdxRegister::Mrtd => Ok(CcMrIndex(0)),
+            TdxRegister::Rtmr(index) => CcMrIndex::from_rtmr(index),
+        }
+    }
+
+    pub fn tdx_register(&self) -> Result<TdxRegister, anyhow::Error> {
+        match self.0 {
+            0 => Ok(TdxRegister::Mrtd),
+            _ => Ok(TdxRegister::Rtmr(self.rtmr_index()?)),
+        }
+    }
+
+    pub fn from_rtmr(index: u8) -> Result<CcMrIndex, anyhow::Error> {
+        if index >= CC_MR_COUNT - 1 {
+            return Err(anyhow!(
+                "[CcMrIndex::from_rtmr] invalid RTMR index {}",
+                index
+            ));
+        }
+        Ok(CcMrIndex(index + 1))
+    }
+
+    // RTMR index, which is also the index kept in event logs of CCEL
+    pub fn rtmr_index(&self) -> Result<u8, anyhow::Error> {
+        match self.0 {
+            1..=4 => Ok(self.0 - 1),
+            _ => Err(anyhow!(
+                "[CcMrIndex::rtmr_index] CC MR index {} is not a RTMR",
+                self.0
+            )),
+        }
+    }
+
+    /***
+        CC MR of an event kept in event logs.
+        Args:
+            imr_index: the imr_index of the event
+            index_flavour: flavour of the index in the boot time data the event comes from
+    */
+    pub fn from_event_log_index(
+        imr_index: u32,
+        index_flavour: ImrIndexFlavour,
+    ) -> Result<CcMrIndex, anyhow::Error> {
+        match index_flavour {
+            ImrIndexFlavour::CcMr => match u8::try_from(imr_index) {
+                Ok(index) => CcMrIndex::from_rtmr(index),
+                Err(_) => Err(anyhow!(
+                    "[CcMrIndex::from_event_log_index] invalid RTMR index {}",
+                    imr_index
+                )),
+            },
+            ImrIndexFlavour::TpmPcr => CcMrIndex::from_tpm_pcr(imr_index),
+        }
+    }
+}
+
+impl TdxQuoteBody {
+    // value of the TDX register of a CC MR, the backing of get_cc_measurement() on TDX
+    pub fn cc_measurement(&self, index: CcMrIndex) -> Result<TcgDigest, anyhow::Error> {
+        let value = match index.tdx_register()? {
+            TdxRegister::Mrtd => self.mrtd,
+            TdxRegister::Rtmr(0) => self.rtmr0,
+            TdxRegister::Rtmr(1) => self.rtmr1,
+            TdxRegister::Rtmr(2) => self.rtmr2,
+            TdxRegister::Rtmr(_) => self.rtmr3,
+        };
+        Ok(TcgDigest {
+            algo_id: TPM_ALG_SHA384,
+            hash: value.to_vec(),
+        })
+    }
+}
+
+impl TdxRTMR {
+    pub fn cc_mr_index(&self) -> CcMrIndex {
+        CcMrIndex(self.get_index() + 1)
+    }
+}
+
+#[cfg(test)]
+mod test_mr_index {
+    use super::*;
+    use crate::eventlog::EventLogs;
+
+    #[test]
+    //TPM PCRs, CC MRs and TDX registers are mapped as MapPcrToMrIndex
+    fn test_cc_mr_mapping() {
+        let mrs: Vec<u8> = (0..16)
+            .map(|pcr| CcMrIndex::from_tpm_pcr(pcr).unwrap().0)
+            .collect();
+        assert_eq!(mrs, vec![0, 1, 2, 2, 2, 2, 2, 1, 3, 3, 3, 3, 3, 3, 3, 3]);
+        assert!(CcMrIndex::from_tpm_pcr(16).is_err());
+        for index in 0..CC_MR_COUNT {
+            let index = CcMrIndex(index);
+            for pcr in index.tpm_pcrs() {
+                assert_eq!(CcMrIndex::from_tpm_pcr(pcr).unwrap(), index);
+            }
+            let register = index.tdx_register().unwrap();
+            assert_eq!(CcMrIndex::from_tdx_register(register).unwrap(), index);
+        }
+        assert_eq!(CcMrIndex(0).tdx_register().unwrap(), TdxRegister::Mrtd);
+        assert_eq!(CcMrIndex(1).tdx_register().unwrap(), TdxRegister::Rtmr(0));
+        assert!(CcMrIndex(0).rtmr_index().is_err());
+        assert!(CcMrIndex::new(5).is_err() && CcMrIndex::from_rtmr(4).is_err());
+        assert_eq!(
+            CcMrIndex::from_event_log_index(2, ImrIndexFlavour::CcMr).unwrap(),
+            CcMrIndex(3)
+        );
+        assert_eq!(
+            CcMrIndex::from_event_log_index(2, ImrIndexFlavour::TpmPcr).unwrap(),
+            CcMrIndex(2)
+        );
+        let rtmr = TdxRTMR::new(2, TPM_ALG_SHA384, [0; 48]).unwrap();
+        assert_eq!(rtmr.cc_mr_index(), CcMrIndex(3));
+    }
+
+    #[test]
+    //events of a TPM event log are replayed to the RTMRs of their CC MR
+    fn test_replay_by_rtmr() {
+        let event = |pcr: u32, hash: u8| {
+            EventLogEntry::TcgImrEvent(TcgImrEvent {
+                imr_index: pcr,
+                event_type: EV_POST_CODE,
+                digests: vec![TcgDigest {
+                    algo_id: TPM_ALG_SHA384,
+                    hash: vec![hash; 48],
+                }],
+                event_size: 0,
+                event: Vec::new(),
+            })
+        };
+        let pcr_events = vec![
+            event(0, 0),
+            event(1, 1),
+            event(4, 4),
+            event(7, 7),
+            event(9, 9),
+        ];
+        let by_rtmr =
+            EventLogs::replay_by_rtmr(pcr_events.clone(), ImrIndexFlavour::TpmPcr).unwrap();
+        let rtmr_events = vec![event(0, 1), event(1, 4), event(0, 7), event(2, 9)];
+        assert_eq!(by_rtmr, EventLogs::replay(rtmr_events.clone()).unwrap());
+        assert_eq!(
+            EventLogs::replay_by_rtmr(rtmr_events.clone(), ImrIndexFlavour::CcMr).unwrap(),
+            EventLogs::replay(rtmr_events).unwrap()
+        );
+        assert!(EventLogs::replay_by_rtmr(vec![event(16, 0)], ImrIndexFlavour::TpmPcr).is_err());
+    }
+}
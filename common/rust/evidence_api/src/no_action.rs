@@ -0,0 +1,352 @@
+use crate::binary_blob::*;
+use crate::uefi::*;
+use anyhow::anyhow;
+use log::info;
+
+/***
+    Decoders of the EV_NO_ACTION event data defined in TCG PC Client Platform Firmware
+    Profile Specification at
+    https://trustedcomputinggroup.org/wp-content/uploads/PC-Client-Specific-Platform-Firmware-Profile-Version-1.06-Revision-52_pub.pdf
+    EV_NO_ACTION events are not extended into the registers, the event data starts with
+    a 16 bytes signature identifying the structure.
+    CC specific markers are not decoded on purpose: the UEFI CC measurement protocol
+    used by CCEL reuses the TCG event structures and defines no EV_NO_ACTION payload
+    of its own, so vendor markers are kept raw in NoActionEvent::Other.
+*/
+
+pub const SPEC_ID_EVENT03_SIGNATURE: &[u8; 16] = b"Spec ID Event03\0";
+pub const SPEC_ID_EVENT00_SIGNATURE: &[u8; 16] = b"Spec ID Event00\0";
+pub const STARTUP_LOCALITY_SIGNATURE: &[u8; 16] = b"StartupLocality\0";
+pub const SP800_155_EVENT_SIGNATURE: &[u8; 16] = b"SP800-155 Event\0";
+pub const SP800_155_EVENT3_SIGNATURE: &[u8; 16] = b"SP800-155 Event3";
+pub const NV_INDEX_INSTANCE_SIGNATURE: &[u8; 16] = b"NvIndexInstance\0";
+pub const NV_INDEX_DYNAMIC_SIGNATURE: &[u8; 16] = b"NvIndexDynamic\0\0";
+
+/***
+    Decoded EV_NO_ACTION event data.
+        SpecIdEvent: Specification ID version event, decoded in EventLogs::spec_id_header_event
+        StartupLocality: locality the TPM was started from, 3 or 4 (H-CRTM)
+        Sp800155: SP800-155 platform reference manifest identification
+        NvIndexInstance/NvIndexDynamic: content of NV indices measured in the log
+        Other: EV_NO_ACTION event with an unknown signature, e.g. vendor specific or CC
+               specific markers, kept raw for the caller to decode
+*/
+#[derive(Clone, Debug)]
+pub enum NoActionEvent {
+    SpecIdEvent(Vec<u8>),
+    StartupLocality(u8),
+    Sp800155(Sp800155Event),
+    NvIndexInstance(NvIndexInstanceEvent),
+    NvIndexDynamic(NvIndexDynamicEvent),
+    Other(Vec<u8>),
+}
+
+/***
+    TCG_Sp800_155_PlatformId_Event2 and TCG_Sp800_155_PlatformId_Event3, the RIM
+    and platform certificate locators are only present in Event3.
+    typedef struct tdTCG_Sp800_155_PlatformId_Event3 {
+        BYTE Signature[16];
+        UINT32 PlatformManufacturerId;
+        UEFI_GUID ReferenceManifestGuid;
+        TCG_ByteString PlatformManufacturerStr;
+        TCG_ByteString PlatformModel;
+        TCG_ByteString PlatformVersion;
+        TCG_ByteString FirmwareManufacturerStr;
+        UINT32 FirmwareManufacturerId;
+        TCG_ByteString FirmwareVersion;
+        UINT32 RimLocatorType;
+        TCG_UINT32String RimLocator;
+        UINT32 PlatformCertLocatorType;
+        TCG_UINT32String PlatformCertLocator;
+    } TCG_Sp800_155_PlatformId_Event3;
+*/
+#[derive(Clone, Debug)]
+pub struct Sp800155Event {
+    pub platform_manufacturer_id: u32,
+    pub reference_manifest_guid: EfiGuid,
+    pub platform_manufacturer_str: String,
+    pub platform_model: String,
+    pub platform_version: String,
+    pub firmware_manufacturer_str: String,
+    pub firmware_manufacturer_id: u32,
+    pub firmware_version: String,
+    pub rim_locator: Option<(u32, Vec<u8>)>,
+    pub platform_cert_locator: Option<(u32, Vec<u8>)>,
+}
+
+/***
+    TCG_NvIndexInstanceEventLogData, the TPMS_NV_PUBLIC and NV data following the
+    header are kept raw.
+    typedef struct tdTCG_NvIndexInstanceEventLogData {
+        BYTE Signature[16];
+        UINT16 Version;
+        UINT8 Reserved[6];
+        TPMS_NV_PUBLIC NvPublic;
+        BYTE Data[];
+    } TCG_NvIndexInstanceEventLogData;
+*/
+#[derive(Clone, Debug)]
+pub struct NvIndexInstanceEvent {
+    pub version: u16,
+    pub data: Vec<u8>,
+}
+
+/***
+    TCG_NvIndexDynamicEventLogData
+    typedef struct tdTCG_NvIndexDynamicEventLogData {
+        BYTE Signature[16];
+        UINT16 Version;
+        UINT8 Reserved[6];
+        UINT64 Uid;
+        UINT16 DescriptionSize;
+        BYTE Description[DescriptionSize];
+        UINT16 DataSize;
+        BYTE Data[DataSize];
+    } TCG_NvIndexDynamicEventLogData;
+*/
+#[derive(Clone, Debug)]
+pub struct NvIndexDynamicEvent {
+    pub version: u16,
+    pub uid: u64,
+    pub description: String,
+    pub data: Vec<u8>,
+}
+
+// sequential reader of the variable length fields
+struct Reader<'a> {
+    data: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], anyhow::Error> {
+        check_len(self.data, self.index, len, "NoActionEvent::parse")?;
+        self.index += len;
+        Ok(&self.data[self.index - len..self.index])
+    }
+
+    fn u16(&mut self) -> Result<u16, anyhow::Error> {
+        Ok(get_u16(self.take(2)?.to_vec()))
+    }
+
+    fn u32(&mut self) -> Result<u32, anyhow::Error> {
+        Ok(get_u32(self.take(4)?.to_vec()))
+    }
+
+    fn u64(&mut self) -> Result<u64, anyhow::Error> {
+        Ok(get_u64(self.take(8)?.to_vec()))
+    }
+
+    // TCG_ByteString, UINT8 size followed by the bytes
+    fn byte_string(&mut self) -> Result<String, anyhow::Error> {
+        let len = self.take(1)?[0] as usize;
+        let value = self.take(len)?;
+        let end = value.iter().position(|v| *v == 0).unwrap_or(len);
+        Ok(String::from_utf8_lossy(&value[..end]).to_string())
+    }
+
+    // TCG_UINT32String, UINT32 size followed by the bytes
+    fn u32_string(&mut self) -> Result<Vec<u8>, anyhow::Error> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+impl NoActionEvent {
+    pub fn parse(data: &[u8]) -> Result<NoActionEvent, anyhow::Error> {
+        if data.len() < 16 {
+            return Ok(NoActionEvent::Other(data.to_vec()));
+        }
+        let mut reader = Reader { data, index: 16 };
+        let signature = &data[0..16];
+
+        if signature == SPEC_ID_EVENT03_SIGNATURE || signature == SPEC_ID_EVENT00_SIGNATURE {
+            Ok(NoActionEvent::SpecIdEvent(data.to_vec()))
+        } else if signature == STARTUP_LOCALITY_SIGNATURE {
+            let locality = reader.take(1)?[0];
+            Ok(NoActionEvent::StartupLocality(locality))
+        } else if signature == SP800_155_EVENT_SIGNATURE || signature == SP800_155_EVENT3_SIGNATURE
+        {
+            let mut event = Sp800155Event {
+                platform_manufacturer_id: reader.u32()?,
+                reference_manifest_guid: EfiGuid::parse(reader.take(EfiGuid::LEN)?)?,
+                platform_manufacturer_str: reader.byte_string()?,
+                platform_model: reader.byte_string()?,
+                platform_version: reader.byte_string()?,
+                firmware_manufacturer_str: reader.byte_string()?,
+                firmware_manufacturer_id: reader.u32()?,
+                firmware_version: reader.byte_string()?,
+                rim_locator: None,
+                platform_cert_locator: None,
+            };
+            if signature == SP800_155_EVENT3_SIGNATURE {
+                event.rim_locator = Some((reader.u32()?, reader.u32_string()?));
+                event.platform_cert_locator = Some((reader.u32()?, reader.u32_string()?));
+            }
+            Ok(NoActionEvent::Sp800155(event))
+        } else if signature == NV_INDEX_INSTANCE_SIGNATURE {
+            let version = reader.u16()?;
+            reader.take(6)?;
+            Ok(NoActionEvent::NvIndexInstance(NvIndexInstanceEvent {
+                version,
+                data: data[reader.index..].to_vec(),
+            }))
+        } else if signature == NV_INDEX_DYNAMIC_SIGNATURE {
+            let version = reader.u16()?;
+            reader.take(6)?;
+            let uid = reader.u64()?;
+            let description_size = reader.u16()? as usize;
+            let description = reader.take(description_size)?;
+            let data_size = reader.u16()? as usize;
+            Ok(NoActionEvent::NvIndexDynamic(NvIndexDynamicEvent {
+                version,
+                uid,
+                description: String::from_utf8_lossy(description)
+                    .trim_end_matches('\0')
+                    .to_string(),
+                data: reader.take(data_size)?.to_vec(),
+            }))
+        } else {
+            Ok(NoActionEvent::Other(data.to_vec()))
+        }
+    }
+
+    pub fn show(&self) {
+        match self {
+            NoActionEvent::StartupLocality(locality) => {
+                info!("        StartupLocality   : {}", locality);
+            }
+            NoActionEvent::Sp800155(event) => {
+                info!(
+                    "        SP800-155         : {} {} {} firmware {} {}",
+                    event.platform_manufacturer_str,
+                    event.platform_model,
+                    event.platform_version,
+                    event.firmware_manufacturer_str,
+                    event.firmware_version
+                );
+                info!(
+                    "        ReferenceManifest : {}",
+                    event.reference_manifest_guid
+                );
+            }
+            NoActionEvent::NvIndexDynamic(event) => {
+                info!(
+                    "        NvIndexDynamic    : uid 0x{:x} {}",
+                    event.uid, event.description
+                );
+                dump_data(&event.data);
+            }
+            NoActionEvent::NvIndexInstance(event) => {
+                info!("        NvIndexInstance   : version {}", event.version);
+                dump_data(&event.data);
+            }
+            NoActionEvent::SpecIdEvent(data) | NoActionEvent::Other(data) => dump_data(data),
+        }
+    }
+}
+
+/***
+    Initial value of TPM PCR0 according to the StartupLocality event, the locality is
+    put in the last byte. PCRs start with all zeros otherwise.
+*/
+pub fn startup_locality_initial_value(
+    locality: u8,
+    digest_size: usize,
+) -> Result<Vec<u8>, anyhow::Error> {
+    if digest_size == 0 {
+        return Err(anyhow!(
+            "[startup_locality_initial_value] invalid digest size 0"
+        ));
+    }
+    let mut value = vec![0; digest_size];
+    value[digest_size - 1] = locality;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test_no_action {
+    use super::*;
+
+    fn byte_string(value: &str) -> Vec<u8> {
+        let mut data = vec![value.len() as u8];
+        data.extend(value.as_bytes());
+        data
+    }
+
+    #[test]
+    //StartupLocality carries the locality in one byte
+    fn test_startup_locality() {
+        let mut data = STARTUP_LOCALITY_SIGNATURE.to_vec();
+        data.push(3);
+        assert!(matches!(
+            NoActionEvent::parse(&data).unwrap(),
+            NoActionEvent::StartupLocality(3)
+        ));
+        assert!(NoActionEvent::parse(&data[..16]).is_err());
+        assert_eq!(
+            startup_locality_initial_value(3, 4).unwrap(),
+            vec![0, 0, 0, 3]
+        );
+    }
+
+    #[test]
+    //SP800-155 Event3 has the locators following the Event2 fields
+    fn test_sp800_155_event3() {
+        let mut data = SP800_155_EVENT3_SIGNATURE.to_vec();
+        data.extend(0x1234_u32.to_le_bytes());
+        data.extend([0x11; 16]);
+        data.extend(byte_string("Vendor"));
+        data.extend(byte_string("Model"));
+        data.extend(byte_string("1.0"));
+        data.extend(byte_string("FwVendor"));
+        data.extend(0x5678_u32.to_le_bytes());
+        data.extend(byte_string("2.0"));
+        data.extend(1_u32.to_le_bytes());
+        data.extend(3_u32.to_le_bytes());
+        data.extend(b"rim");
+        data.extend(2_u32.to_le_bytes());
+        data.extend(0_u32.to_le_bytes());
+        match NoActionEvent::parse(&data).unwrap() {
+            NoActionEvent::Sp800155(event) => {
+                assert_eq!(event.platform_manufacturer_id, 0x1234);
+                assert_eq!(event.platform_model, "Model");
+                assert_eq!(event.firmware_version, "2.0");
+                assert_eq!(event.rim_locator, Some((1, b"rim".to_vec())));
+                assert_eq!(event.platform_cert_locator, Some((2, Vec::new())));
+            }
+            e => panic!("unexpected event {:?}", e),
+        }
+        assert!(NoActionEvent::parse(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    //NvIndexDynamic carries description and data
+    fn test_nv_index_dynamic() {
+        let mut data = NV_INDEX_DYNAMIC_SIGNATURE.to_vec();
+        data.extend(1_u16.to_le_bytes());
+        data.extend([0; 6]);
+        data.extend(0x42_u64.to_le_bytes());
+        data.extend(5_u16.to_le_bytes());
+        data.extend(b"test\0");
+        data.extend(2_u16.to_le_bytes());
+        data.extend([0xaa, 0xbb]);
+        match NoActionEvent::parse(&data).unwrap() {
+            NoActionEvent::NvIndexDynamic(event) => {
+                assert_eq!(event.uid, 0x42);
+                assert_eq!(event.description, "test");
+                assert_eq!(event.data, vec![0xaa, 0xbb]);
+            }
+            e => panic!("unexpected event {:?}", e),
+        }
+    }
+
+    #[test]
+    //unknown signatures are kept raw
+    fn test_other_no_action_event() {
+        assert!(matches!(
+            NoActionEvent::parse(b"Unknown marker\0\0\x01").unwrap(),
+            NoActionEvent::Other(_)
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::api_data::{ReplayResult, ReplayResults};
+use crate::mr_index::{CcMrIndex, CC_MR_COUNT};
+use crate::tcg::*;
+use crate::tdx::quote::TdxQuoteBody;
+use log::info;
+
+/***
+    Comparison of one replayed IMR bank with the value read from the register.
+        register: the value of the register, e.g. from a quote
+        replayed: the replayed measurement, the initial value of zeros if no event is
+                  logged to the register with the algorithm
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RegisterCheck {
+    pub imr_index: u32,
+    pub algo_id: u16,
+    pub register: Vec<u8>,
+    pub replayed: Vec<u8>,
+}
+
+impl RegisterCheck {
+    pub fn is_match(&self) -> bool {
+        self.register == self.replayed
+    }
+
+    pub fn show(&self) {
+        if self.is_match() {
+            info!(
+                "IMR[{}] {}: match",
+                self.imr_index,
+                TcgDigest::get_algorithm_name(self.algo_id)
+            );
+        } else {
+            info!(
+                "IMR[{}] {}: MISMATCH, register {} replayed {}",
+                self.imr_index,
+                TcgDigest::get_algorithm_name(self.algo_id),
+                hex::encode(&self.register),
+                hex::encode(&self.replayed)
+            );
+        }
+    }
+}
+
+impl ReplayResults {
+    // replayed measurement of a bank, None if no event is logged to it
+    pub fn get(&self, imr_index: u32, algo_id: u16) -> Option<&[u8]> {
+        self.imrs
+            .get(&imr_index)
+            .and_then(|banks| banks.get(&algo_id))
+            .map(|value| value.as_slice())
+    }
+
+    // IMR indexes with events logged, in ascending order
+    pub fn imr_indexes(&self) -> Vec<u32> {
+        self.imrs.keys().copied().collect()
+    }
+
+    // replay results of each IMR, the banks are ordered by algorithm id
+    pub fn results(&self) -> Vec<ReplayResult> {
+        self.imrs
+            .iter()
+            .map(|(imr_index, banks)| ReplayResult {
+                imr_index: *imr_index,
+                digests: banks
+                    .iter()
+                    .map(|(algo_id, hash)| TcgDigest {
+                        algo_id: *algo_id,
+                        hash: hash.clone(),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn show(&self) {
+        for result in self.results() {
+            result.show();
+        }
+    }
+
+    fn check(&self, imr_index: u32, algo_id: u16, register: &[u8]) -> RegisterCheck {
+        let replayed = match self.get(imr_index, algo_id) {
+            Some(value) => value.to_vec(),
+            None => vec![0; register.len()],
+        };
+        RegisterCheck {
+            imr_index,
+            algo_id,
+            register: register.to_vec(),
+            replayed,
+        }
+    }
+
+    /***
+        Compare the replayed measurements with register values, the IMR index of a
+        register is the index the events are logged to, e.g. the RTMR index for CCEL.
+        Args:
+            imrs: the registers, e.g. TdxRTMR
+            algo_id: the bank of the registers to compare
+        Returns:
+            A check per register, or an error if the registers do not support the algorithm
+    */
+    pub fn verify_against<T: TcgIMR>(
+        &self,
+        imrs: &[T],
+        algo_id: u16,
+    ) -> Result<Vec<RegisterCheck>, anyhow::Error> {
+        T::is_valid_algo(algo_id)?;
+        Ok(imrs
+            .iter()
+            .map(|imr| {
+                self.check(
+                    imr.get_index() as u32,
+                    algo_id,
+                    &imr.get_tcg_digest(algo_id).hash,
+                )
+            })
+            .collect())
+    }
+
+    /***
+        Compare the replayed SHA384 measurements with RTMR0 to RTMR3 of a TD quote.
+        The replay results must be keyed by RTMR index, e.g. the replay of CCEL or the
+        results of EventLogs::replay_by_rtmr().
+        Returns:
+            A check per RTMR, in RTMR index order
+    */
+    pub fn verify_against_quote(
+        &self,
+        body: &TdxQuoteBody,
+    ) -> Result<Vec<RegisterCheck>, anyhow::Error> {
+        let mut checks = Vec::new();
+        for index in (1..CC_MR_COUNT).map(CcMrIndex) {
+            let register = body.cc_measurement(index)?;
+            checks.push(self.check(index.rtmr_index()? as u32, register.algo_id, &register.hash));
+        }
+        Ok(checks)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_replay {
+    use super::*;
+    use crate::algorithm::get_algorithm;
+    use crate::eventlog::EventLogs;
+    use crate::tdx::rtmr::TdxRTMR;
+
+    pub(crate) fn quote_body(rtmrs: [[u8; 48]; 4]) -> TdxQuoteBody {
+        TdxQuoteBody {
+            tee_tcb_svn: [0; 16],
+            mrseam: [0; 48],
+            mrseam_signer: [0; 48],
+            seam_attributes: [0; 8],
+            td_attributes: [0; 8],
+            xfam: [0; 8],
+            mrtd: [0; 48],
+            mrconfigid: [0; 48],
+            mrowner: [0; 48],
+            mrownerconfig: [0; 48],
+            rtmr0: rtmrs[0],
+            rtmr1: rtmrs[1],
+            rtmr2: rtmrs[2],
+            rtmr3: rtmrs[3],
+            report_data: [0; 64],
+        }
+    }
+
+    fn two_bank_event(imr_index: u32, hash: u8) -> EventLogEntry {
+        EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index,
+            event_type: EV_POST_CODE,
+            digests: vec![
+                TcgDigest {
+                    algo_id: TPM_ALG_SHA256,
+                    hash: vec![hash; 32],
+                },
+                TcgDigest {
+                    algo_id: TPM_ALG_SHA384,
+                    hash: vec![hash; 48],
+                },
+            ],
+            event_size: 0,
+            event: Vec::new(),
+        })
+    }
+
+    fn extend(algo_id: u16, hashes: &[Vec<u8>]) -> Vec<u8> {
+        let algorithm = get_algorithm(algo_id).unwrap();
+        hashes
+            .iter()
+            .fold(vec![0; algorithm.digest_size()], |value, hash| {
+                algorithm.hash(&[value, hash.clone()].concat())
+            })
+    }
+
+    #[test]
+    //each bank of an IMR is replayed separately
+    fn test_replay_banks() {
+        let replay_results = EventLogs::replay(vec![
+            two_bank_event(1, 0x11),
+            two_bank_event(1, 0x22),
+            two_bank_event(2, 0x33),
+        ])
+        .unwrap();
+        assert_eq!(replay_results.imr_indexes(), vec![1, 2]);
+        assert_eq!(
+            replay_results.get(1, TPM_ALG_SHA256).unwrap(),
+            extend(TPM_ALG_SHA256, &[vec![0x11; 32], vec![0x22; 32]])
+        );
+        assert_eq!(
+            replay_results.get(1, TPM_ALG_SHA384).unwrap(),
+            extend(TPM_ALG_SHA384, &[vec![0x11; 48], vec![0x22; 48]])
+        );
+        assert!(replay_results.get(0, TPM_ALG_SHA384).is_none());
+        let results = replay_results.results();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].digests[0].algo_id, TPM_ALG_SHA256);
+        assert_eq!(results[0].digests[1].algo_id, TPM_ALG_SHA384);
+    }
+
+    #[test]
+    //replayed banks are compared with RTMRs and the RTMRs of a quote
+    fn test_verify_against() {
+        let replay_results =
+            EventLogs::replay(vec![two_bank_event(0, 0x11), two_bank_event(2, 0x33)]).unwrap();
+        let rtmr0: [u8; 48] = extend(TPM_ALG_SHA384, &[vec![0x11; 48]])
+            .try_into()
+            .unwrap();
+        let rtmrs = vec![
+            TdxRTMR::new(0, TPM_ALG_SHA384, rtmr0).unwrap(),
+            TdxRTMR::new(1, TPM_ALG_SHA384, [0; 48]).unwrap(),
+            TdxRTMR::new(2, TPM_ALG_SHA384, [0x33; 48]).unwrap(),
+        ];
+        let checks = replay_results
+            .verify_against(&rtmrs, TPM_ALG_SHA384)
+            .unwrap();
+        assert!(checks[0].is_match() && checks[1].is_match());
+        assert!(!checks[2].is_match());
+        assert_eq!(
+            checks[2].replayed,
+            replay_results.get(2, TPM_ALG_SHA384).unwrap()
+        );
+        assert!(replay_results
+            .verify_against(&rtmrs, TPM_ALG_SHA256)
+            .is_err());
+
+        let body = quote_body([rtmr0, [0; 48], [0; 48], [0; 48]]);
+        let checks = replay_results.verify_against_quote(&body).unwrap();
+        assert_eq!(checks.len(), 4);
+        let matches: Vec<bool> = checks.iter().map(|check| check.is_match()).collect();
+        assert_eq!(matches, vec![true, true, false, true]);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::algorithm::get_algorithm;
+use crate::secureboot::SecureBootState;
+use crate::tcg::*;
+use crate::uefi::signature::*;
+use crate::uefi::EfiGuid;
+use anyhow::anyhow;
+use core::fmt;
+
+/***
+    SBAT revocation list, the content of the SbatLevel variable, e.g.
+        sbat,1,2022052400
+        grub,2
+    Each line is a component name and the minimum generation accepted, the first
+    line is the version of the SBAT format and the date stamp of the list.
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SbatLevel {
+    pub datestamp: Option<String>,
+    pub generations: Vec<(String, u32)>,
+}
+
+impl SbatLevel {
+    pub fn parse(text: &str) -> Result<SbatLevel, anyhow::Error> {
+        let mut level = SbatLevel {
+            datestamp: None,
+            generations: Vec::new(),
+        };
+        for line in text.lines().map(|line| line.trim()) {
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').collect();
+            let generation = match fields.get(1).and_then(|v| v.trim().parse::<u32>().ok()) {
+                Some(v) => v,
+                None => return Err(anyhow!("[SbatLevel::parse] invalid SBAT line {}", line)),
+            };
+            if fields[0] == "sbat" {
+                level.datestamp = fields.get(2).map(|v| v.trim().to_string());
+            }
+            level.generations.push((fields[0].to_string(), generation));
+        }
+        Ok(level)
+    }
+
+    // minimum generation accepted, components not listed are not revoked
+    pub fn generation(&self, component: &str) -> u32 {
+        self.generations
+            .iter()
+            .find(|(name, _)| name == component)
+            .map_or(1, |(_, generation)| *generation)
+    }
+}
+
+/***
+    Reason why a boot component is revoked.
+        ImageHash: the Authenticode hash measured for the image is in dbx
+        Certificate: the certificate authorizing the image is in dbx
+        CertificateHash: the hash of the TBSCertificate of the certificate authorizing
+                         the image is in dbx
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RevocationReason {
+    ImageHash { algo_id: u16, hash: Vec<u8> },
+    Certificate { subject: String },
+    CertificateHash { subject: String, algo_id: u16 },
+}
+
+// revoked component, the image path
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Revocation {
+    pub component: String,
+    pub reason: RevocationReason,
+}
+
+impl fmt::Display for Revocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.reason {
+            RevocationReason::ImageHash { algo_id, hash } => write!(
+                f,
+                "{}: {} Authenticode hash {} is revoked by dbx",
+                self.component,
+                TcgDigest::get_algorithm_name(*algo_id),
+                hex::encode(hash)
+            ),
+            RevocationReason::Certificate { subject } => write!(
+                f,
+                "{}: signing certificate {} is revoked by dbx",
+                self.component, subject
+            ),
+            RevocationReason::CertificateHash { subject, algo_id } => write!(
+                f,
+                "{}: {} hash of signing certificate {} is revoked by dbx",
+                self.component,
+                TcgDigest::get_algorithm_name(*algo_id),
+                subject
+            ),
+        }
+    }
+}
+
+/***
+    Finding of the SBAT policy check, about the measured SbatLevel policy rather than
+    the loaded images, whose SBAT generations are not measured.
+        OutdatedGeneration: the measured SbatLevel accepts generations of the component
+                            that the SbatLevel supplied by the caller revokes
+        SbatLevelNotMeasured: SbatLevel is not measured, SBAT is not enforced
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SbatFinding {
+    OutdatedGeneration {
+        component: String,
+        measured: u32,
+        required: u32,
+    },
+    SbatLevelNotMeasured,
+}
+
+impl fmt::Display for SbatFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SbatFinding::OutdatedGeneration {
+                component,
+                measured,
+                required,
+            } => write!(
+                f,
+                "{}: SbatLevel accepts generation {} but generations below {} are revoked",
+                component, measured, required
+            ),
+            SbatFinding::SbatLevelNotMeasured => write!(f, "SbatLevel is not measured"),
+        }
+    }
+}
+
+// algorithm of the hashes in dbx signature lists
+fn dbx_hash_algorithm(signature_type: EfiGuid) -> Option<u16> {
+    match signature_type {
+        EFI_CERT_SHA1_GUID => Some(TPM_ALG_SHA1),
+        EFI_CERT_SHA256_GUID | EFI_CERT_X509_SHA256_GUID => Some(TPM_ALG_SHA256),
+        EFI_CERT_SHA384_GUID | EFI_CERT_X509_SHA384_GUID => Some(TPM_ALG_SHA384),
+        EFI_CERT_SHA512_GUID | EFI_CERT_X509_SHA512_GUID => Some(TPM_ALG_SHA512),
+        _ => None,
+    }
+}
+
+impl SecureBootState {
+    /***
+        Appraise the loaded boot components against the measured dbx.
+        The digests of the image load events are the Authenticode hashes of the images,
+        they are compared with the dbx hashes of the same algorithm. The certificates
+        measured as new authorities of the images are compared with the dbx certificates
+        and certificate hashes, so a revoked certificate is reported for the image it
+        was first measured for.
+        Returns:
+            The revoked components, empty if nothing is revoked
+    */
+    pub fn appraise_revocations(&self) -> Result<Vec<Revocation>, anyhow::Error> {
+        let mut revocations = Vec::new();
+
+        for image in &self.image_authorities {
+            for digest in &image.digests {
+                let revoked = self.dbx.iter().any(|list| {
+                    dbx_hash_algorithm(list.signature_type) == Some(digest.algo_id)
+                        && list.hashes().contains(&digest.hash)
+                });
+                if revoked {
+                    revocations.push(Revocation {
+                        component: image.image_path.clone(),
+                        reason: RevocationReason::ImageHash {
+                            algo_id: digest.algo_id,
+                            hash: digest.hash.clone(),
+                        },
+                    });
+                }
+            }
+
+            for certificate in image
+                .new_authorities
+                .iter()
+                .filter_map(|authority| authority.certificate.as_ref())
+            {
+                for list in &self.dbx {
+                    let reason = match list.signature_type {
+                        EFI_CERT_X509_GUID => list
+                            .signatures
+                            .iter()
+                            .any(|signature| signature.signature_data == certificate.der)
+                            .then(|| RevocationReason::Certificate {
+                                subject: certificate.subject.clone(),
+                            }),
+                        EFI_CERT_X509_SHA256_GUID
+                        | EFI_CERT_X509_SHA384_GUID
+                        | EFI_CERT_X509_SHA512_GUID => {
+                            // EFI_CERT_X509_SHA*: ToBeSignedHash followed by TimeOfRevocation
+                            let algo_id = dbx_hash_algorithm(list.signature_type).unwrap();
+                            let hash = match get_algorithm(algo_id) {
+                                Some(algorithm) => algorithm.hash(&certificate.tbs_certificate),
+                                None => continue,
+                            };
+                            list.signatures
+                                .iter()
+                                .any(|signature| signature.signature_data.starts_with(&hash))
+                                .then(|| RevocationReason::CertificateHash {
+                                    subject: certificate.subject.clone(),
+                                    algo_id,
+                                })
+                        }
+                        _ => None,
+                    };
+                    if let Some(reason) = reason {
+                        revocations.push(Revocation {
+                            component: image.image_path.clone(),
+                            reason,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(revocations)
+    }
+
+    /***
+        Appraise the measured SbatLevel policy against the SbatLevel expected by the
+        caller. Shim measures the SbatLevel it enforces but not the SBAT generations
+        of the images it loads, so the findings show that the platform still accepts
+        component generations the caller considers revoked, not that a revoked
+        generation was actually loaded.
+        Args:
+            sbat_revocations: the SbatLevel expected by the caller, e.g. the latest
+                              SbatLevel published by shim
+        Returns:
+            The policy findings, empty if the measured SbatLevel is up to date
+    */
+    pub fn appraise_sbat_level(
+        &self,
+        sbat_revocations: &SbatLevel,
+    ) -> Result<Vec<SbatFinding>, anyhow::Error> {
+        let measured_level = match &self.sbat_level {
+            Some(sbat_level) => SbatLevel::parse(sbat_level)?,
+            None => return Ok(vec![SbatFinding::SbatLevelNotMeasured]),
+        };
+        let mut findings = Vec::new();
+        for (component, required) in &sbat_revocations.generations {
+            let measured = measured_level.generation(component);
+            if measured < *required {
+                findings.push(SbatFinding::OutdatedGeneration {
+                    component: component.clone(),
+                    measured,
+                    required: *required,
+                });
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod test_revocation {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::secureboot::test_secureboot::{image_load_event, imr_event};
+    use crate::uefi::signature::test_uefi_signature::signature_list;
+    use crate::uefi::variable::test_uefi_variable::variable_data;
+    use crate::uefi::EFI_IMAGE_SECURITY_DATABASE_GUID;
+    use crate::x509::test_x509::test_certificate;
+    use crate::x509::X509Certificate;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    #[test]
+    //measured SbatLevel is older than the caller's, reported apart from revocations
+    fn test_sbat_level_findings() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let state = event_logs.secure_boot_state().unwrap();
+
+        let current = SbatLevel::parse("sbat,1,2022052400\ngrub,2\n").unwrap();
+        assert_eq!(current.datestamp.as_deref(), Some("2022052400"));
+        assert!(state.appraise_sbat_level(&current).unwrap().is_empty());
+
+        let latest = SbatLevel::parse("sbat,1,2023012900\nshim,2\ngrub,3\n").unwrap();
+        let findings = state.appraise_sbat_level(&latest).unwrap();
+        assert_eq!(
+            findings,
+            vec![
+                SbatFinding::OutdatedGeneration {
+                    component: "shim".to_string(),
+                    measured: 1,
+                    required: 2
+                },
+                SbatFinding::OutdatedGeneration {
+                    component: "grub".to_string(),
+                    measured: 2,
+                    required: 3
+                }
+            ]
+        );
+        assert_eq!(
+            findings[1].to_string(),
+            "grub: SbatLevel accepts generation 2 but generations below 3 are revoked"
+        );
+        assert!(state.appraise_revocations().unwrap().is_empty());
+        assert!(SbatLevel::parse("sbat\n").is_err());
+    }
+
+    #[test]
+    //image hash and signing certificate revoked by dbx
+    fn test_dbx_revocations() {
+        let certificate = test_certificate("Revoked CA", 2);
+        let tbs_hash = get_algorithm(TPM_ALG_SHA256).unwrap().hash(
+            &X509Certificate::parse(&certificate)
+                .unwrap()
+                .tbs_certificate,
+        );
+        let mut dbx = signature_list(EFI_CERT_SHA256_GUID, &[vec![3; 32]]);
+        dbx.extend(signature_list(
+            EFI_CERT_X509_SHA256_GUID,
+            &[[tbs_hash, vec![0; 16]].concat()],
+        ));
+        let mut authority = vec![0x77; 16];
+        authority.extend(&certificate);
+        let event_logs = vec![
+            imr_event(
+                EV_EFI_VARIABLE_DRIVER_CONFIG,
+                0,
+                variable_data(EFI_IMAGE_SECURITY_DATABASE_GUID, "dbx", &dbx),
+            ),
+            image_load_event("\\EFI\\BOOT\\BOOTX64.EFI", 3),
+            imr_event(
+                EV_EFI_VARIABLE_AUTHORITY,
+                1,
+                variable_data(EFI_IMAGE_SECURITY_DATABASE_GUID, "db", &authority),
+            ),
+            image_load_event("\\EFI\\ubuntu\\grubx64.efi", 4),
+        ];
+
+        let state = SecureBootState::from_event_logs(&event_logs);
+        let revocations = state.appraise_revocations().unwrap();
+        assert_eq!(revocations.len(), 2);
+        assert_eq!(revocations[0].component, "\\EFI\\BOOT\\BOOTX64.EFI");
+        assert!(matches!(
+            revocations[0].reason,
+            RevocationReason::ImageHash {
+                algo_id: TPM_ALG_SHA256,
+                ..
+            }
+        ));
+        assert_eq!(
+            revocations[1].to_string(),
+            "\\EFI\\ubuntu\\grubx64.efi: TPM_ALG_SHA256 hash of signing certificate O=Test, CN=Revoked CA is revoked by dbx"
+        );
+        assert_eq!(
+            state
+                .appraise_sbat_level(&SbatLevel::parse("sbat,1\n").unwrap())
+                .unwrap(),
+            vec![SbatFinding::SbatLevelNotMeasured]
+        );
+    }
+}
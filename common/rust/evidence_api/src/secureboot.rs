@@ -0,0 +1,438 @@
+use crate::eventlog::EventLogs;
+use crate::tcg::*;
+use crate::uefi::firmware::description_to_string;
+use crate::uefi::signature::EfiSignatureList;
+use crate::uefi::variable::{UefiVariableData, UefiVariableValue};
+use crate::uefi::EfiGuid;
+use crate::x509::X509Certificate;
+use log::info;
+
+pub const SBAT_LEVEL_VARIABLE: &str = "SbatLevel";
+
+/***
+    Secure Boot state measured in the boot time event logs.
+
+    Attributes:
+        secure_boot: value of the SecureBoot variable, None if it is not measured
+        pk, kek, db, dbx: signature lists of the signature database variables
+        image_authorities: EFI applications loaded by the firmware, in load order, with
+                           the EV_EFI_VARIABLE_AUTHORITY events that may have verified them
+        sbat_level: SBAT revocations applied by shim, None if shim did not measure it
+        malformed_events: measured variables and images that could not be decoded, they
+                          are left out of the state above
+*/
+#[derive(Clone, Default)]
+pub struct SecureBootState {
+    pub secure_boot: Option<bool>,
+    pub pk: Vec<EfiSignatureList>,
+    pub kek: Vec<EfiSignatureList>,
+    pub db: Vec<EfiSignatureList>,
+    pub dbx: Vec<EfiSignatureList>,
+    pub image_authorities: Vec<ImageAuthority>,
+    pub sbat_level: Option<String>,
+    pub malformed_events: Vec<MalformedEvent>,
+}
+
+/***
+    Event of the Secure Boot state whose data could not be decoded.
+        event_type: type of the event
+        imr_index: the register the event is measured to
+        variable_name: unicode name of the variable, None if it could not be decoded
+        error: the decoding error
+*/
+#[derive(Clone, Debug)]
+pub struct MalformedEvent {
+    pub event_type: u32,
+    pub imr_index: u32,
+    pub variable_name: Option<String>,
+    pub error: String,
+}
+
+/***
+    Authority measured in an EV_EFI_VARIABLE_AUTHORITY event. The firmware measures
+    the EFI_SIGNATURE_DATA of the db entry used to verify an image, shim measures its
+    vendor certificate as it is.
+        variable_name, unicode_name: the variable the authority comes from, e.g. db
+        signature_owner: owner of the EFI_SIGNATURE_DATA if any
+        certificate: the X.509 certificate, None for hash entries
+        data: the raw variable data
+*/
+#[derive(Clone)]
+pub struct SignatureAuthority {
+    pub variable_name: EfiGuid,
+    pub unicode_name: String,
+    pub signature_owner: Option<EfiGuid>,
+    pub certificate: Option<X509Certificate>,
+    pub data: Vec<u8>,
+}
+
+/***
+    EFI application measured in an EV_EFI_BOOT_SERVICES_APPLICATION event and the
+    authorities that may have verified it. An authority is only measured the first
+    time it is used, so the log tells which authority verified an image only when
+    the authority is new. An image verified by an authority measured for an earlier
+    image has no new authorities and was verified by one of the previous authorities,
+    the log does not tell which one.
+        new_authorities: authorities measured between the previous application and
+                         this one, they verified this image
+        previous_authorities: authorities measured for earlier images
+*/
+#[derive(Clone)]
+pub struct ImageAuthority {
+    pub image_path: String,
+    pub imr_index: u32,
+    pub digests: Vec<TcgDigest>,
+    pub new_authorities: Vec<SignatureAuthority>,
+    pub previous_authorities: Vec<SignatureAuthority>,
+}
+
+impl ImageAuthority {
+    // the new authorities if any, otherwise the previous authorities, one of them verified the image
+    pub fn candidate_authorities(&self) -> &[SignatureAuthority] {
+        if self.new_authorities.is_empty() {
+            &self.previous_authorities
+        } else {
+            &self.new_authorities
+        }
+    }
+}
+
+impl SignatureAuthority {
+    pub fn from_variable(variable: &UefiVariableData) -> SignatureAuthority {
+        let data = &variable.variable_data;
+        let (signature_owner, certificate) = match X509Certificate::parse(data) {
+            Ok(certificate) => (None, Some(certificate)),
+            Err(_) if data.len() >= EfiGuid::LEN => (
+                EfiGuid::parse(&data[..EfiGuid::LEN]).ok(),
+                X509Certificate::parse(&data[EfiGuid::LEN..]).ok(),
+            ),
+            Err(_) => (None, None),
+        };
+        SignatureAuthority {
+            variable_name: variable.variable_name,
+            unicode_name: variable.unicode_name.clone(),
+            signature_owner,
+            certificate,
+            data: data.clone(),
+        }
+    }
+}
+
+impl SecureBootState {
+    /***
+        Collect the Secure Boot state from the boot time event logs.
+        Args:
+            event_logs: event logs in TCG_PCCLIENT_FORMAT, e.g. from EventLogs::select()
+        Returns:
+            The Secure Boot state, malformed variables and images are recorded in
+            malformed_events rather than failing the collection
+    */
+    pub fn from_event_logs(event_logs: &[EventLogEntry]) -> SecureBootState {
+        let mut state = SecureBootState::default();
+        let mut new_authorities: Vec<SignatureAuthority> = Vec::new();
+        let mut previous_authorities: Vec<SignatureAuthority> = Vec::new();
+
+        for event in event_logs {
+            let event = match event {
+                EventLogEntry::TcgImrEvent(event) => event,
+                _ => continue,
+            };
+            let result = match event.event_type {
+                EV_EFI_VARIABLE_DRIVER_CONFIG => state.add_driver_config(event.parse_event_data()),
+                EV_EFI_VARIABLE_AUTHORITY => match event.parse_event_data() {
+                    Ok(TcgEventData::UefiVariable(variable)) => {
+                        // SbatLevel is the SBAT policy applied by shim, not an authority
+                        if variable.unicode_name == SBAT_LEVEL_VARIABLE {
+                            state.sbat_level = Some(description_to_string(&variable.variable_data));
+                        } else {
+                            new_authorities.push(SignatureAuthority::from_variable(&variable));
+                        }
+                        Ok(())
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => Err((None, e)),
+                },
+                EV_EFI_BOOT_SERVICES_APPLICATION => match event.parse_event_data() {
+                    Ok(TcgEventData::UefiImageLoad(image)) => {
+                        state.image_authorities.push(ImageAuthority {
+                            image_path: image
+                                .device_path
+                                .file_path()
+                                .unwrap_or(image.device_path.to_string()),
+                            imr_index: event.imr_index,
+                            digests: event.digests.clone(),
+                            new_authorities: new_authorities.clone(),
+                            previous_authorities: previous_authorities.clone(),
+                        });
+                        previous_authorities.append(&mut new_authorities);
+                        Ok(())
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => Err((None, e)),
+                },
+                _ => Ok(()),
+            };
+            if let Err((variable_name, error)) = result {
+                state.malformed_events.push(MalformedEvent {
+                    event_type: event.event_type,
+                    imr_index: event.imr_index,
+                    variable_name,
+                    error: format!("{:?}", error),
+                });
+            }
+        }
+        state
+    }
+
+    // decode a signature database or SecureBoot variable, error with the variable name if known
+    fn add_driver_config(
+        &mut self,
+        event_data: Result<TcgEventData, anyhow::Error>,
+    ) -> Result<(), (Option<String>, anyhow::Error)> {
+        let variable = match event_data {
+            Ok(TcgEventData::UefiVariable(variable)) => variable,
+            Ok(_) => return Ok(()),
+            Err(e) => return Err((None, e)),
+        };
+        match variable.value() {
+            Ok(UefiVariableValue::SecureBoot(enabled)) => self.secure_boot = Some(enabled),
+            Ok(UefiVariableValue::PlatformKey(lists)) => self.pk = lists,
+            Ok(UefiVariableValue::KeyExchangeKey(lists)) => self.kek = lists,
+            Ok(UefiVariableValue::SignatureDatabase(lists)) => self.db = lists,
+            Ok(UefiVariableValue::ForbiddenSignatureDatabase(lists)) => self.dbx = lists,
+            Ok(_) => (),
+            Err(e) => return Err((Some(variable.unicode_name), e)),
+        }
+        Ok(())
+    }
+
+    // Secure Boot is enabled if the measured SecureBoot variable is 1
+    pub fn is_enabled(&self) -> bool {
+        self.secure_boot == Some(true)
+    }
+
+    // certificates of the X.509 signature lists in db
+    pub fn db_certificates(&self) -> Result<Vec<X509Certificate>, anyhow::Error> {
+        let mut certificates = Vec::new();
+        for list in &self.db {
+            certificates.extend(list.certificates()?);
+        }
+        Ok(certificates)
+    }
+
+    pub fn show(&self) {
+        info!("==== Secure Boot State ====");
+        match self.secure_boot {
+            Some(enabled) => info!("SecureBoot: {}", enabled),
+            None => info!("SecureBoot: not measured"),
+        }
+        for (name, lists) in [
+            ("PK", &self.pk),
+            ("KEK", &self.kek),
+            ("db", &self.db),
+            ("dbx", &self.dbx),
+        ] {
+            info!("{}:", name);
+            for list in lists {
+                list.show();
+            }
+        }
+        if let Some(sbat_level) = &self.sbat_level {
+            info!("SbatLevel: {}", sbat_level.trim_end().replace('\n', " "));
+        }
+        for event in &self.malformed_events {
+            info!(
+                "Malformed {} {} (IMR[{}]): {}",
+                TcgEventType::get_event_type_string(event.event_type),
+                event.variable_name.as_deref().unwrap_or_default(),
+                event.imr_index,
+                event.error
+            );
+        }
+        for image in &self.image_authorities {
+            info!("Image {} (IMR[{}])", image.image_path, image.imr_index);
+            if image.new_authorities.is_empty() && !image.previous_authorities.is_empty() {
+                info!("    Verified by one of the previous authorities");
+            }
+            for authority in &image.new_authorities {
+                match &authority.certificate {
+                    Some(certificate) => {
+                        info!("    Authority {}: {}", authority.unicode_name, certificate)
+                    }
+                    None => info!(
+                        "    Authority {}: {}",
+                        authority.unicode_name,
+                        hex::encode(&authority.data)
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl EventLogs {
+    /***
+        Collect the Secure Boot state measured in the event logs, see SecureBootState.
+        Unparsed data is parsed before the collection.
+    */
+    pub fn secure_boot_state(&mut self) -> Result<SecureBootState, anyhow::Error> {
+        let event_logs = self.select(None, None)?;
+        Ok(SecureBootState::from_event_logs(&event_logs))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_secureboot {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::uefi::signature::test_uefi_signature::signature_list;
+    use crate::uefi::signature::EFI_CERT_X509_GUID;
+    use crate::uefi::variable::test_uefi_variable::variable_data;
+    use crate::uefi::{EFI_GLOBAL_VARIABLE, EFI_IMAGE_SECURITY_DATABASE_GUID};
+    use crate::x509::test_x509::test_certificate;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    pub(crate) fn imr_event(event_type: u32, hash: u8, data: Vec<u8>) -> EventLogEntry {
+        EventLogEntry::TcgImrEvent(TcgImrEvent {
+            imr_index: 0,
+            event_type,
+            digests: vec![TcgDigest {
+                algo_id: TPM_ALG_SHA256,
+                hash: vec![hash; 32],
+            }],
+            event_size: data.len() as u32,
+            event: data,
+        })
+    }
+
+    // UEFI_IMAGE_LOAD_EVENT with a file path device path
+    pub(crate) fn image_load_event(path: &str, hash: u8) -> EventLogEntry {
+        let path: Vec<u8> = format!("{}\0", path)
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        let mut device_path = vec![0x04, 0x04];
+        device_path.extend(((path.len() + 4) as u16).to_le_bytes());
+        device_path.extend(path);
+        device_path.extend([0x7f, 0xff, 0x04, 0x00]);
+        let mut data = vec![0; 24];
+        data.extend((device_path.len() as u64).to_le_bytes());
+        data.extend(device_path);
+        imr_event(EV_EFI_BOOT_SERVICES_APPLICATION, hash, data)
+    }
+
+    #[test]
+    //TDVF boots without Secure Boot and empty signature databases
+    fn test_tdvf_secure_boot_state() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let state = event_logs.secure_boot_state().unwrap();
+        assert_eq!(state.secure_boot, Some(false));
+        assert!(!state.is_enabled());
+        assert!(state.pk.is_empty() && state.kek.is_empty());
+        assert!(state.db.is_empty() && state.dbx.is_empty());
+        assert_eq!(state.sbat_level.unwrap(), "sbat,1,2022052400\ngrub,2\n");
+        assert_eq!(state.image_authorities.len(), 4);
+        assert!(state
+            .image_authorities
+            .iter()
+            .all(|image| image.candidate_authorities().is_empty()));
+        assert!(state.malformed_events.is_empty());
+    }
+
+    #[test]
+    //the db certificate measured before an image authorized it
+    fn test_image_authority() {
+        let certificate = test_certificate("Test UEFI CA", 1);
+        let db = signature_list(EFI_CERT_X509_GUID, std::slice::from_ref(&certificate));
+        let mut authority = vec![0x77; 16];
+        authority.extend(&certificate);
+        let event_logs = vec![
+            imr_event(
+                EV_EFI_VARIABLE_DRIVER_CONFIG,
+                0,
+                variable_data(EFI_GLOBAL_VARIABLE, "SecureBoot", &[1]),
+            ),
+            imr_event(
+                EV_EFI_VARIABLE_DRIVER_CONFIG,
+                1,
+                variable_data(EFI_IMAGE_SECURITY_DATABASE_GUID, "db", &db),
+            ),
+            imr_event(
+                EV_EFI_VARIABLE_AUTHORITY,
+                2,
+                variable_data(EFI_IMAGE_SECURITY_DATABASE_GUID, "db", &authority),
+            ),
+            image_load_event("\\EFI\\BOOT\\BOOTX64.EFI", 3),
+            image_load_event("\\EFI\\ubuntu\\grubx64.efi", 4),
+        ];
+
+        let state = SecureBootState::from_event_logs(&event_logs);
+        assert!(state.is_enabled());
+        assert_eq!(state.db_certificates().unwrap()[0].der, certificate);
+        let image = &state.image_authorities[0];
+        assert_eq!(image.image_path, "\\EFI\\BOOT\\BOOTX64.EFI");
+        assert_eq!(image.new_authorities[0].unicode_name, "db");
+        assert_eq!(
+            image.new_authorities[0].signature_owner.unwrap().data1,
+            0x77777777
+        );
+        assert_eq!(
+            image.new_authorities[0]
+                .certificate
+                .as_ref()
+                .unwrap()
+                .subject,
+            "O=Test, CN=Test UEFI CA"
+        );
+
+        // grub is verified by the db certificate already measured for shim
+        let image = &state.image_authorities[1];
+        assert!(image.new_authorities.is_empty());
+        assert_eq!(image.previous_authorities.len(), 1);
+        assert_eq!(
+            image.candidate_authorities()[0]
+                .certificate
+                .as_ref()
+                .unwrap()
+                .der,
+            certificate
+        );
+    }
+
+    #[test]
+    //malformed variable is recorded and the rest of the state is collected
+    fn test_malformed_variable() {
+        let event_logs = vec![
+            imr_event(
+                EV_EFI_VARIABLE_DRIVER_CONFIG,
+                0,
+                variable_data(EFI_GLOBAL_VARIABLE, "SecureBoot", &[1]),
+            ),
+            imr_event(
+                EV_EFI_VARIABLE_DRIVER_CONFIG,
+                1,
+                variable_data(EFI_IMAGE_SECURITY_DATABASE_GUID, "db", &[0xff; 8]),
+            ),
+            imr_event(EV_EFI_VARIABLE_DRIVER_CONFIG, 2, vec![0; 8]),
+            image_load_event("\\EFI\\BOOT\\BOOTX64.EFI", 3),
+        ];
+
+        let state = SecureBootState::from_event_logs(&event_logs);
+        assert!(state.is_enabled());
+        assert!(state.db.is_empty());
+        assert_eq!(state.image_authorities.len(), 1);
+        assert_eq!(state.malformed_events.len(), 2);
+        assert_eq!(
+            state.malformed_events[0].variable_name.as_deref(),
+            Some("db")
+        );
+        assert_eq!(state.malformed_events[1].variable_name, None);
+        assert_eq!(
+            state.malformed_events[1].event_type,
+            EV_EFI_VARIABLE_DRIVER_CONFIG
+        );
+    }
+}
@@ -1,5 +1,13 @@
+use crate::algorithm::{get_algorithm, get_algorithm_by_digest_size, get_algorithms};
 use crate::binary_blob::dump_data;
+use crate::no_action::NoActionEvent;
 use crate::tcgcel::*;
+use crate::uefi::firmware::UefiPlatformFirmwareBlob;
+use crate::uefi::gpt::UefiGptData;
+use crate::uefi::handoff::UefiHandoffTablePointers;
+use crate::uefi::image::UefiImageLoadEvent;
+use crate::uefi::spdm::DeviceSecurityEventData2;
+use crate::uefi::variable::UefiVariableData;
 use hashbrown::HashMap;
 use log::info;
 use std::any::Any;
@@ -10,47 +18,64 @@ pub const TPM_ALG_SHA1: u16 = 0x4;
 pub const TPM_ALG_SHA256: u16 = 0xB;
 pub const TPM_ALG_SHA384: u16 = 0xC;
 pub const TPM_ALG_SHA512: u16 = 0xD;
+pub const TPM_ALG_SM3_256: u16 = 0x12;
 pub const TPM_ALG_ECDSA: u16 = 0x18;
+pub const TPM_ALG_SHA3_256: u16 = 0x27;
+pub const TPM_ALG_SHA3_384: u16 = 0x28;
+pub const TPM_ALG_SHA3_512: u16 = 0x29;
 
 pub const TCG_PCCLIENT_FORMAT: u8 = 1;
 pub const TCG_CANONICAL_FORMAT: u8 = 2;
 
-// hash algorithm ID to algorithm name string map
-lazy_static! {
-    pub static ref ALGO_NAME_MAP: HashMap<u16, String> = {
-        let mut map: HashMap<u16, String> = HashMap::new();
-        map.insert(TPM_ALG_ERROR, "TPM_ALG_ERROR".to_string());
-        map.insert(TPM_ALG_RSA, "TPM_ALG_RSA".to_string());
-        map.insert(TPM_ALG_SHA1, "TPM_ALG_SHA1".to_string());
-        map.insert(TPM_ALG_SHA256, "TPM_ALG_SHA256".to_string());
-        map.insert(TPM_ALG_SHA384, "TPM_ALG_SHA384".to_string());
-        map.insert(TPM_ALG_SHA512, "TPM_ALG_SHA512".to_string());
-        map.insert(TPM_ALG_ECDSA, "TPM_ALG_ECDSA".to_string());
-        map
-    };
-}
+// snapshots of the algorithm registry kept for compatibility, see crate::algorithm
+#[allow(deprecated)]
+mod deprecated_maps {
+    use super::*;
+
+    lazy_static! {
+        #[deprecated(note = "use crate::algorithm::get_algorithm() instead")]
+        pub static ref ALGO_NAME_MAP: HashMap<u16, String> = {
+            let mut map: HashMap<u16, String> = HashMap::new();
+            for algo_id in [TPM_ALG_ERROR, TPM_ALG_RSA, TPM_ALG_ECDSA] {
+                map.insert(algo_id, TcgDigest::get_algorithm_name(algo_id));
+            }
+            for algorithm in get_algorithms() {
+                map.insert(algorithm.algo_id(), algorithm.name().to_string());
+            }
+            map
+        };
+    }
 
-lazy_static! {
-    pub static ref TPM_DIGEST_SIZE_ALG_HASH_MAP: HashMap<u8, u16> = {
-        let mut map: HashMap<u8, u16> = HashMap::new();
-        map.insert(20, TPM_ALG_SHA1);
-        map.insert(32, TPM_ALG_SHA256);
-        map.insert(48, TPM_ALG_SHA384);
-        map.insert(64, TPM_ALG_SHA512);
-        map
-    };
-}
+    lazy_static! {
+        #[deprecated(note = "use crate::algorithm::get_algorithm_by_digest_size() instead")]
+        pub static ref TPM_DIGEST_SIZE_ALG_HASH_MAP: HashMap<u8, u16> = {
+            let mut map: HashMap<u8, u16> = HashMap::new();
+            for algorithm in get_algorithms().iter().rev() {
+                if let Ok(digest_size) = u8::try_from(algorithm.digest_size()) {
+                    map.insert(digest_size, algorithm.algo_id());
+                }
+            }
+            map
+        };
+    }
 
-lazy_static! {
-    pub static ref TPM_HASH_ALG_DIGEST_SIZE_MAP: HashMap<u16, u8> = {
-        let mut map: HashMap<u16, u8> = HashMap::new();
-        map.insert(TPM_ALG_SHA1, 20);
-        map.insert(TPM_ALG_SHA256, 32);
-        map.insert(TPM_ALG_SHA384, 48);
-        map.insert(TPM_ALG_SHA512, 64);
-        map
-    };
+    lazy_static! {
+        #[deprecated(note = "use crate::algorithm::get_algorithm() instead")]
+        pub static ref TPM_HASH_ALG_DIGEST_SIZE_MAP: HashMap<u16, u8> = {
+            let mut map: HashMap<u16, u8> = HashMap::new();
+            for algorithm in get_algorithms() {
+                if let Ok(digest_size) = u8::try_from(algorithm.digest_size()) {
+                    map.insert(algorithm.algo_id(), digest_size);
+                }
+            }
+            map
+        };
+    }
 }
+#[allow(deprecated)]
+pub use deprecated_maps::{
+    ALGO_NAME_MAP, TPM_DIGEST_SIZE_ALG_HASH_MAP, TPM_HASH_ALG_DIGEST_SIZE_MAP,
+};
 
 // this trait retrieve tcg standard algorithm name in string
 pub trait TcgAlgorithmRegistry {
@@ -68,10 +93,7 @@ pub struct TcgDigest {
 impl TcgDigest {
     pub fn show(&self) {
         info!("show data in struct TcgDigest");
-        info!(
-            "algo = {}",
-            ALGO_NAME_MAP.get(&self.algo_id).unwrap().to_owned()
-        );
+        info!("algo = {}", TcgDigest::get_algorithm_name(self.algo_id));
         info!("hash = {:02X?}", self.hash);
     }
 
@@ -79,16 +101,32 @@ impl TcgDigest {
         self.hash.clone()
     }
 
+    // preferred registered hash algorithm of the digest size, TPM_ALG_ERROR if none
     pub fn get_algorithm_id_from_digest_size(digest_size: u8) -> u16 {
-        match TPM_DIGEST_SIZE_ALG_HASH_MAP.get(&digest_size) {
-            Some(algo_id) => *algo_id,
+        match get_algorithm_by_digest_size(digest_size as usize) {
+            Some(algorithm) => algorithm.algo_id(),
             None => TPM_ALG_ERROR,
         }
     }
 
+    // name of the algorithm, unknown algorithms are shown with their id
+    pub fn get_algorithm_name(algo_id: u16) -> String {
+        if let Some(algorithm) = get_algorithm(algo_id) {
+            return algorithm.name().to_string();
+        }
+        match algo_id {
+            TPM_ALG_ERROR => "TPM_ALG_ERROR".to_string(),
+            TPM_ALG_RSA => "TPM_ALG_RSA".to_string(),
+            TPM_ALG_ECDSA => "TPM_ALG_ECDSA".to_string(),
+            _ => format!("UNKNOWN(0x{:04X})", algo_id),
+        }
+    }
+
+    // digest size of the registered hash algorithm, 0 for unknown algorithms and
+    // digest sizes not fitting in the byte used by event logs
     pub fn get_digest_size_from_algorithm_id(algo_id: u16) -> u8 {
-        match TPM_HASH_ALG_DIGEST_SIZE_MAP.get(&algo_id) {
-            Some(digest_size) => *digest_size,
+        match get_algorithm(algo_id) {
+            Some(algorithm) => u8::try_from(algorithm.digest_size()).unwrap_or(0),
             None => 0,
         }
     }
@@ -100,7 +138,7 @@ impl TcgAlgorithmRegistry for TcgDigest {
     }
 
     fn get_algorithm_id_str(&self) -> String {
-        ALGO_NAME_MAP.get(&self.algo_id).unwrap().to_owned()
+        TcgDigest::get_algorithm_name(self.algo_id)
     }
 }
 
@@ -371,14 +409,19 @@ pub struct TcgEfiSpecIdEventAlgorithmSize {
     pub digest_size: u32,
 }
 
-#[derive(Clone)]
-pub struct TcgCanonicalEvent {}
+#[deprecated(note = "canonical event logs are EventLogEntry::TcgTpmsCelEvent")]
+pub type TcgCanonicalEvent = TcgTpmsCelEvent;
 
+/***
+    Event log entry in the format requested from EventLogs.
+    TcgCanonicalEvent is kept for compatibility and carries a CEL record as
+    TcgTpmsCelEvent does, event logs in TCG_CANONICAL_FORMAT are TcgTpmsCelEvent.
+*/
 #[derive(Clone)]
 pub enum EventLogEntry {
     TcgImrEvent(TcgImrEvent),
     TcgPcClientImrEvent(TcgPcClientImrEvent),
-    TcgCanonicalEvent(TcgCanonicalEvent),
+    TcgCanonicalEvent(TcgTpmsCelEvent),
     TcgTpmsCelEvent(TcgTpmsCelEvent),
 }
 
@@ -393,13 +436,87 @@ impl EventLogEntry {
             EventLogEntry::TcgPcClientImrEvent(tcg_pc_client_imr_event) => {
                 &tcg_pc_client_imr_event.show()
             }
-            EventLogEntry::TcgCanonicalEvent(_) => todo!(),
-            EventLogEntry::TcgTpmsCelEvent(_) => todo!(),
+            EventLogEntry::TcgCanonicalEvent(tcg_cel_event)
+            | EventLogEntry::TcgTpmsCelEvent(tcg_cel_event) => &tcg_cel_event.show(),
         };
     }
 }
 
+/***
+    Event data decoded according to the event type, see TcgImrEvent::parse_event_data().
+    Event data of types without a decoder is kept raw.
+*/
+#[derive(Clone, Debug)]
+pub enum TcgEventData {
+    UefiVariable(UefiVariableData),
+    UefiImageLoad(UefiImageLoadEvent),
+    UefiGpt(UefiGptData),
+    UefiFirmwareBlob(UefiPlatformFirmwareBlob),
+    UefiHandoffTables(UefiHandoffTablePointers),
+    SpdmDevice(DeviceSecurityEventData2),
+    NoAction(NoActionEvent),
+    Raw(Vec<u8>),
+}
+
+impl TcgEventData {
+    pub fn show(&self) {
+        match self {
+            TcgEventData::UefiVariable(variable) => variable.show(),
+            TcgEventData::UefiImageLoad(image) => image.show(),
+            TcgEventData::UefiGpt(gpt) => gpt.show(),
+            TcgEventData::UefiFirmwareBlob(blob) => blob.show(),
+            TcgEventData::UefiHandoffTables(tables) => tables.show(),
+            TcgEventData::SpdmDevice(device) => device.show(),
+            TcgEventData::NoAction(event) => event.show(),
+            TcgEventData::Raw(data) => dump_data(data),
+        }
+    }
+}
+
 impl TcgImrEvent {
+    /***
+        Decode the event data according to the event type.
+        Returns:
+            The decoded event data, or an error if the event data is malformed
+    */
+    pub fn parse_event_data(&self) -> Result<TcgEventData, anyhow::Error> {
+        match self.event_type {
+            EV_NO_ACTION => Ok(TcgEventData::NoAction(NoActionEvent::parse(&self.event)?)),
+            EV_EFI_VARIABLE_DRIVER_CONFIG
+            | EV_EFI_VARIABLE_BOOT
+            | EV_EFI_VARIABLE_BOOT2
+            | EV_EFI_VARIABLE_AUTHORITY
+            | EV_EFI_SPDM_DEVICE_POLICY
+            | EV_EFI_SPDM_DEVICE_AUTHORITY => Ok(TcgEventData::UefiVariable(
+                UefiVariableData::parse(&self.event)?,
+            )),
+            EV_EFI_BOOT_SERVICES_APPLICATION
+            | EV_EFI_BOOT_SERVICES_DRIVER
+            | EV_EFI_RUNTIME_SERVICES_DRIVER => Ok(TcgEventData::UefiImageLoad(
+                UefiImageLoadEvent::parse(&self.event)?,
+            )),
+            EV_EFI_GPT_EVENT | EV_EFI_GPT_EVENT2 => {
+                Ok(TcgEventData::UefiGpt(UefiGptData::parse(&self.event)?))
+            }
+            EV_EFI_PLATFORM_FIRMWARE_BLOB => Ok(TcgEventData::UefiFirmwareBlob(
+                UefiPlatformFirmwareBlob::parse(&self.event)?,
+            )),
+            EV_EFI_PLATFORM_FIRMWARE_BLOB2 => Ok(TcgEventData::UefiFirmwareBlob(
+                UefiPlatformFirmwareBlob::parse_blob2(&self.event)?,
+            )),
+            EV_EFI_HANDOFF_TABLES => Ok(TcgEventData::UefiHandoffTables(
+                UefiHandoffTablePointers::parse(&self.event)?,
+            )),
+            EV_EFI_HANDOFF_TABLES2 => Ok(TcgEventData::UefiHandoffTables(
+                UefiHandoffTablePointers::parse_tables2(&self.event)?,
+            )),
+            EV_EFI_SPDM_FIRMWARE_BLOB | EV_EFI_SPDM_FIRMWARE_CONFIG => Ok(
+                TcgEventData::SpdmDevice(DeviceSecurityEventData2::parse(&self.event)?),
+            ),
+            _ => Ok(TcgEventData::Raw(self.event.clone())),
+        }
+    }
+
     pub fn show(&self) {
         info!(
             "        -------------------------------Event Log Entry-----------------------------"
@@ -416,16 +533,16 @@ impl TcgImrEvent {
                 "        Algorithm_id[{}]   : {} {}",
                 digest_index,
                 self.digests[digest_index].algo_id,
-                ALGO_NAME_MAP
-                    .get(&self.digests[digest_index].algo_id)
-                    .unwrap()
-                    .to_owned()
+                TcgDigest::get_algorithm_name(self.digests[digest_index].algo_id)
             );
             info!("        Digest[{}]:", digest_index);
             dump_data(&self.digests[digest_index].hash);
         }
         info!("        Event:");
-        dump_data(&self.event);
+        match self.parse_event_data() {
+            Ok(event_data) => event_data.show(),
+            Err(_) => dump_data(&self.event),
+        }
     }
 }
 
@@ -2,7 +2,7 @@ extern crate lazy_static;
 
 use crate::tcg::*;
 use crate::binary_blob::*;
-use log::error;
+use log::{error, info};
 use std::collections::HashMap;
 use std::any::Any;
 
@@ -18,7 +18,7 @@ pub struct TcgTpmsCelEvent {
 }
 
 impl TcgTpmsCelEvent {
-    fn new(
+    pub fn new(
         rec_num: i32,
         digests: Vec<TcgDigest>,
         content_type: Option<i32>,
@@ -64,7 +64,7 @@ impl TcgTpmsCelEvent {
         }
     }
 
-    fn rec_num(&self) -> i32 {
+    pub fn rec_num(&self) -> i32 {
         self.rec_num
     }
 
@@ -72,7 +72,7 @@ impl TcgTpmsCelEvent {
         self.rec_num = rec_num;
     }
 
-    fn index(&self) -> Option<i32> {
+    pub fn index(&self) -> Option<i32> {
         self.imr.or(self.nv_index)
     }
 
@@ -84,7 +84,7 @@ impl TcgTpmsCelEvent {
         self.nv_index = Some(nv_index);
     }
 
-    fn digests(&self) -> &Vec<TcgDigest> {
+    pub fn digests(&self) -> &Vec<TcgDigest> {
         &self.digests
     }
 
@@ -92,7 +92,7 @@ impl TcgTpmsCelEvent {
         self.digests = digests;
     }
 
-    fn content(&self) -> Option<&TcgTpmuEventContent> {
+    pub fn content(&self) -> Option<&TcgTpmuEventContent> {
         self.content.as_ref()
     }
 
@@ -100,85 +100,71 @@ impl TcgTpmsCelEvent {
         self.content = Some(content);
     }
 
-    fn content_type(&self) -> Option<i32> {
+    pub fn content_type(&self) -> Option<i32> {
         self.content_type
     }
 
-    fn encoding(&self) -> Option<&str> {
+    pub fn encoding(&self) -> Option<&str> {
         self.encoding.as_ref().map(|x| x.as_str())
     }
 
+    /***
+        Convert the record to a TCG PCClient event log. None if the record is not
+        measured to an IMR, e.g. a record of a NV index, or if its content has no
+        PCClient form.
+    */
     pub fn to_pcclient_format(&self) -> Option<TcgImrEvent> {
-        match self.content_type {
-            Some(TcgCelTypes::CEL_IMA_TEMPLATE) => {
-                // if let Some(event) = eve
-                match self.content.clone().unwrap() {
-                    TcgTpmuEventContent::TcgTpmsEventImaTemplate(template_content) => {
-                        let event = &template_content.template_data().clone();
-                        Some(TcgImrEvent {
-                            imr_index: self.imr.unwrap() as u32,
-                            event_type: IMA_MEASUREMENT_EVENT,
-                            digests: self.digests.clone(),
-                            event_size: event.len() as u32,
-                            event: <String as Clone>::clone(&event).into_bytes(),
-                        })
-                    }
-                    TcgTpmuEventContent::TcgTpmsEventPcClientStd(_) => None,
-                    TcgTpmuEventContent::TcgTpmsEventCelMgt(_) => None,
-                    TcgTpmuEventContent::TcgImaTlv(_) => None,
-                }
-                // if let Some(template_content) = self.content.unwrap().downcast_ref::<TcgTpmsEventImaTemplate>() {
-                //     let event = &template_content.template_data().clone();
-                //     Some(TcgImrEvent {
-                //         imr_index: self.imr.unwrap() as u32,
-                //         event_type: IMA_MEASUREMENT_EVENT,
-                //         digests: self.digests.clone(),
-                //         event_size: event.len() as u32,
-                //         event: <String as Clone>::clone(&event).into_bytes(),
-                //     })
-                // }
-                // else {
-                //     None
-                // }
+        let imr_index = u32::try_from(self.imr?).ok()?;
+        let (event_type, event) = match (self.content_type, self.content.as_ref()) {
+            (
+                Some(TcgCelTypes::CEL_IMA_TEMPLATE),
+                Some(TcgTpmuEventContent::TcgTpmsEventImaTemplate(template_content)),
+            ) => {
+                // the event data is the binary template data hashed into the digests
+                (IMA_MEASUREMENT_EVENT, template_content.template_data().clone())
             }
-            Some(TcgCelTypes::CEL_PCCLIENT_STD) => {
-                match self.content.clone().unwrap() {
-                    TcgTpmuEventContent::TcgTpmsEventPcClientStd(content) => {
-                        // let event = &template_content.template_data().clone();
-                        Some(TcgImrEvent {
-                            imr_index: self.imr.unwrap() as u32,
-                            event_type: content.event_type() as u32,
-                            digests: self.digests.clone(),
-                            event_size: content.event_data().len() as u32,
-                            event: content.event_data().to_vec(),
-                        })
-                    }
-                    TcgTpmuEventContent::TcgTpmsEventCelMgt(_) => None,
-                    TcgTpmuEventContent::TcgTpmsEventImaTemplate(_) => None,
-                    TcgTpmuEventContent::TcgImaTlv(_) => None,
-                }
-                
-            //     if let Some(content) = self.content.unwrap().downcast_ref::<TcgTpmsEventPcClientStd>() {
-                    
-            //         Some(TcgImrEvent {
-            //             imr_index: self.imr.unwrap() as u32,
-            //             event_type: content.event_type() as u32,
-            //             digests: self.digests.clone(),
-            //             event_size: content.event_data().len() as u32,
-            //             event: content.event_data().to_vec(),
-            //         })
-            // }
-            // else {
-            //     None
-            // }
-         }
+            (
+                Some(TcgCelTypes::CEL_PCCLIENT_STD),
+                Some(TcgTpmuEventContent::TcgTpmsEventPcClientStd(content)),
+            ) => (content.event_type() as u32, content.event_data().to_vec()),
             _ => {
                 error!("Unsupported content to parse into TCG PCClient format.");
-                None
+                return None;
             }
-        }
+        };
+
+        Some(TcgImrEvent {
+            imr_index,
+            event_type,
+            digests: self.digests.clone(),
+            event_size: event.len() as u32,
+            event,
+        })
     }
 
+    pub fn show(&self) {
+        info!("        --------------------TcgTpmsCelEvent--------------------------");
+        info!("rec_num = {}", self.rec_num);
+        info!("index = {}", self.index().unwrap_or_default());
+        info!(
+            "content_type = {}",
+            TcgTpmiCelContentType::get_content_type_string(self.content_type.unwrap_or_default())
+        );
+        for digest in &self.digests {
+            info!("digest[{}] = {}", digest.algo_id, hex::encode(&digest.hash));
+        }
+        match &self.content {
+            Some(TcgTpmuEventContent::TcgTpmsEventPcClientStd(content)) => {
+                info!("event_type = {}", content.event_type());
+                info!("event = {}", String::from_utf8_lossy(content.event_data()));
+            }
+            Some(TcgTpmuEventContent::TcgTpmsEventImaTemplate(content)) => {
+                info!("template_name = {}", content.template_name());
+                info!("template_data = {}", hex::encode(content.template_data()));
+            }
+            _ => (),
+        }
+    }
 
     pub fn encode(&self, mut obj: TcgTpmsCelEvent, encoding: i32) -> TcgTpmsCelEvent {
         match encoding {
@@ -280,10 +266,10 @@ impl TcgTpmsCelEvent {
             index.set_type(TcgCelTypes::CEL_PCR);
             index.set_value(imr);
             obj.set_imr(imr);
-        } else {
+        } else if let Some(nv_index) = obj.nv_index {
             index.set_type(TcgCelTypes::CEL_NV_INDEX);
-            index.set_value(obj.nv_index.unwrap());
-            obj.set_nv_index(obj.nv_index.unwrap());
+            index.set_value(nv_index);
+            obj.set_nv_index(nv_index);
         }
 
         obj
@@ -389,7 +375,7 @@ impl TcgTpmiCelContentType {
 }
 
 #[derive(Clone)]
-enum TcgTpmuEventContent {
+pub enum TcgTpmuEventContent {
     TcgTpmsEventPcClientStd(TcgTpmsEventPcClientStd),
     TcgTpmsEventCelMgt(TcgTpmsEventCelMgt),
     TcgTpmsEventImaTemplate(TcgTpmsEventImaTemplate),
@@ -806,7 +792,7 @@ impl TcgTpmsEventPcClientStd {
 
 #[derive(Clone)]
 pub struct TcgTpmsEventImaTemplate {
-    template_data: String,
+    template_data: Vec<u8>,
     template_name: String,
     IMA_TEMPLATE_TABLE: HashMap<i32, String>,
 }
@@ -817,7 +803,7 @@ impl TcgTpmsEventImaTemplate {
     //     (TcgCelTypes::IMA_TEMPLATE_DATA, "IMA_TEMPLATE_DATA"),
     // ];
 
-    pub fn new(template_data: String, template_name: String) -> Self {
+    pub fn new(template_data: Vec<u8>, template_name: String) -> Self {
             let mut IMA_TEMPLATE_TABLE: HashMap<i32, String> = HashMap::new();
             IMA_TEMPLATE_TABLE.insert(TcgCelTypes::IMA_TEMPLATE_NAME, "IMA_TEMPLATE_NAME".to_string());
             IMA_TEMPLATE_TABLE.insert(TcgCelTypes::IMA_TEMPLATE_DATA, "IMA_TEMPLATE_DATA".to_string());
@@ -828,7 +814,7 @@ impl TcgTpmsEventImaTemplate {
         }
     }
 
-    pub fn template_data(&self) -> &String {
+    pub fn template_data(&self) -> &Vec<u8> {
         &self.template_data
     }
 
@@ -845,7 +831,7 @@ impl TcgTpmsEventImaTemplate {
         let mut template_name = TcgTlvBase::new(TcgCelTypes::IMA_TEMPLATE_NAME, 0);
         let mut template_data = TcgTlvBase::new(TcgCelTypes::IMA_TEMPLATE_DATA, 0);
         template_name.set_value(self.template_name.clone().into_bytes().into_iter().map(|b| b as i32).sum());
-        template_data.set_value(self.template_data.clone().into_bytes().into_iter().map(|b| b as i32).sum());
+        template_data.set_value(self.template_data.clone().into_iter().map(|b| b as i32).sum());
         template_name.set_attr_table(self.IMA_TEMPLATE_TABLE.clone());
         template_data.set_attr_table(self.IMA_TEMPLATE_TABLE.clone());
         content_list.push(template_name);
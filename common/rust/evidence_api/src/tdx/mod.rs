@@ -1,4 +1,5 @@
 pub mod common;
 pub mod quote;
 pub mod report;
+pub mod report_data;
 pub mod rtmr;
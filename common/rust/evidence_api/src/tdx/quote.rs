@@ -9,6 +9,8 @@ use log::*;
 use crate::api::ParseCcReport;
 use crate::api_data::CcReport;
 use crate::tdx::common::*;
+use crate::tdx::report::ReportDataVerifier;
+use crate::tdx::report_data::ReportData;
 
 #[repr(C)]
 pub struct qgs_msg_header {
@@ -568,6 +570,12 @@ impl TdxQuote {
     }
 }
 
+impl ReportDataVerifier for TdxQuote {
+    fn get_report_data(&self) -> ReportData {
+        ReportData::new(self.body.report_data)
+    }
+}
+
 // API function parses raw cc report to TdxQuote struct
 impl ParseCcReport<TdxQuote> for CcReport {
     fn parse_cc_report(report: Vec<u8>) -> Result<TdxQuote, anyhow::Error> {
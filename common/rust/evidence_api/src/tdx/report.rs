@@ -1,10 +1,10 @@
 #![allow(non_camel_case_types)]
 use crate::tdx::common::*;
+use crate::tdx::report_data::*;
 use anyhow::*;
 use core::mem::transmute;
 use core::result::Result;
 use core::result::Result::Ok;
-use sha2::{Digest, Sha512};
 
 #[repr(C)]
 pub struct tdx_1_0_report_req {
@@ -196,6 +196,82 @@ pub struct TDReport {
     pub td_info: TdInfo,
 }
 
+/***
+    trait to be implemented by TDX evidence carrying the 64 bytes REPORTDATA,
+    e.g.: TdxQuote and TDReport.
+
+    The verifier calls verify_report_data() with the nonce and user data it expects,
+    the report data is recomputed the same way as generate_tdx_report_data() does on
+    the attester side and compared with the one inside the evidence. For report data
+    generated with another layout, use verify_report_data_with_binding().
+*/
+pub trait ReportDataVerifier {
+    fn get_report_data(&self) -> ReportData;
+
+    /***
+        verify the report data binds the given base64 encoded nonce and data
+
+        Args:
+            nonce (String): base64 encoded nonce expected by the verifier
+            data (String): base64 encoded user data expected by the verifier
+
+        Returns:
+            true if the report data matches, false otherwise
+    */
+    fn verify_report_data(
+        &self,
+        nonce: Option<String>,
+        data: Option<String>,
+    ) -> Result<bool, anyhow::Error> {
+        Tdx::verify_tdx_report_data(&self.get_report_data(), nonce, data)
+    }
+
+    /***
+        verify the report data binds the given raw nonce and data bytes
+
+        Args:
+            nonce (Option<&[u8]>): nonce expected by the verifier
+            data (Option<&[u8]>): user data expected by the verifier
+
+        Returns:
+            true if the report data matches, false otherwise
+    */
+    fn verify_report_data_raw(&self, nonce: Option<&[u8]>, data: Option<&[u8]>) -> bool {
+        Tdx::verify_tdx_report_data_raw(&self.get_report_data(), nonce, data)
+    }
+
+    /***
+        verify the report data binds the given raw nonce and data bytes with the
+        binding scheme expected by the verifier
+
+        Args:
+            binding (ReportDataBinding): the scheme used to bind nonce and data
+            nonce (Option<&[u8]>): nonce expected by the verifier
+            data (Option<&[u8]>): user data expected by the verifier
+
+        Returns:
+            true if the report data matches, false otherwise
+    */
+    fn verify_report_data_with_binding(
+        &self,
+        binding: &dyn ReportDataBinding,
+        nonce: Option<&[u8]>,
+        data: Option<&[u8]>,
+    ) -> Result<bool, anyhow::Error> {
+        binding.verify(
+            &self.get_report_data(),
+            nonce.unwrap_or_default(),
+            data.unwrap_or_default(),
+        )
+    }
+}
+
+impl ReportDataVerifier for TDReport {
+    fn get_report_data(&self) -> ReportData {
+        ReportData::new(self.report_mac_struct.report_data)
+    }
+}
+
 impl Tdx {
     /***
         generate tdx data with nonce and data
@@ -211,53 +287,111 @@ impl Tdx {
         nonce: Option<String>,
         data: Option<String>,
     ) -> Result<String, anyhow::Error> {
-        let mut hasher = Sha512::new();
-
-        match nonce {
-            Some(_encoded_nonce) => {
-                if _encoded_nonce.is_empty() {
-                    hasher.update("")
-                } else {
-                    let decoded_nonce = match base64::decode(_encoded_nonce) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            return Err(anyhow!(
-                                "[generate_tdx_report_data] nonce is not base64 encoded: {:?}",
-                                e
-                            ))
-                        }
-                    };
-                    hasher.update(decoded_nonce)
-                }
-            }
-            None => hasher.update(""),
-        };
+        let report_data =
+            Tdx::generate_tdx_report_data_with_binding(&Sha512NonceData, nonce, data)?;
+        Ok(report_data.to_base64())
+    }
 
-        match data {
-            Some(_encoded_data) => {
-                if _encoded_data.is_empty() {
-                    hasher.update("")
-                } else {
-                    let decoded_data = match base64::decode(_encoded_data) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            return Err(anyhow!(
-                                "[generate_tdx_report_data] user data is not base64 encoded: {:?}",
-                                e
-                            ))
-                        }
-                    };
-                    hasher.update(decoded_data)
-                }
-            }
-            None => hasher.update(""),
-        };
-        let hash_array: [u8; 64] = hasher
-            .finalize()
-            .as_slice()
-            .try_into()
-            .expect("[generate_tdx_report_data] Wrong length of data");
-        Ok(base64::encode(hash_array))
+    /***
+        generate tdx report data with nonce and data using the given binding scheme
+
+        Args:
+            binding (ReportDataBinding): the scheme used to bind nonce and data
+            nonce (String): base64 encoded nonce against replay attacks
+            data (String): base64 encoded user data
+
+        Returns:
+            The ReportData to be put into tdreport
+    */
+    pub fn generate_tdx_report_data_with_binding(
+        binding: &dyn ReportDataBinding,
+        nonce: Option<String>,
+        data: Option<String>,
+    ) -> Result<ReportData, anyhow::Error> {
+        let decoded_nonce = Tdx::decode_report_data_input(nonce, "nonce")?;
+        let decoded_data = Tdx::decode_report_data_input(data, "user data")?;
+        binding.bind(
+            decoded_nonce.as_deref().unwrap_or_default(),
+            decoded_data.as_deref().unwrap_or_default(),
+        )
+    }
+
+    /***
+        generate tdx report data with raw nonce and data bytes
+
+        Args:
+            nonce (Option<&[u8]>): against replay attacks
+            data (Option<&[u8]>): user data
+
+        Returns:
+            The report data, i.e. SHA-512(nonce || data)
+    */
+    pub fn generate_tdx_report_data_raw(nonce: Option<&[u8]>, data: Option<&[u8]>) -> ReportData {
+        Sha512NonceData
+            .bind(nonce.unwrap_or_default(), data.unwrap_or_default())
+            .expect("[generate_tdx_report_data_raw] Wrong length of data")
+    }
+
+    /***
+        verify the report data carried by a quote or tdreport binds the given nonce and data
+
+        Args:
+            report_data (ReportData): report data extracted from the quote or tdreport
+            nonce (String): base64 encoded nonce expected by the verifier
+            data (String): base64 encoded user data expected by the verifier
+
+        Returns:
+            true if the report data equals SHA-512(nonce || data), false otherwise
+    */
+    pub fn verify_tdx_report_data(
+        report_data: &ReportData,
+        nonce: Option<String>,
+        data: Option<String>,
+    ) -> Result<bool, anyhow::Error> {
+        let decoded_nonce = Tdx::decode_report_data_input(nonce, "nonce")?;
+        let decoded_data = Tdx::decode_report_data_input(data, "user data")?;
+        Ok(Tdx::verify_tdx_report_data_raw(
+            report_data,
+            decoded_nonce.as_deref(),
+            decoded_data.as_deref(),
+        ))
+    }
+
+    /***
+        verify the report data binds the given raw nonce and data bytes
+
+        Args:
+            report_data (ReportData): report data extracted from the quote or tdreport
+            nonce (Option<&[u8]>): nonce expected by the verifier
+            data (Option<&[u8]>): user data expected by the verifier
+
+        Returns:
+            true if the report data equals SHA-512(nonce || data), false otherwise
+    */
+    pub fn verify_tdx_report_data_raw(
+        report_data: &ReportData,
+        nonce: Option<&[u8]>,
+        data: Option<&[u8]>,
+    ) -> bool {
+        Tdx::generate_tdx_report_data_raw(nonce, data) == *report_data
+    }
+
+    // decode the optional base64 encoded nonce or user data, empty input is kept as is
+    fn decode_report_data_input(
+        input: Option<String>,
+        name: &str,
+    ) -> Result<Option<Vec<u8>>, anyhow::Error> {
+        match input {
+            Some(encoded) => match base64::decode(encoded) {
+                Ok(v) => Ok(Some(v)),
+                Err(e) => Err(anyhow!(
+                    "[decode_report_data_input] {} is not base64 encoded: {:?}",
+                    name,
+                    e
+                )),
+            },
+            None => Ok(None),
+        }
     }
 
     pub fn parse_td_report(
@@ -405,3 +539,102 @@ mod test_generate_tdx_report_data {
         assert_eq!(generated_hash_len, 64);
     }
 }
+
+#[cfg(test)]
+mod test_verify_tdx_report_data {
+    use super::*;
+    use crate::tdx::common::{Tdx, TdxVersion};
+
+    fn td_report_with_report_data(report_data: &ReportData) -> TDReport {
+        let mut raw_report = vec![0; TDX_REPORT_LEN as usize];
+        raw_report[0x80..0xc0].copy_from_slice(report_data.as_bytes());
+        Tdx::parse_td_report(&raw_report, TdxVersion::TDX_1_5).unwrap()
+    }
+
+    #[test]
+    //verify_tdx_report_data accepts report data generated from the same nonce and data
+    fn test_verify_tdx_report_data_match() {
+        let report_data = base64::decode(
+            Tdx::generate_tdx_report_data(
+                Some("MTIzNDU2Nzg=".to_string()),
+                Some("YWJjZGVmZw==".to_string()),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let result = Tdx::verify_tdx_report_data(
+            &ReportData::from_slice(&report_data).unwrap(),
+            Some("MTIzNDU2Nzg=".to_string()),
+            Some("YWJjZGVmZw==".to_string()),
+        );
+        assert!(result.unwrap());
+    }
+
+    #[test]
+    //verify_tdx_report_data rejects report data generated from another nonce
+    fn test_verify_tdx_report_data_nonce_mismatch() {
+        let report_data = Tdx::generate_tdx_report_data_raw(Some(b"12345678"), Some(b"abcdefg"));
+        let result = Tdx::verify_tdx_report_data(
+            &report_data,
+            Some("IXUKoBO1XEFBPwopN4sY".to_string()),
+            Some("YWJjZGVmZw==".to_string()),
+        );
+        assert!(!result.unwrap());
+    }
+
+    #[test]
+    //verify_tdx_report_data require nonce string is base64 encoded
+    fn test_verify_tdx_report_data_nonce_not_base64_encoded() {
+        let report_data = ReportData::new([0; REPORT_DATA_LEN as usize]);
+        let result = Tdx::verify_tdx_report_data(&report_data, Some("XD^%*!x".to_string()), None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    //raw bytes and base64 encoded inputs lead to the same report data
+    fn test_generate_tdx_report_data_raw_equals_base64() {
+        let encoded = Tdx::generate_tdx_report_data(
+            Some("MTIzNDU2Nzg=".to_string()),
+            Some("YWJjZGVmZw==".to_string()),
+        )
+        .unwrap();
+        let raw = Tdx::generate_tdx_report_data_raw(Some(b"12345678"), Some(b"abcdefg"));
+        assert_eq!(base64::decode(encoded).unwrap(), raw.as_bytes());
+    }
+
+    #[test]
+    //TDReport verifies the report data with raw nonce and data
+    fn test_td_report_verify_report_data_raw() {
+        let report_data = Tdx::generate_tdx_report_data_raw(Some(b"nonce"), None);
+        let td_report = td_report_with_report_data(&report_data);
+        assert!(td_report.verify_report_data_raw(Some(b"nonce"), None));
+        assert!(!td_report.verify_report_data_raw(Some(b"nonce"), Some(b"data")));
+    }
+
+    #[test]
+    //TDReport verifies the report data with base64 encoded nonce and data
+    fn test_td_report_verify_report_data() {
+        let report_data = Tdx::generate_tdx_report_data_raw(Some(b"12345678"), None);
+        let td_report = td_report_with_report_data(&report_data);
+        assert!(td_report
+            .verify_report_data(Some("MTIzNDU2Nzg=".to_string()), None)
+            .unwrap());
+    }
+
+    #[test]
+    //TDReport verifies the report data generated with another binding scheme
+    fn test_td_report_verify_report_data_with_binding() {
+        const NONCE: &[u8] = b"0123456789abcdef0123456789abcdef";
+        let report_data = Tdx::generate_tdx_report_data_with_binding(
+            &PubKeyHashNonce,
+            Some("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=".to_string()),
+            Some("YWJjZGVmZw==".to_string()),
+        )
+        .unwrap();
+        let td_report = td_report_with_report_data(&report_data);
+        assert!(td_report
+            .verify_report_data_with_binding(&PubKeyHashNonce, Some(NONCE), Some(b"abcdefg"))
+            .unwrap());
+        assert!(!td_report.verify_report_data_raw(Some(NONCE), Some(b"abcdefg")));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::tdx::common::REPORT_DATA_LEN;
+use anyhow::anyhow;
+use core::result::Result;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/***
+    The 64 bytes REPORTDATA carried by TDREPORT and TD quote.
+
+    The content is defined by the TD, usually a binding of the verifier provided
+    nonce and user data, see ReportDataBinding for the supported layouts.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReportData([u8; REPORT_DATA_LEN as usize]);
+
+impl ReportData {
+    pub fn new(data: [u8; REPORT_DATA_LEN as usize]) -> ReportData {
+        ReportData(data)
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<ReportData, anyhow::Error> {
+        match data.try_into() {
+            Ok(v) => Ok(ReportData(v)),
+            Err(_) => Err(anyhow!(
+                "[from_slice] report data must be {} bytes, got {} bytes",
+                REPORT_DATA_LEN,
+                data.len()
+            )),
+        }
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<ReportData, anyhow::Error> {
+        match base64::decode(encoded) {
+            Ok(v) => ReportData::from_slice(&v),
+            Err(e) => Err(anyhow!(
+                "[from_base64] report data is not base64 encoded: {:?}",
+                e
+            )),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8; REPORT_DATA_LEN as usize] {
+        &self.0
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::encode(self.0)
+    }
+}
+
+impl From<[u8; REPORT_DATA_LEN as usize]> for ReportData {
+    fn from(data: [u8; REPORT_DATA_LEN as usize]) -> ReportData {
+        ReportData(data)
+    }
+}
+
+impl From<ReportData> for [u8; REPORT_DATA_LEN as usize] {
+    fn from(report_data: ReportData) -> [u8; REPORT_DATA_LEN as usize] {
+        report_data.0
+    }
+}
+
+/***
+    trait describing how the nonce and user data are bound into the REPORTDATA.
+
+    The attester calls bind() to produce the report data put into TDREPORT, the
+    verifier calls verify() with the nonce and data it expects. Built-in schemes:
+        - Sha512NonceData: SHA-512(nonce || data), the default scheme of this SDK
+        - Sha384Padded: SHA-384(nonce || data) followed by 16 zero bytes
+        - PubKeyHashNonce: SHA-256(public key) in the first 32 bytes and the
+          nonce of exactly 32 bytes in the last 32 bytes, the data is the public key
+        - RawReportData: nonce || data passed through, which must be exactly 64 bytes
+    Applications can implement the trait for other layouts required by their verifier.
+*/
+pub trait ReportDataBinding {
+    fn name(&self) -> &str;
+
+    fn bind(&self, nonce: &[u8], data: &[u8]) -> Result<ReportData, anyhow::Error>;
+
+    fn verify(
+        &self,
+        report_data: &ReportData,
+        nonce: &[u8],
+        data: &[u8],
+    ) -> Result<bool, anyhow::Error> {
+        Ok(self.bind(nonce, data)? == *report_data)
+    }
+}
+
+// copy the data into the beginning of the report data, the rest is zero
+fn zero_padded(data: &[u8], name: &str) -> Result<ReportData, anyhow::Error> {
+    if data.len() > REPORT_DATA_LEN as usize {
+        return Err(anyhow!(
+            "[{}] data of {} bytes does not fit into {} bytes report data",
+            name,
+            data.len(),
+            REPORT_DATA_LEN
+        ));
+    }
+    let mut report_data = [0; REPORT_DATA_LEN as usize];
+    report_data[..data.len()].copy_from_slice(data);
+    Ok(ReportData(report_data))
+}
+
+pub struct Sha512NonceData;
+
+impl ReportDataBinding for Sha512NonceData {
+    fn name(&self) -> &str {
+        "sha512"
+    }
+
+    fn bind(&self, nonce: &[u8], data: &[u8]) -> Result<ReportData, anyhow::Error> {
+        let mut hasher = Sha512::new();
+        hasher.update(nonce);
+        hasher.update(data);
+        ReportData::from_slice(hasher.finalize().as_slice())
+    }
+}
+
+pub struct Sha384Padded;
+
+impl ReportDataBinding for Sha384Padded {
+    fn name(&self) -> &str {
+        "sha384-padded"
+    }
+
+    fn bind(&self, nonce: &[u8], data: &[u8]) -> Result<ReportData, anyhow::Error> {
+        let mut hasher = Sha384::new();
+        hasher.update(nonce);
+        hasher.update(data);
+        zero_padded(hasher.finalize().as_slice(), self.name())
+    }
+}
+
+pub struct PubKeyHashNonce;
+
+impl PubKeyHashNonce {
+    pub const PUBKEY_HASH_LEN: usize = 32;
+    pub const NONCE_LEN: usize = REPORT_DATA_LEN as usize - PubKeyHashNonce::PUBKEY_HASH_LEN;
+}
+
+impl ReportDataBinding for PubKeyHashNonce {
+    fn name(&self) -> &str {
+        "pubkey-hash-nonce"
+    }
+
+    fn bind(&self, nonce: &[u8], data: &[u8]) -> Result<ReportData, anyhow::Error> {
+        // not padded, otherwise a nonce and the nonce with trailing zeros collide
+        if nonce.len() != PubKeyHashNonce::NONCE_LEN {
+            return Err(anyhow!(
+                "[{}] nonce of {} bytes, expect exactly {} bytes",
+                self.name(),
+                nonce.len(),
+                PubKeyHashNonce::NONCE_LEN
+            ));
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        let mut report_data = [0; REPORT_DATA_LEN as usize];
+        report_data[..PubKeyHashNonce::PUBKEY_HASH_LEN].copy_from_slice(&hasher.finalize());
+        report_data[PubKeyHashNonce::PUBKEY_HASH_LEN..].copy_from_slice(nonce);
+        Ok(ReportData(report_data))
+    }
+}
+
+pub struct RawReportData;
+
+impl ReportDataBinding for RawReportData {
+    fn name(&self) -> &str {
+        "raw"
+    }
+
+    // not padded, otherwise inputs differing in the split or in trailing zeros collide
+    fn bind(&self, nonce: &[u8], data: &[u8]) -> Result<ReportData, anyhow::Error> {
+        let report_data = [nonce, data].concat();
+        if report_data.len() != REPORT_DATA_LEN as usize {
+            return Err(anyhow!(
+                "[{}] nonce and data of {} bytes, expect exactly {} bytes",
+                self.name(),
+                report_data.len(),
+                REPORT_DATA_LEN
+            ));
+        }
+        ReportData::from_slice(&report_data)
+    }
+}
+
+#[cfg(test)]
+mod test_report_data_binding {
+    use super::*;
+
+    #[test]
+    //report data must be exactly 64 bytes
+    fn test_report_data_from_slice_wrong_length() {
+        assert!(ReportData::from_slice(&[0; 48]).is_err());
+        assert!(ReportData::from_slice(&[0; 64]).is_ok());
+    }
+
+    #[test]
+    //sha384 scheme keeps the last 16 bytes zero
+    fn test_sha384_padded_layout() {
+        let report_data = Sha384Padded.bind(b"nonce", b"data").unwrap();
+        let expected = Sha384::digest(b"noncedata");
+        assert_eq!(&report_data.as_bytes()[..48], expected.as_slice());
+        assert_eq!(report_data.as_bytes()[48..], [0; 16]);
+    }
+
+    #[test]
+    //public key hash scheme splits report data into key hash and nonce
+    fn test_pubkey_hash_nonce_layout() {
+        let nonce = [0xa5; 32];
+        let report_data = PubKeyHashNonce.bind(&nonce, b"public key").unwrap();
+        let expected = Sha256::digest(b"public key");
+        assert_eq!(&report_data.as_bytes()[..32], expected.as_slice());
+        assert_eq!(report_data.as_bytes()[32..], nonce);
+        assert!(PubKeyHashNonce
+            .verify(&report_data, &nonce, b"public key")
+            .unwrap());
+        assert!(!PubKeyHashNonce
+            .verify(&report_data, &nonce, b"another key")
+            .unwrap());
+    }
+
+    #[test]
+    //public key hash scheme rejects nonce longer than 32 bytes
+    fn test_pubkey_hash_nonce_long_nonce() {
+        assert!(PubKeyHashNonce.bind(&[0; 33], b"public key").is_err());
+    }
+
+    #[test]
+    //public key hash scheme rejects nonce shorter than 32 bytes instead of zero padding
+    fn test_pubkey_hash_nonce_short_nonce() {
+        assert!(PubKeyHashNonce.bind(&[0x5a; 31], b"public key").is_err());
+        assert!(PubKeyHashNonce.bind(&[], b"public key").is_err());
+    }
+
+    #[test]
+    //raw scheme passes the data through
+    fn test_raw_report_data() {
+        let data = [0x5a; 64];
+        assert_eq!(*RawReportData.bind(&[], &data).unwrap().as_bytes(), data);
+        assert!(RawReportData.bind(&[0; 1], &data).is_err());
+        assert_eq!(
+            *RawReportData
+                .bind(&data[..16], &data[16..])
+                .unwrap()
+                .as_bytes(),
+            data
+        );
+        // short input is rejected rather than zero padded
+        assert!(RawReportData.bind(b"a", b"b").is_err());
+        assert!(RawReportData.bind(b"ab", b"").is_err());
+    }
+}
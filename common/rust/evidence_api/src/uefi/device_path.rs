@@ -0,0 +1,305 @@
+use crate::binary_blob::*;
+use crate::uefi::*;
+use anyhow::anyhow;
+use core::fmt;
+
+/***
+    EFI_DEVICE_PATH_PROTOCOL node defined in UEFI specification 10.2.
+    typedef struct _EFI_DEVICE_PATH_PROTOCOL {
+        UINT8 Type;
+        UINT8 SubType;
+        UINT8 Length[2];
+    } EFI_DEVICE_PATH_PROTOCOL;
+    The node specific data follows the header, Length includes the header.
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DevicePathNode {
+    pub node_type: u8,
+    pub sub_type: u8,
+    pub data: Vec<u8>,
+}
+
+pub const HARDWARE_DEVICE_PATH: u8 = 0x01;
+pub const ACPI_DEVICE_PATH: u8 = 0x02;
+pub const MESSAGING_DEVICE_PATH: u8 = 0x03;
+pub const MEDIA_DEVICE_PATH: u8 = 0x04;
+pub const BBS_DEVICE_PATH: u8 = 0x05;
+pub const END_DEVICE_PATH: u8 = 0x7f;
+
+pub const HW_PCI_DP: u8 = 0x01;
+pub const HW_MEMMAP_DP: u8 = 0x03;
+pub const HW_VENDOR_DP: u8 = 0x04;
+pub const ACPI_DP: u8 = 0x01;
+pub const MSG_SCSI_DP: u8 = 0x02;
+pub const MSG_USB_DP: u8 = 0x05;
+pub const MSG_MAC_ADDR_DP: u8 = 0x0b;
+pub const MSG_SATA_DP: u8 = 0x12;
+pub const MSG_NVME_NAMESPACE_DP: u8 = 0x17;
+pub const MSG_URI_DP: u8 = 0x18;
+pub const MEDIA_HARDDRIVE_DP: u8 = 0x01;
+pub const MEDIA_CDROM_DP: u8 = 0x02;
+pub const MEDIA_VENDOR_DP: u8 = 0x03;
+pub const MEDIA_FILEPATH_DP: u8 = 0x04;
+pub const MEDIA_PIWG_FW_FILE_DP: u8 = 0x06;
+pub const MEDIA_PIWG_FW_VOL_DP: u8 = 0x07;
+pub const MEDIA_RELATIVE_OFFSET_RANGE_DP: u8 = 0x08;
+pub const END_INSTANCE_DEVICE_PATH_SUBTYPE: u8 = 0x01;
+pub const END_ENTIRE_DEVICE_PATH_SUBTYPE: u8 = 0xff;
+
+// EISA ID of PCI root bridges, PNP0A03 and PNP0A08
+const EISA_PNP0A03: u32 = 0x0a0341d0;
+const EISA_PNP0A08: u32 = 0x0a0841d0;
+
+impl DevicePathNode {
+    pub fn is_end(&self) -> bool {
+        self.node_type == END_DEVICE_PATH && self.sub_type == END_ENTIRE_DEVICE_PATH_SUBTYPE
+    }
+
+    // render the node in the generic format, e.g. Path(4,9,0102)
+    fn to_generic_text(&self) -> String {
+        format!(
+            "Path({},{},{})",
+            self.node_type,
+            self.sub_type,
+            hex::encode_upper(&self.data)
+        )
+    }
+
+    // render the node in the text format defined in UEFI specification 10.6
+    fn to_text(&self) -> String {
+        let d = &self.data;
+        let u16_at = |i: usize| get_u16(d[i..i + 2].to_vec());
+        let u32_at = |i: usize| get_u32(d[i..i + 4].to_vec());
+        let u64_at = |i: usize| get_u64(d[i..i + 8].to_vec());
+        let guid_at = |i: usize| EfiGuid::parse(&d[i..i + EfiGuid::LEN]).unwrap();
+
+        match (self.node_type, self.sub_type, d.len()) {
+            (HARDWARE_DEVICE_PATH, HW_PCI_DP, 2) => format!("Pci(0x{:x},0x{:x})", d[1], d[0]),
+            (HARDWARE_DEVICE_PATH, HW_MEMMAP_DP, 20) => format!(
+                "MemoryMapped(0x{:x},0x{:x},0x{:x})",
+                u32_at(0),
+                u64_at(4),
+                u64_at(12)
+            ),
+            (HARDWARE_DEVICE_PATH, HW_VENDOR_DP, l) if l >= 16 => vendor_text("VenHw", d),
+            (ACPI_DEVICE_PATH, ACPI_DP, 8) => {
+                let hid = u32_at(0);
+                let uid = u32_at(4);
+                if hid == EISA_PNP0A03 || hid == EISA_PNP0A08 {
+                    format!("PciRoot(0x{:x})", uid)
+                } else {
+                    format!("Acpi({},0x{:x})", eisa_id_text(hid), uid)
+                }
+            }
+            (MESSAGING_DEVICE_PATH, MSG_SCSI_DP, 4) => {
+                format!("Scsi(0x{:x},0x{:x})", u16_at(0), u16_at(2))
+            }
+            (MESSAGING_DEVICE_PATH, MSG_USB_DP, 2) => format!("USB(0x{:x},0x{:x})", d[0], d[1]),
+            (MESSAGING_DEVICE_PATH, MSG_MAC_ADDR_DP, 33) => {
+                // address is padded to 32 bytes, 6 bytes are shown for ethernet
+                let len = if d[32] == 0 || d[32] == 1 { 6 } else { 32 };
+                format!("MAC({},0x{:x})", hex::encode(&d[..len]), d[32])
+            }
+            (MESSAGING_DEVICE_PATH, MSG_SATA_DP, 6) => format!(
+                "Sata(0x{:x},0x{:x},0x{:x})",
+                u16_at(0),
+                u16_at(2),
+                u16_at(4)
+            ),
+            (MESSAGING_DEVICE_PATH, MSG_NVME_NAMESPACE_DP, 12) => {
+                let eui: Vec<String> = d[4..12].iter().map(|v| format!("{:02X}", v)).collect();
+                format!("NVMe(0x{:x},{})", u32_at(0), eui.join("-"))
+            }
+            (MESSAGING_DEVICE_PATH, MSG_URI_DP, _) => {
+                format!("Uri({})", String::from_utf8_lossy(d))
+            }
+            (MEDIA_DEVICE_PATH, MEDIA_HARDDRIVE_DP, 38) => {
+                let signature = match d[37] {
+                    // MBR signature is a 32 bit number
+                    0x01 => format!("MBR,0x{:08x}", u32_at(20)),
+                    0x02 => format!("GPT,{}", guid_at(20)),
+                    _ => format!("0x{:x},{}", d[37], hex::encode_upper(&d[20..36])),
+                };
+                format!(
+                    "HD({},{},0x{:x},0x{:x})",
+                    u32_at(0),
+                    signature,
+                    u64_at(4),
+                    u64_at(12)
+                )
+            }
+            (MEDIA_DEVICE_PATH, MEDIA_CDROM_DP, 20) => format!(
+                "CDROM(0x{:x},0x{:x},0x{:x})",
+                u32_at(0),
+                u64_at(4),
+                u64_at(12)
+            ),
+            (MEDIA_DEVICE_PATH, MEDIA_VENDOR_DP, l) if l >= 16 => vendor_text("VenMedia", d),
+            (MEDIA_DEVICE_PATH, MEDIA_FILEPATH_DP, _) => utf16_to_string(d),
+            (MEDIA_DEVICE_PATH, MEDIA_PIWG_FW_FILE_DP, 16) => format!("FvFile({})", guid_at(0)),
+            (MEDIA_DEVICE_PATH, MEDIA_PIWG_FW_VOL_DP, 16) => format!("Fv({})", guid_at(0)),
+            (MEDIA_DEVICE_PATH, MEDIA_RELATIVE_OFFSET_RANGE_DP, 20) => {
+                format!("Offset(0x{:x},0x{:x})", u64_at(4), u64_at(12))
+            }
+            (END_DEVICE_PATH, END_INSTANCE_DEVICE_PATH_SUBTYPE, _) => ",".to_string(),
+            _ => self.to_generic_text(),
+        }
+    }
+}
+
+// vendor defined node, the GUID is followed by vendor data
+fn vendor_text(name: &str, data: &[u8]) -> String {
+    let guid = EfiGuid::parse(data).unwrap();
+    if data.len() == EfiGuid::LEN {
+        format!("{}({})", name, guid)
+    } else {
+        format!(
+            "{}({},{})",
+            name,
+            guid,
+            hex::encode_upper(&data[EfiGuid::LEN..])
+        )
+    }
+}
+
+// compressed EISA ID, e.g. PNP0501
+fn eisa_id_text(id: u32) -> String {
+    let vendor = id & 0xffff;
+    let letter = |shift: u32| (((vendor >> shift) & 0x1f) as u8 + b'A' - 1) as char;
+    format!("{}{}{}{:04X}", letter(10), letter(5), letter(0), id >> 16)
+}
+
+/***
+    Device path, a list of EFI_DEVICE_PATH_PROTOCOL nodes ending with an End of
+    Hardware Device Path node.
+*/
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct DevicePath {
+    pub nodes: Vec<DevicePathNode>,
+}
+
+impl DevicePath {
+    /***
+        Parse device path nodes until the end node or the end of the data.
+        The end node is not kept in the nodes.
+    */
+    pub fn parse(data: &[u8]) -> Result<DevicePath, anyhow::Error> {
+        let mut nodes = Vec::new();
+        let mut index = 0;
+        while index < data.len() {
+            check_len(data, index, 4, "DevicePath::parse")?;
+            let length = get_u16(data[index + 2..index + 4].to_vec()) as usize;
+            if length < 4 {
+                return Err(anyhow!(
+                    "[DevicePath::parse] invalid node length {} at offset {}",
+                    length,
+                    index
+                ));
+            }
+            check_len(data, index, length, "DevicePath::parse")?;
+            let node = DevicePathNode {
+                node_type: data[index],
+                sub_type: data[index + 1],
+                data: data[index + 4..index + length].to_vec(),
+            };
+            index += length;
+            if node.is_end() {
+                break;
+            }
+            nodes.push(node);
+        }
+        Ok(DevicePath { nodes })
+    }
+
+    // the file path of the last file path node if any, e.g. \EFI\BOOT\BOOTX64.EFI
+    pub fn file_path(&self) -> Option<String> {
+        self.nodes
+            .iter()
+            .rev()
+            .find(|node| node.node_type == MEDIA_DEVICE_PATH && node.sub_type == MEDIA_FILEPATH_DP)
+            .map(|node| utf16_to_string(&node.data))
+    }
+}
+
+// text format defined in UEFI specification 10.6, e.g. PciRoot(0x0)/Pci(0x1,0x1)/\EFI\BOOT\BOOTX64.EFI
+impl fmt::Display for DevicePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = String::new();
+        for node in &self.nodes {
+            let node_text = node.to_text();
+            if !text.is_empty() && !text.ends_with(',') && node_text != "," {
+                text.push('/');
+            }
+            text.push_str(&node_text);
+        }
+        write!(f, "{}", text)
+    }
+}
+
+#[cfg(test)]
+mod test_device_path {
+    use super::*;
+
+    fn node(node_type: u8, sub_type: u8, data: &[u8]) -> Vec<u8> {
+        let mut raw = vec![node_type, sub_type];
+        raw.extend(((data.len() + 4) as u16).to_le_bytes());
+        raw.extend(data);
+        raw
+    }
+
+    fn file_path_node(path: &str) -> Vec<u8> {
+        let mut data: Vec<u8> = path.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        data.extend([0, 0]);
+        node(MEDIA_DEVICE_PATH, MEDIA_FILEPATH_DP, &data)
+    }
+
+    #[test]
+    //device path is rendered in UEFI text format
+    fn test_device_path_text() {
+        let mut raw = node(
+            ACPI_DEVICE_PATH,
+            ACPI_DP,
+            &[0xd0, 0x41, 0x03, 0x0a, 0, 0, 0, 0],
+        );
+        raw.extend(node(HARDWARE_DEVICE_PATH, HW_PCI_DP, &[1, 1]));
+        raw.extend(node(MESSAGING_DEVICE_PATH, MSG_SCSI_DP, &[0, 0, 1, 0]));
+        let mut hd = 1_u32.to_le_bytes().to_vec();
+        hd.extend(0x800_u64.to_le_bytes());
+        hd.extend(0x32000_u64.to_le_bytes());
+        hd.extend([
+            0x78, 0x56, 0x34, 0x12, 0x34, 0x12, 0x34, 0x12, 0x12, 0x34, 0x12, 0x34, 0x56, 0x78,
+            0x9a, 0xbc,
+        ]);
+        hd.extend([2, 2]);
+        raw.extend(node(MEDIA_DEVICE_PATH, MEDIA_HARDDRIVE_DP, &hd));
+        raw.extend(file_path_node("\\EFI\\BOOT\\BOOTX64.EFI"));
+        raw.extend(node(END_DEVICE_PATH, END_ENTIRE_DEVICE_PATH_SUBTYPE, &[]));
+
+        let device_path = DevicePath::parse(&raw).unwrap();
+        assert_eq!(
+            device_path.to_string(),
+            "PciRoot(0x0)/Pci(0x1,0x1)/Scsi(0x0,0x1)/HD(1,GPT,12345678-1234-1234-1234-123456789abc,0x800,0x32000)/\\EFI\\BOOT\\BOOTX64.EFI"
+        );
+        assert_eq!(device_path.file_path().unwrap(), "\\EFI\\BOOT\\BOOTX64.EFI");
+    }
+
+    #[test]
+    //unknown nodes are rendered in the generic format
+    fn test_unknown_node_text() {
+        let mut raw = node(
+            ACPI_DEVICE_PATH,
+            ACPI_DP,
+            &[0xd0, 0x41, 0x01, 0x05, 1, 0, 0, 0],
+        );
+        raw.extend(node(0x04, 0x09, &[0x01, 0x02]));
+        let device_path = DevicePath::parse(&raw).unwrap();
+        assert_eq!(device_path.to_string(), "Acpi(PNP0501,0x1)/Path(4,9,0102)");
+    }
+
+    #[test]
+    //node length smaller than the header is rejected
+    fn test_invalid_node_length() {
+        assert!(DevicePath::parse(&[0x04, 0x04, 0x02, 0x00]).is_err());
+        assert!(DevicePath::parse(&[0x04, 0x04, 0x08, 0x00, 0x00]).is_err());
+    }
+}
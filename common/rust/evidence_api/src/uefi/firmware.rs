@@ -0,0 +1,93 @@
+use crate::binary_blob::*;
+use crate::uefi::*;
+use log::info;
+
+/***
+    UEFI_PLATFORM_FIRMWARE_BLOB carried by EV_EFI_PLATFORM_FIRMWARE_BLOB events and
+    UEFI_PLATFORM_FIRMWARE_BLOB2 carried by EV_EFI_PLATFORM_FIRMWARE_BLOB2 events.
+    typedef struct tdUEFI_PLATFORM_FIRMWARE_BLOB {
+        UEFI_PHYSICAL_ADDRESS BlobBase;
+        UINT64 BlobLength;
+    } UEFI_PLATFORM_FIRMWARE_BLOB;
+    typedef struct tdUEFI_PLATFORM_FIRMWARE_BLOB2 {
+        UINT8 BlobDescriptionSize;
+        BYTE BlobDescription[BlobDescriptionSize];
+        UEFI_PHYSICAL_ADDRESS BlobBase;
+        UINT64 BlobLength;
+    } UEFI_PLATFORM_FIRMWARE_BLOB2;
+    The description is None for UEFI_PLATFORM_FIRMWARE_BLOB.
+*/
+#[derive(Clone, Debug)]
+pub struct UefiPlatformFirmwareBlob {
+    pub blob_description: Option<String>,
+    pub blob_base: u64,
+    pub blob_length: u64,
+}
+
+impl UefiPlatformFirmwareBlob {
+    pub fn parse(data: &[u8]) -> Result<UefiPlatformFirmwareBlob, anyhow::Error> {
+        check_len(data, 0, 16, "UefiPlatformFirmwareBlob::parse")?;
+        Ok(UefiPlatformFirmwareBlob {
+            blob_description: None,
+            blob_base: get_u64(data[0..8].to_vec()),
+            blob_length: get_u64(data[8..16].to_vec()),
+        })
+    }
+
+    pub fn parse_blob2(data: &[u8]) -> Result<UefiPlatformFirmwareBlob, anyhow::Error> {
+        check_len(data, 0, 1, "UefiPlatformFirmwareBlob::parse_blob2")?;
+        let description_size = data[0] as usize;
+        let index = 1 + description_size;
+        check_len(data, index, 16, "UefiPlatformFirmwareBlob::parse_blob2")?;
+        Ok(UefiPlatformFirmwareBlob {
+            blob_description: Some(description_to_string(&data[1..index])),
+            blob_base: get_u64(data[index..index + 8].to_vec()),
+            blob_length: get_u64(data[index + 8..index + 16].to_vec()),
+        })
+    }
+
+    pub fn show(&self) {
+        if let Some(description) = &self.blob_description {
+            info!("        BlobDescription   : {}", description);
+        }
+        info!("        BlobBase          : 0x{:x}", self.blob_base);
+        info!("        BlobLength        : 0x{:x}", self.blob_length);
+    }
+}
+
+// description of the *2 events is an ASCII string, usually NUL terminated
+pub(crate) fn description_to_string(data: &[u8]) -> String {
+    let end = data.iter().position(|v| *v == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+#[cfg(test)]
+mod test_uefi_firmware {
+    use super::*;
+
+    #[test]
+    //firmware volume measured by TDVF
+    fn test_firmware_blob2() {
+        let mut data = vec![41];
+        data.extend(b"Fv(XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX)\0");
+        data.extend(0xffc00000_u64.to_le_bytes());
+        data.extend(0x84000_u64.to_le_bytes());
+        let blob = UefiPlatformFirmwareBlob::parse_blob2(&data).unwrap();
+        assert_eq!(
+            blob.blob_description.unwrap(),
+            "Fv(XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX)"
+        );
+        assert_eq!(blob.blob_base, 0xffc00000);
+        assert_eq!(blob.blob_length, 0x84000);
+        assert!(UefiPlatformFirmwareBlob::parse_blob2(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    //firmware blob without description
+    fn test_firmware_blob() {
+        let blob = UefiPlatformFirmwareBlob::parse(&[0x10; 16]).unwrap();
+        assert!(blob.blob_description.is_none());
+        assert_eq!(blob.blob_length, 0x1010101010101010);
+        assert!(UefiPlatformFirmwareBlob::parse(&[0x10; 15]).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+use crate::binary_blob::*;
+use crate::uefi::*;
+use anyhow::anyhow;
+use log::info;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+pub const GPT_HEADER_LEN: usize = 92;
+pub const GPT_PARTITION_ENTRY_LEN: usize = 128;
+// upper bound of the partition entry array read from disk images
+pub const GPT_MAX_PARTITION_ENTRIES: usize = 4096;
+
+/***
+    CRC32 used by the GPT header and partition entry array, i.e. the ISO-HDLC
+    CRC with reflected polynomial 0xEDB88320, see UEFI specification 5.3.2.
+*/
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffff_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/***
+    UEFI_PARTITION_TABLE_HEADER defined in UEFI specification 5.3.2.
+    typedef struct {
+        UINT64 Signature;
+        UINT32 Revision;
+        UINT32 HeaderSize;
+        UINT32 HeaderCRC32;
+        UINT32 Reserved;
+        UINT64 MyLBA;
+        UINT64 AlternateLBA;
+        UINT64 FirstUsableLBA;
+        UINT64 LastUsableLBA;
+        EFI_GUID DiskGUID;
+        UINT64 PartitionEntryLBA;
+        UINT32 NumberOfPartitionEntries;
+        UINT32 SizeOfPartitionEntry;
+        UINT32 PartitionEntryArrayCRC32;
+    } UEFI_PARTITION_TABLE_HEADER;
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UefiPartitionTableHeader {
+    pub signature: [u8; 8],
+    pub revision: u32,
+    pub header_size: u32,
+    pub header_crc32: u32,
+    pub my_lba: u64,
+    pub alternate_lba: u64,
+    pub first_usable_lba: u64,
+    pub last_usable_lba: u64,
+    pub disk_guid: EfiGuid,
+    pub partition_entry_lba: u64,
+    pub number_of_partition_entries: u32,
+    pub size_of_partition_entry: u32,
+    pub partition_entry_array_crc32: u32,
+}
+
+impl UefiPartitionTableHeader {
+    pub fn parse(data: &[u8]) -> Result<UefiPartitionTableHeader, anyhow::Error> {
+        check_len(data, 0, GPT_HEADER_LEN, "UefiPartitionTableHeader::parse")?;
+        if &data[0..8] != GPT_SIGNATURE {
+            return Err(anyhow!(
+                "[UefiPartitionTableHeader::parse] invalid GPT signature {:02X?}",
+                &data[0..8]
+            ));
+        }
+        let header = UefiPartitionTableHeader {
+            signature: data[0..8].try_into().unwrap(),
+            revision: get_u32(data[8..12].to_vec()),
+            header_size: get_u32(data[12..16].to_vec()),
+            header_crc32: get_u32(data[16..20].to_vec()),
+            my_lba: get_u64(data[24..32].to_vec()),
+            alternate_lba: get_u64(data[32..40].to_vec()),
+            first_usable_lba: get_u64(data[40..48].to_vec()),
+            last_usable_lba: get_u64(data[48..56].to_vec()),
+            disk_guid: EfiGuid::parse(&data[56..72])?,
+            partition_entry_lba: get_u64(data[72..80].to_vec()),
+            number_of_partition_entries: get_u32(data[80..84].to_vec()),
+            size_of_partition_entry: get_u32(data[84..88].to_vec()),
+            partition_entry_array_crc32: get_u32(data[88..92].to_vec()),
+        };
+        if (header.size_of_partition_entry as usize) < GPT_PARTITION_ENTRY_LEN {
+            return Err(anyhow!(
+                "[UefiPartitionTableHeader::parse] invalid SizeOfPartitionEntry {}",
+                header.size_of_partition_entry
+            ));
+        }
+        Ok(header)
+    }
+}
+
+/***
+    UEFI_PARTITION_ENTRY defined in UEFI specification 5.3.3.
+    typedef struct {
+        EFI_GUID PartitionTypeGUID;
+        EFI_GUID UniquePartitionGUID;
+        UINT64 StartingLBA;
+        UINT64 EndingLBA;
+        UINT64 Attributes;
+        CHAR16 PartitionName[36];
+    } UEFI_PARTITION_ENTRY;
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UefiPartitionEntry {
+    pub partition_type_guid: EfiGuid,
+    pub unique_partition_guid: EfiGuid,
+    pub starting_lba: u64,
+    pub ending_lba: u64,
+    pub attributes: u64,
+    pub partition_name: String,
+}
+
+impl UefiPartitionEntry {
+    pub fn parse(data: &[u8]) -> Result<UefiPartitionEntry, anyhow::Error> {
+        check_len(
+            data,
+            0,
+            GPT_PARTITION_ENTRY_LEN,
+            "UefiPartitionEntry::parse",
+        )?;
+        Ok(UefiPartitionEntry {
+            partition_type_guid: EfiGuid::parse(&data[0..16])?,
+            unique_partition_guid: EfiGuid::parse(&data[16..32])?,
+            starting_lba: get_u64(data[32..40].to_vec()),
+            ending_lba: get_u64(data[40..48].to_vec()),
+            attributes: get_u64(data[48..56].to_vec()),
+            partition_name: utf16_to_string(&data[56..128]),
+        })
+    }
+
+    // entries with zero type GUID are unused and not measured
+    pub fn is_used(&self) -> bool {
+        self.partition_type_guid != EfiGuid::new(0, 0, 0, [0; 8])
+    }
+}
+
+/***
+    UEFI_GPT_DATA carried by EV_EFI_GPT_EVENT and EV_EFI_GPT_EVENT2 events.
+    typedef struct {
+        UEFI_PARTITION_TABLE_HEADER UEFIPartitionHeader;
+        UINT64 NumberOfPartitions;
+        UEFI_PARTITION_ENTRY Partitions[NumberOfPartitions];
+    } UEFI_GPT_DATA;
+    Only the used partition entries are measured by the firmware.
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UefiGptData {
+    pub header: UefiPartitionTableHeader,
+    pub partitions: Vec<UefiPartitionEntry>,
+}
+
+impl UefiGptData {
+    pub fn parse(data: &[u8]) -> Result<UefiGptData, anyhow::Error> {
+        let header = UefiPartitionTableHeader::parse(data)?;
+        check_len(data, GPT_HEADER_LEN, 8, "UefiGptData::parse")?;
+        let count = get_u64(data[GPT_HEADER_LEN..GPT_HEADER_LEN + 8].to_vec());
+        let entry_size = header.size_of_partition_entry as usize;
+        let entries_len = match usize::try_from(count)
+            .ok()
+            .and_then(|v| v.checked_mul(entry_size))
+        {
+            Some(v) => v,
+            None => {
+                return Err(anyhow!(
+                    "[UefiGptData::parse] invalid NumberOfPartitions {}",
+                    count
+                ))
+            }
+        };
+        let index = GPT_HEADER_LEN + 8;
+        check_len(data, index, entries_len, "UefiGptData::parse")?;
+
+        let mut partitions = Vec::new();
+        for entry in data[index..index + entries_len].chunks(entry_size) {
+            partitions.push(UefiPartitionEntry::parse(entry)?);
+        }
+        Ok(UefiGptData { header, partitions })
+    }
+
+    /***
+        Read the primary GPT of a disk image in the way the firmware measures it.
+        Args:
+            path: path of the raw disk image or block device
+            block_size: logical block size of the disk, usually 512
+        Returns:
+            The GPT data containing the header and the used partition entries,
+            error if the header or the partition entry array fails its CRC32 check
+    */
+    pub fn from_disk_image<P: AsRef<Path>>(
+        path: P,
+        block_size: u64,
+    ) -> Result<UefiGptData, anyhow::Error> {
+        let mut file = File::open(path.as_ref()).map_err(|e| {
+            anyhow!(
+                "[from_disk_image] failed to open {}: {:?}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+        // the metadata length of a block device is 0, seek to its end instead
+        let image_len = file
+            .seek(SeekFrom::End(0))
+            .map_err(|e| anyhow!("[from_disk_image] failed to read disk image: {:?}", e))?;
+        let mut read_at = |offset: u64, len: usize| -> Result<Vec<u8>, anyhow::Error> {
+            let mut buf = vec![0; len];
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_exact(&mut buf))
+                .map_err(|e| anyhow!("[from_disk_image] failed to read disk image: {:?}", e))?;
+            Ok(buf)
+        };
+
+        let header_block = read_at(block_size, GPT_HEADER_LEN)?;
+        let header = UefiPartitionTableHeader::parse(&header_block)?;
+        let header_size = header.header_size as usize;
+        if header_size < GPT_HEADER_LEN || header_size as u64 > block_size {
+            return Err(anyhow!(
+                "[from_disk_image] invalid HeaderSize {}",
+                header.header_size
+            ));
+        }
+        let mut header_data = read_at(block_size, header_size)?;
+        header_data[16..20].fill(0);
+        if crc32(&header_data) != header.header_crc32 {
+            return Err(anyhow!("[from_disk_image] invalid GPT header CRC32"));
+        }
+
+        let entry_size = header.size_of_partition_entry as usize;
+        let entry_count = header.number_of_partition_entries as usize;
+        let entries_len = match entry_count.checked_mul(entry_size) {
+            Some(v) if entry_count <= GPT_MAX_PARTITION_ENTRIES => v,
+            _ => {
+                return Err(anyhow!(
+                    "[from_disk_image] invalid NumberOfPartitionEntries {}",
+                    header.number_of_partition_entries
+                ))
+            }
+        };
+        let entries_offset = match header
+            .partition_entry_lba
+            .checked_mul(block_size)
+            .filter(|v| v.checked_add(entries_len as u64) <= Some(image_len))
+        {
+            Some(v) => v,
+            None => {
+                return Err(anyhow!(
+                    "[from_disk_image] invalid PartitionEntryLBA {}",
+                    header.partition_entry_lba
+                ))
+            }
+        };
+        let entries = read_at(entries_offset, entries_len)?;
+        if crc32(&entries) != header.partition_entry_array_crc32 {
+            return Err(anyhow!(
+                "[from_disk_image] invalid GPT partition entry array CRC32"
+            ));
+        }
+        let mut partitions = Vec::new();
+        for entry in entries.chunks(entry_size) {
+            let partition = UefiPartitionEntry::parse(entry)?;
+            if partition.is_used() {
+                partitions.push(partition);
+            }
+        }
+        Ok(UefiGptData { header, partitions })
+    }
+
+    /***
+        Check the GPT of a disk image against the measured GPT data.
+        Returns:
+            true if the disk image has the measured partition table
+    */
+    pub fn check_disk_image<P: AsRef<Path>>(
+        &self,
+        path: P,
+        block_size: u64,
+    ) -> Result<bool, anyhow::Error> {
+        Ok(UefiGptData::from_disk_image(path, block_size)? == *self)
+    }
+
+    pub fn show(&self) {
+        info!("        DiskGUID          : {}", self.header.disk_guid);
+        info!(
+            "        UsableLBA         : 0x{:x} - 0x{:x}",
+            self.header.first_usable_lba, self.header.last_usable_lba
+        );
+        for (index, partition) in self.partitions.iter().enumerate() {
+            info!(
+                "        Partition[{}]      : {} type {} unique {} LBA 0x{:x} - 0x{:x} attributes 0x{:x}",
+                index,
+                partition.partition_name,
+                partition.partition_type_guid,
+                partition.unique_partition_guid,
+                partition.starting_lba,
+                partition.ending_lba,
+                partition.attributes
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_uefi_gpt {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::tcg::*;
+    use std::fs;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    fn tdvf_gpt_event() -> Vec<u8> {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        for event in event_logs.select(None, None).unwrap() {
+            if let EventLogEntry::TcgImrEvent(event) = event {
+                if event.event_type == EV_EFI_GPT_EVENT {
+                    return event.event;
+                }
+            }
+        }
+        panic!("no GPT event");
+    }
+
+    // disk image with the measured GPT and its CRC32 values recomputed, the used
+    // partitions of the measured cloud image are partitions 1, 14 and 15
+    fn disk_image(event: &[u8], name: &str) -> std::path::PathBuf {
+        let gpt = UefiGptData::parse(event).unwrap();
+        let entry_lba = gpt.header.partition_entry_lba as usize;
+        let mut image = vec![0; (entry_lba + 32) * 512];
+        image[512..512 + GPT_HEADER_LEN].copy_from_slice(&event[..GPT_HEADER_LEN]);
+        let entries = event[GPT_HEADER_LEN + 8..].chunks(GPT_PARTITION_ENTRY_LEN);
+        for (entry, slot) in entries.zip([0, 13, 14]) {
+            let offset = entry_lba * 512 + slot * GPT_PARTITION_ENTRY_LEN;
+            image[offset..offset + GPT_PARTITION_ENTRY_LEN].copy_from_slice(entry);
+        }
+
+        let entries_len = gpt.header.number_of_partition_entries as usize * GPT_PARTITION_ENTRY_LEN;
+        let entries_crc = crc32(&image[entry_lba * 512..entry_lba * 512 + entries_len]);
+        image[512 + 88..512 + 92].copy_from_slice(&entries_crc.to_le_bytes());
+        image[512 + 16..512 + 20].fill(0);
+        let header_crc = crc32(&image[512..512 + GPT_HEADER_LEN]);
+        image[512 + 16..512 + 20].copy_from_slice(&header_crc.to_le_bytes());
+
+        let path = std::env::temp_dir().join(format!("{}-{}.img", name, std::process::id()));
+        fs::write(&path, image).unwrap();
+        path
+    }
+
+    #[test]
+    //GPT measured by TDVF is decoded
+    fn test_tdvf_gpt_event() {
+        let event = TcgImrEvent {
+            imr_index: 1,
+            event_type: EV_EFI_GPT_EVENT,
+            digests: Vec::new(),
+            event_size: 0,
+            event: tdvf_gpt_event(),
+        };
+        let gpt = match event.parse_event_data().unwrap() {
+            TcgEventData::UefiGpt(gpt) => gpt,
+            _ => panic!("unexpected event data"),
+        };
+        assert_eq!(gpt.header.my_lba, 1);
+        assert_eq!(gpt.partitions.len(), 3);
+        assert!(gpt.partitions.iter().all(|p| p.is_used()));
+        assert!(gpt
+            .partitions
+            .iter()
+            .any(|p| p.unique_partition_guid.to_string()
+                == "507a4a79-eca4-4fd0-8bbc-d5f4fac3e723"
+                && p.starting_lba == 0x2800));
+    }
+
+    #[test]
+    //disk image is checked against the measured GPT
+    fn test_check_disk_image() {
+        let event = tdvf_gpt_event();
+        let gpt = UefiGptData::parse(&event).unwrap();
+        let path = disk_image(&event, "gpt-match");
+        assert_eq!(
+            fs::read(&path).unwrap()[512..512 + GPT_HEADER_LEN],
+            event[..GPT_HEADER_LEN]
+        );
+        assert!(gpt.check_disk_image(&path, 512).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let mut modified = event.clone();
+        modified[GPT_HEADER_LEN + 8 + 32] ^= 1;
+        let path = disk_image(&modified, "gpt-mismatch");
+        assert!(!gpt.check_disk_image(&path, 512).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    //partition count beyond the event is rejected
+    fn test_truncated_gpt_event() {
+        let mut event = tdvf_gpt_event();
+        event[GPT_HEADER_LEN] += 1;
+        assert!(UefiGptData::parse(&event).is_err());
+        assert!(UefiGptData::parse(&event[..GPT_HEADER_LEN]).is_err());
+    }
+
+    #[test]
+    //CRC32 check value of the ISO-HDLC CRC
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    //disk images failing the CRC32 or bounds checks are rejected
+    fn test_invalid_disk_image() {
+        let event = tdvf_gpt_event();
+        let path = disk_image(&event, "gpt-valid");
+        let image = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let entry_lba = UefiGptData::parse(&event)
+            .unwrap()
+            .header
+            .partition_entry_lba as usize;
+
+        let mut cases = Vec::new();
+        let mut corrupted = image.clone();
+        corrupted[512 + 40] ^= 1;
+        cases.push(corrupted);
+        let mut corrupted = image.clone();
+        corrupted[entry_lba * 512 + 32] ^= 1;
+        cases.push(corrupted);
+        let mut corrupted = image.clone();
+        corrupted[512 + 80..512 + 84].copy_from_slice(&u32::MAX.to_le_bytes());
+        corrupted[512 + 16..512 + 20].fill(0);
+        let header_crc = crc32(&corrupted[512..512 + GPT_HEADER_LEN]);
+        corrupted[512 + 16..512 + 20].copy_from_slice(&header_crc.to_le_bytes());
+        cases.push(corrupted);
+        cases.push(image[..entry_lba * 512 + 512].to_vec());
+
+        let path = std::env::temp_dir().join(format!("gpt-invalid-{}.img", std::process::id()));
+        for image in cases {
+            fs::write(&path, image).unwrap();
+            let error = UefiGptData::from_disk_image(&path, 512).unwrap_err();
+            assert!(error.to_string().starts_with("[from_disk_image] invalid"));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
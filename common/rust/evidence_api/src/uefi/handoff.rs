@@ -0,0 +1,209 @@
+use crate::binary_blob::*;
+use crate::uefi::firmware::description_to_string;
+use crate::uefi::*;
+use anyhow::anyhow;
+use log::info;
+
+pub const EFI_ACPI_10_TABLE_GUID: EfiGuid = EfiGuid::new(
+    0xeb9d2d30,
+    0x2d88,
+    0x11d3,
+    [0x9a, 0x16, 0x00, 0x90, 0x27, 0x3f, 0xc1, 0x4d],
+);
+pub const EFI_ACPI_20_TABLE_GUID: EfiGuid = EfiGuid::new(
+    0x8868e871,
+    0xe4f1,
+    0x11d3,
+    [0xbc, 0x22, 0x00, 0x80, 0xc7, 0x3c, 0x88, 0x81],
+);
+pub const SMBIOS_TABLE_GUID: EfiGuid = EfiGuid::new(
+    0xeb9d2d31,
+    0x2d88,
+    0x11d3,
+    [0x9a, 0x16, 0x00, 0x90, 0x27, 0x3f, 0xc1, 0x4d],
+);
+pub const SMBIOS3_TABLE_GUID: EfiGuid = EfiGuid::new(
+    0xf2fd1544,
+    0x9794,
+    0x4a2c,
+    [0x99, 0x2e, 0xe5, 0xbb, 0xcf, 0x20, 0xe3, 0x94],
+);
+pub const EFI_HOB_LIST_GUID: EfiGuid = EfiGuid::new(
+    0x7739f24c,
+    0x93d7,
+    0x11d4,
+    [0x9a, 0x3a, 0x00, 0x90, 0x27, 0x3f, 0xc1, 0x4d],
+);
+
+/***
+    EFI_CONFIGURATION_TABLE defined in UEFI specification 4.6.
+    typedef struct {
+        EFI_GUID VendorGuid;
+        VOID *VendorTable;
+    } EFI_CONFIGURATION_TABLE;
+*/
+#[derive(Clone, Debug)]
+pub struct EfiConfigurationTable {
+    pub vendor_guid: EfiGuid,
+    pub vendor_table: u64,
+}
+
+impl EfiConfigurationTable {
+    // name of well-known configuration tables
+    pub fn name(&self) -> Option<&'static str> {
+        match self.vendor_guid {
+            EFI_ACPI_10_TABLE_GUID => Some("ACPI 1.0"),
+            EFI_ACPI_20_TABLE_GUID => Some("ACPI 2.0"),
+            SMBIOS_TABLE_GUID => Some("SMBIOS"),
+            SMBIOS3_TABLE_GUID => Some("SMBIOS3"),
+            EFI_HOB_LIST_GUID => Some("HOB List"),
+            _ => None,
+        }
+    }
+}
+
+/***
+    UEFI_HANDOFF_TABLE_POINTERS carried by EV_EFI_HANDOFF_TABLES events and
+    UEFI_HANDOFF_TABLE_POINTERS2 carried by EV_EFI_HANDOFF_TABLES2 events.
+    typedef struct tdUEFI_HANDOFF_TABLE_POINTERS {
+        UINT64 NumberOfTables;
+        UEFI_CONFIGURATION_TABLE TableEntry[NumberOfTables];
+    } UEFI_HANDOFF_TABLE_POINTERS;
+    typedef struct tdUEFI_HANDOFF_TABLE_POINTERS2 {
+        UINT8 TableDescriptionSize;
+        BYTE TableDescription[TableDescriptionSize];
+        UINT64 NumberOfTables;
+        UEFI_CONFIGURATION_TABLE TableEntry[NumberOfTables];
+    } UEFI_HANDOFF_TABLE_POINTERS2;
+    The description is None for UEFI_HANDOFF_TABLE_POINTERS.
+*/
+#[derive(Clone, Debug)]
+pub struct UefiHandoffTablePointers {
+    pub table_description: Option<String>,
+    pub table_entries: Vec<EfiConfigurationTable>,
+}
+
+impl UefiHandoffTablePointers {
+    pub fn parse(data: &[u8]) -> Result<UefiHandoffTablePointers, anyhow::Error> {
+        Ok(UefiHandoffTablePointers {
+            table_description: None,
+            table_entries: parse_table_entries(data)?,
+        })
+    }
+
+    pub fn parse_tables2(data: &[u8]) -> Result<UefiHandoffTablePointers, anyhow::Error> {
+        check_len(data, 0, 1, "UefiHandoffTablePointers::parse_tables2")?;
+        let index = 1 + data[0] as usize;
+        check_len(
+            data,
+            1,
+            data[0] as usize,
+            "UefiHandoffTablePointers::parse_tables2",
+        )?;
+        Ok(UefiHandoffTablePointers {
+            table_description: Some(description_to_string(&data[1..index])),
+            table_entries: parse_table_entries(&data[index..])?,
+        })
+    }
+
+    pub fn show(&self) {
+        if let Some(description) = &self.table_description {
+            info!("        TableDescription  : {}", description);
+        }
+        for (index, table) in self.table_entries.iter().enumerate() {
+            info!(
+                "        TableEntry[{}]     : {} {} 0x{:x}",
+                index,
+                table.vendor_guid,
+                table.name().unwrap_or("-"),
+                table.vendor_table
+            );
+        }
+    }
+}
+
+// NumberOfTables followed by the configuration tables
+fn parse_table_entries(data: &[u8]) -> Result<Vec<EfiConfigurationTable>, anyhow::Error> {
+    check_len(data, 0, 8, "parse_table_entries")?;
+    let count = get_u64(data[0..8].to_vec());
+    let entries_len = match usize::try_from(count).ok().and_then(|v| v.checked_mul(24)) {
+        Some(v) => v,
+        None => {
+            return Err(anyhow!(
+                "[parse_table_entries] invalid NumberOfTables {}",
+                count
+            ))
+        }
+    };
+    check_len(data, 8, entries_len, "parse_table_entries")?;
+    data[8..8 + entries_len]
+        .chunks(24)
+        .map(|entry| {
+            Ok(EfiConfigurationTable {
+                vendor_guid: EfiGuid::parse(&entry[0..16])?,
+                vendor_table: get_u64(entry[16..24].to_vec()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_uefi_handoff {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::tcg::*;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    #[test]
+    //TDVF firmware volume and TD tables are decoded
+    fn test_tdvf_blob_and_handoff_events() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let mut decoded = 0;
+        for event in event_logs.select(None, None).unwrap() {
+            if let EventLogEntry::TcgImrEvent(event) = event {
+                match event.parse_event_data().unwrap() {
+                    TcgEventData::UefiHandoffTables(tables) => {
+                        assert_eq!(tables.table_description.unwrap(), "TdxTable");
+                        assert_eq!(tables.table_entries.len(), 1);
+                        assert_eq!(tables.table_entries[0].vendor_table, 0x809000);
+                        decoded += 1;
+                    }
+                    TcgEventData::UefiFirmwareBlob(blob) => {
+                        assert_eq!(
+                            blob.blob_description.unwrap(),
+                            "Fv(XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX)"
+                        );
+                        assert_eq!(blob.blob_base, 0xffc00000);
+                        decoded += 1;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        assert_eq!(decoded, 2);
+    }
+
+    #[test]
+    //well-known configuration tables are named
+    fn test_handoff_tables() {
+        let mut data = 2_u64.to_le_bytes().to_vec();
+        for (guid, address) in [
+            (EFI_ACPI_20_TABLE_GUID, 0x7fb7e014_u64),
+            (SMBIOS3_TABLE_GUID, 0x7f9f0000),
+        ] {
+            data.extend(guid.data1.to_le_bytes());
+            data.extend(guid.data2.to_le_bytes());
+            data.extend(guid.data3.to_le_bytes());
+            data.extend(guid.data4);
+            data.extend(address.to_le_bytes());
+        }
+        let tables = UefiHandoffTablePointers::parse(&data).unwrap();
+        assert!(tables.table_description.is_none());
+        assert_eq!(tables.table_entries[0].name(), Some("ACPI 2.0"));
+        assert_eq!(tables.table_entries[1].name(), Some("SMBIOS3"));
+        assert!(UefiHandoffTablePointers::parse(&data[..data.len() - 1]).is_err());
+    }
+}
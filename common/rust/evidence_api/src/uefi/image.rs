@@ -0,0 +1,109 @@
+use crate::binary_blob::*;
+use crate::uefi::device_path::DevicePath;
+use crate::uefi::*;
+use anyhow::anyhow;
+use log::info;
+
+/***
+    UEFI_IMAGE_LOAD_EVENT carried by EV_EFI_BOOT_SERVICES_APPLICATION,
+    EV_EFI_BOOT_SERVICES_DRIVER and EV_EFI_RUNTIME_SERVICES_DRIVER events.
+    typedef struct tdUEFI_IMAGE_LOAD_EVENT {
+        UEFI_PHYSICAL_ADDRESS ImageLocationInMemory;
+        UINT64 ImageLengthInMemory;
+        UINT64 ImageLinkTimeAddress;
+        UINT64 LengthOfDevicePath;
+        UEFI_DEVICE_PATH DevicePath[LengthOfDevicePath];
+    } UEFI_IMAGE_LOAD_EVENT;
+*/
+#[derive(Clone, Debug)]
+pub struct UefiImageLoadEvent {
+    pub image_location_in_memory: u64,
+    pub image_length_in_memory: u64,
+    pub image_link_time_address: u64,
+    pub device_path: DevicePath,
+}
+
+impl UefiImageLoadEvent {
+    pub fn parse(data: &[u8]) -> Result<UefiImageLoadEvent, anyhow::Error> {
+        check_len(data, 0, 32, "UefiImageLoadEvent::parse")?;
+        let device_path_len = match usize::try_from(get_u64(data[24..32].to_vec())) {
+            Ok(v) => v,
+            Err(_) => {
+                return Err(anyhow!(
+                    "[UefiImageLoadEvent::parse] invalid LengthOfDevicePath"
+                ))
+            }
+        };
+        check_len(data, 32, device_path_len, "UefiImageLoadEvent::parse")?;
+
+        Ok(UefiImageLoadEvent {
+            image_location_in_memory: get_u64(data[0..8].to_vec()),
+            image_length_in_memory: get_u64(data[8..16].to_vec()),
+            image_link_time_address: get_u64(data[16..24].to_vec()),
+            device_path: DevicePath::parse(&data[32..32 + device_path_len])?,
+        })
+    }
+
+    pub fn show(&self) {
+        info!(
+            "        ImageLocation     : 0x{:x}",
+            self.image_location_in_memory
+        );
+        info!(
+            "        ImageLength       : 0x{:x}",
+            self.image_length_in_memory
+        );
+        info!(
+            "        LinkTimeAddress   : 0x{:x}",
+            self.image_link_time_address
+        );
+        info!("        DevicePath        : {}", self.device_path);
+    }
+}
+
+#[cfg(test)]
+mod test_uefi_image {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::tcg::*;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    #[test]
+    //device path longer than the event is rejected
+    fn test_truncated_image_load_event() {
+        let mut data = vec![0; 32];
+        data[24] = 4;
+        assert!(UefiImageLoadEvent::parse(&data).is_err());
+        data.extend([0x7f, 0xff, 0x04, 0x00]);
+        let event = UefiImageLoadEvent::parse(&data).unwrap();
+        assert!(event.device_path.nodes.is_empty());
+    }
+
+    #[test]
+    //images loaded by TDVF are decoded with their device paths
+    fn test_tdvf_image_load_events() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let mut images = Vec::new();
+        for event in event_logs.select(None, None).unwrap() {
+            if let EventLogEntry::TcgImrEvent(event) = event {
+                if let TcgEventData::UefiImageLoad(image) = event.parse_event_data().unwrap() {
+                    assert_eq!(event.imr_index, 1);
+                    images.push(image.device_path.to_string());
+                }
+            }
+        }
+        let disk = "PciRoot(0x0)/Pci(0x3,0x0)/HD(15,GPT,507a4a79-eca4-4fd0-8bbc-d5f4fac3e723,0x2800,0x35000)";
+        assert_eq!(
+            images,
+            vec![
+                format!("{}/\\EFI\\BOOT\\BOOTX64.EFI", disk),
+                "\\EFI\\BOOT\\fbx64.efi".to_string(),
+                format!("{}/\\EFI\\ubuntu\\shimx64.efi", disk),
+                "\\EFI\\ubuntu\\grubx64.efi".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+/***
+    Decoders of the UEFI structures carried by the event data of EV_EFI_* events,
+    defined in TCG PC Client Platform Firmware Profile Specification at
+    https://trustedcomputinggroup.org/wp-content/uploads/PC-Client-Specific-Platform-Firmware-Profile-Version-1.06-Revision-52_pub.pdf
+    and in UEFI Specification at https://uefi.org/specifications.
+*/
+pub mod device_path;
+pub mod firmware;
+pub mod gpt;
+pub mod handoff;
+pub mod image;
+pub mod signature;
+pub mod spdm;
+pub mod variable;
+
+use crate::binary_blob::*;
+use anyhow::anyhow;
+use core::fmt;
+
+/***
+    EFI_GUID defined in UEFI specification Appendix A.
+    typedef struct {
+        UINT32 Data1;
+        UINT16 Data2;
+        UINT16 Data3;
+        UINT8 Data4[8];
+    } EFI_GUID;
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct EfiGuid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl EfiGuid {
+    pub const LEN: usize = 16;
+
+    pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> EfiGuid {
+        EfiGuid {
+            data1,
+            data2,
+            data3,
+            data4,
+        }
+    }
+
+    pub fn parse(data: &[u8]) -> Result<EfiGuid, anyhow::Error> {
+        if data.len() < EfiGuid::LEN {
+            return Err(anyhow!(
+                "[parse] EFI_GUID needs {} bytes, got {} bytes",
+                EfiGuid::LEN,
+                data.len()
+            ));
+        }
+        Ok(EfiGuid {
+            data1: get_u32(data[0..4].to_vec()),
+            data2: get_u16(data[4..6].to_vec()),
+            data3: get_u16(data[6..8].to_vec()),
+            data4: data[8..16].try_into().unwrap(),
+        })
+    }
+}
+
+// registry format, e.g. 8be4df61-93ca-11d2-aa0d-00e098032b8c
+impl fmt::Display for EfiGuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{}-{}",
+            self.data1,
+            self.data2,
+            self.data3,
+            hex::encode(&self.data4[0..2]),
+            hex::encode(&self.data4[2..8])
+        )
+    }
+}
+
+pub const EFI_GLOBAL_VARIABLE: EfiGuid = EfiGuid::new(
+    0x8be4df61,
+    0x93ca,
+    0x11d2,
+    [0xaa, 0x0d, 0x00, 0xe0, 0x98, 0x03, 0x2b, 0x8c],
+);
+pub const EFI_IMAGE_SECURITY_DATABASE_GUID: EfiGuid = EfiGuid::new(
+    0xd719b2cb,
+    0x3d3a,
+    0x4596,
+    [0xa3, 0xbc, 0xda, 0xd0, 0x0e, 0x67, 0x65, 0x6f],
+);
+
+// decode UTF-16LE string, the string ends at the first NUL character if any
+pub fn utf16_to_string(data: &[u8]) -> String {
+    let chars: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    String::from_utf16_lossy(&chars)
+}
+
+/***
+    Decode NUL terminated UTF-16LE string at the beginning of the data.
+    Returns:
+        The string and the number of bytes consumed including the NUL character
+*/
+pub fn read_utf16_nul(data: &[u8]) -> Result<(String, usize), anyhow::Error> {
+    match data.chunks_exact(2).position(|c| c == [0, 0]) {
+        Some(pos) => Ok((utf16_to_string(&data[..pos * 2]), pos * 2 + 2)),
+        None => Err(anyhow!(
+            "[read_utf16_nul] UTF-16 string is not NUL terminated"
+        )),
+    }
+}
+
+// check that the data contains len bytes from offset
+pub(crate) fn check_len(
+    data: &[u8],
+    offset: usize,
+    len: usize,
+    name: &str,
+) -> Result<(), anyhow::Error> {
+    match offset.checked_add(len) {
+        Some(end) if end <= data.len() => Ok(()),
+        _ => Err(anyhow!(
+            "[{}] truncated data, need {} bytes at offset {}, got {} bytes",
+            name,
+            len,
+            offset,
+            data.len()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test_uefi {
+    use super::*;
+
+    #[test]
+    //GUID is shown in registry format
+    fn test_guid_display() {
+        let data = [
+            0x61, 0xdf, 0xe4, 0x8b, 0xca, 0x93, 0xd2, 0x11, 0xaa, 0x0d, 0x00, 0xe0, 0x98, 0x03,
+            0x2b, 0x8c,
+        ];
+        let guid = EfiGuid::parse(&data).unwrap();
+        assert_eq!(guid, EFI_GLOBAL_VARIABLE);
+        assert_eq!(guid.to_string(), "8be4df61-93ca-11d2-aa0d-00e098032b8c");
+        assert!(EfiGuid::parse(&data[..15]).is_err());
+    }
+
+    #[test]
+    //UTF-16 string ends at the NUL character
+    fn test_read_utf16_nul() {
+        let data = [b'B', 0, b'o', 0, 0, 0, 0xff, 0xff];
+        assert_eq!(read_utf16_nul(&data).unwrap(), ("Bo".to_string(), 6));
+        assert!(read_utf16_nul(&data[..4]).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::binary_blob::*;
+use crate::uefi::*;
+use crate::x509::X509Certificate;
+use anyhow::anyhow;
+use log::info;
+
+pub const EFI_CERT_SHA1_GUID: EfiGuid = EfiGuid::new(
+    0x826ca512,
+    0xcf10,
+    0x4ac9,
+    [0xb1, 0x87, 0xbe, 0x01, 0x49, 0x66, 0x31, 0xbd],
+);
+pub const EFI_CERT_SHA256_GUID: EfiGuid = EfiGuid::new(
+    0xc1c41626,
+    0x504c,
+    0x4092,
+    [0xac, 0xa9, 0x41, 0xf9, 0x36, 0x93, 0x43, 0x28],
+);
+pub const EFI_CERT_SHA384_GUID: EfiGuid = EfiGuid::new(
+    0xff3e5307,
+    0x9fd0,
+    0x48c9,
+    [0x85, 0xf1, 0x8a, 0xd5, 0x6c, 0x70, 0x1e, 0x01],
+);
+pub const EFI_CERT_SHA512_GUID: EfiGuid = EfiGuid::new(
+    0x093e0fae,
+    0xa6c4,
+    0x4f50,
+    [0x9f, 0x1b, 0xd4, 0x1e, 0x2b, 0x89, 0xc1, 0x9a],
+);
+pub const EFI_CERT_RSA2048_GUID: EfiGuid = EfiGuid::new(
+    0x3c5766e8,
+    0x269c,
+    0x4e34,
+    [0xaa, 0x14, 0xed, 0x77, 0x6e, 0x85, 0xb3, 0xb6],
+);
+pub const EFI_CERT_X509_GUID: EfiGuid = EfiGuid::new(
+    0xa5c059a1,
+    0x94e4,
+    0x4aa7,
+    [0x87, 0xb5, 0xab, 0x15, 0x5c, 0x2b, 0xf0, 0x72],
+);
+pub const EFI_CERT_X509_SHA256_GUID: EfiGuid = EfiGuid::new(
+    0x3bd2a492,
+    0x96c0,
+    0x4079,
+    [0xb4, 0x20, 0xfc, 0xf9, 0x8e, 0xf1, 0x03, 0xed],
+);
+pub const EFI_CERT_X509_SHA384_GUID: EfiGuid = EfiGuid::new(
+    0x7076876e,
+    0x80c2,
+    0x4ee6,
+    [0xaa, 0xd2, 0x28, 0xb3, 0x49, 0xa6, 0x86, 0x5b],
+);
+pub const EFI_CERT_X509_SHA512_GUID: EfiGuid = EfiGuid::new(
+    0x446dbf63,
+    0x2502,
+    0x4cda,
+    [0xbc, 0xfa, 0x24, 0x65, 0xd2, 0xb0, 0xfe, 0x9d],
+);
+
+/***
+    EFI_SIGNATURE_DATA defined in UEFI specification 32.4.1.
+    typedef struct _EFI_SIGNATURE_DATA {
+        EFI_GUID SignatureOwner;
+        UINT8 SignatureData[_];
+    } EFI_SIGNATURE_DATA;
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EfiSignatureData {
+    pub signature_owner: EfiGuid,
+    pub signature_data: Vec<u8>,
+}
+
+impl EfiSignatureData {
+    pub fn parse(data: &[u8]) -> Result<EfiSignatureData, anyhow::Error> {
+        check_len(data, 0, EfiGuid::LEN, "EfiSignatureData::parse")?;
+        Ok(EfiSignatureData {
+            signature_owner: EfiGuid::parse(&data[0..EfiGuid::LEN])?,
+            signature_data: data[EfiGuid::LEN..].to_vec(),
+        })
+    }
+}
+
+/***
+    EFI_SIGNATURE_LIST defined in UEFI specification 32.4.1, the content of the
+    signature database variables PK, KEK, db and dbx is an array of them.
+    typedef struct _EFI_SIGNATURE_LIST {
+        EFI_GUID SignatureType;
+        UINT32 SignatureListSize;
+        UINT32 SignatureHeaderSize;
+        UINT32 SignatureSize;
+        UINT8 SignatureHeader[SignatureHeaderSize];
+        EFI_SIGNATURE_DATA Signatures[][SignatureSize];
+    } EFI_SIGNATURE_LIST;
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EfiSignatureList {
+    pub signature_type: EfiGuid,
+    pub signature_header: Vec<u8>,
+    pub signatures: Vec<EfiSignatureData>,
+}
+
+impl EfiSignatureList {
+    // parse a list, returns the list and its size
+    fn parse(data: &[u8]) -> Result<(EfiSignatureList, usize), anyhow::Error> {
+        check_len(data, 0, 28, "EfiSignatureList::parse")?;
+        let list_size = get_u32(data[16..20].to_vec()) as usize;
+        let header_size = get_u32(data[20..24].to_vec()) as usize;
+        let signature_size = get_u32(data[24..28].to_vec()) as usize;
+        check_len(data, 0, list_size, "EfiSignatureList::parse")?;
+        let signatures_len = match list_size.checked_sub(28 + header_size) {
+            Some(v) => v,
+            None => {
+                return Err(anyhow!(
+                    "[EfiSignatureList::parse] SignatureListSize {} is smaller than the header",
+                    list_size
+                ))
+            }
+        };
+        let chunks = data[28 + header_size..list_size].chunks_exact(signature_size.max(1));
+        if signature_size < EfiGuid::LEN || !chunks.remainder().is_empty() {
+            return Err(anyhow!(
+                "[EfiSignatureList::parse] invalid SignatureSize {} for {} bytes of signatures",
+                signature_size,
+                signatures_len
+            ));
+        }
+
+        let signatures = chunks
+            .map(EfiSignatureData::parse)
+            .collect::<Result<Vec<EfiSignatureData>, anyhow::Error>>()?;
+        Ok((
+            EfiSignatureList {
+                signature_type: EfiGuid::parse(&data[0..16])?,
+                signature_header: data[28..28 + header_size].to_vec(),
+                signatures,
+            },
+            list_size,
+        ))
+    }
+
+    /***
+        Parse the array of signature lists of a signature database variable.
+        Returns:
+            The signature lists, empty for an empty variable
+    */
+    pub fn parse_list(data: &[u8]) -> Result<Vec<EfiSignatureList>, anyhow::Error> {
+        let mut lists = Vec::new();
+        let mut index = 0;
+        while index < data.len() {
+            let (list, size) = EfiSignatureList::parse(&data[index..])?;
+            lists.push(list);
+            index += size;
+        }
+        Ok(lists)
+    }
+
+    // name of the signature type, e.g. X509 or SHA256
+    pub fn type_name(&self) -> String {
+        match self.signature_type {
+            EFI_CERT_SHA1_GUID => "SHA1".to_string(),
+            EFI_CERT_SHA256_GUID => "SHA256".to_string(),
+            EFI_CERT_SHA384_GUID => "SHA384".to_string(),
+            EFI_CERT_SHA512_GUID => "SHA512".to_string(),
+            EFI_CERT_RSA2048_GUID => "RSA2048".to_string(),
+            EFI_CERT_X509_GUID => "X509".to_string(),
+            EFI_CERT_X509_SHA256_GUID => "X509_SHA256".to_string(),
+            EFI_CERT_X509_SHA384_GUID => "X509_SHA384".to_string(),
+            EFI_CERT_X509_SHA512_GUID => "X509_SHA512".to_string(),
+            guid => guid.to_string(),
+        }
+    }
+
+    // X.509 certificates of an EFI_CERT_X509_GUID list, empty for other lists
+    pub fn certificates(&self) -> Result<Vec<X509Certificate>, anyhow::Error> {
+        if self.signature_type != EFI_CERT_X509_GUID {
+            return Ok(Vec::new());
+        }
+        self.signatures
+            .iter()
+            .map(|signature| X509Certificate::parse(&signature.signature_data))
+            .collect()
+    }
+
+    // hashes of an EFI_CERT_SHA*_GUID list, empty for other lists
+    pub fn hashes(&self) -> Vec<Vec<u8>> {
+        match self.signature_type {
+            EFI_CERT_SHA1_GUID | EFI_CERT_SHA256_GUID | EFI_CERT_SHA384_GUID
+            | EFI_CERT_SHA512_GUID => self
+                .signatures
+                .iter()
+                .map(|signature| signature.signature_data.clone())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn show(&self) {
+        info!(
+            "        SignatureList     : {} ({} signatures)",
+            self.type_name(),
+            self.signatures.len()
+        );
+        let certificates = self.certificates().unwrap_or_default();
+        for (index, signature) in self.signatures.iter().enumerate() {
+            match certificates.get(index) {
+                Some(certificate) => info!(
+                    "            Owner {}: {}",
+                    signature.signature_owner, certificate
+                ),
+                None => info!(
+                    "            Owner {}: {}",
+                    signature.signature_owner,
+                    hex::encode(&signature.signature_data)
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_uefi_signature {
+    use super::*;
+    use crate::x509::test_x509::test_certificate;
+
+    pub(crate) fn signature_list(signature_type: EfiGuid, signatures: &[Vec<u8>]) -> Vec<u8> {
+        let signature_size = 16 + signatures.first().map_or(0, |v| v.len());
+        let mut data = Vec::new();
+        data.extend(signature_type.data1.to_le_bytes());
+        data.extend(signature_type.data2.to_le_bytes());
+        data.extend(signature_type.data3.to_le_bytes());
+        data.extend(signature_type.data4);
+        data.extend(((28 + signature_size * signatures.len()) as u32).to_le_bytes());
+        data.extend(0_u32.to_le_bytes());
+        data.extend((signature_size as u32).to_le_bytes());
+        for signature in signatures {
+            data.extend([0x77; 16]);
+            data.extend(signature);
+        }
+        data
+    }
+
+    #[test]
+    //db with a certificate list and a hash list
+    fn test_signature_lists() {
+        let mut data = signature_list(EFI_CERT_X509_GUID, &[test_certificate("Test UEFI CA", 1)]);
+        data.extend(signature_list(
+            EFI_CERT_SHA256_GUID,
+            &[vec![0xaa; 32], vec![0xbb; 32]],
+        ));
+        let lists = EfiSignatureList::parse_list(&data).unwrap();
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0].type_name(), "X509");
+        let certificates = lists[0].certificates().unwrap();
+        assert_eq!(certificates[0].subject, "O=Test, CN=Test UEFI CA");
+        assert!(lists[0].hashes().is_empty());
+        assert_eq!(lists[1].hashes(), vec![vec![0xaa; 32], vec![0xbb; 32]]);
+        assert_eq!(lists[1].signatures[0].signature_owner.data1, 0x77777777);
+        assert!(EfiSignatureList::parse_list(&data[..data.len() - 1]).is_err());
+        assert!(EfiSignatureList::parse_list(&[]).unwrap().is_empty());
+    }
+}
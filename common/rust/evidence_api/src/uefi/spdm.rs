@@ -0,0 +1,469 @@
+use crate::binary_blob::*;
+use crate::uefi::device_path::DevicePath;
+use crate::uefi::*;
+use crate::x509::der_length;
+use anyhow::anyhow;
+use log::info;
+
+pub const SPDM_DEVICE_SEC2_SIGNATURE: &[u8; 16] = b"SPDM Device Sec2";
+
+pub const TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_TYPE_NULL: u32 = 0;
+pub const TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_TYPE_PCI: u32 = 1;
+pub const TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_TYPE_USB: u32 = 2;
+
+pub const TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_SUB_HEADER_TYPE_SPDM_MEASUREMENT_BLOCK: u32 = 0;
+pub const TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_SUB_HEADER_TYPE_SPDM_CERT_CHAIN: u32 = 1;
+
+/***
+    TCG_DEVICE_SECURITY_EVENT_DATA2 carried by EV_EFI_SPDM_FIRMWARE_BLOB and
+    EV_EFI_SPDM_FIRMWARE_CONFIG events, defined in TCG PC Client Platform Firmware
+    Profile Specification 10.2.7.
+    typedef struct tdTCG_DEVICE_SECURITY_EVENT_DATA_HEADER2 {
+        UINT8 Signature[16];
+        UINT16 Version;
+        UINT8 AuthState;
+        UINT8 Reserved;
+        UINT32 Length;
+        UINT32 DeviceType;
+        UINT32 SubHeaderType;
+        UINT32 SubHeaderLength;
+        UINT64 SubHeaderUID;
+        UINT64 DevicePathLength;
+        UINT8 DevicePath[DevicePathLength];
+    } TCG_DEVICE_SECURITY_EVENT_DATA_HEADER2;
+    typedef struct tdTCG_DEVICE_SECURITY_EVENT_DATA2 {
+        TCG_DEVICE_SECURITY_EVENT_DATA_HEADER2 EventDataHeader;
+        TCG_DEVICE_SECURITY_EVENT_DATA_SUB_HEADER EventDataSubHeader;
+        TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_CONTEXT DeviceContext;
+    } TCG_DEVICE_SECURITY_EVENT_DATA2;
+*/
+#[derive(Clone, Debug)]
+pub struct DeviceSecurityEventData2 {
+    pub version: u16,
+    pub auth_state: u8,
+    pub length: u32,
+    pub device_type: u32,
+    pub sub_header_uid: u64,
+    pub device_path: DevicePath,
+    pub sub_header: SpdmSubHeader,
+    pub device_context: DeviceContext,
+}
+
+/***
+    TCG_DEVICE_SECURITY_EVENT_DATA_SUB_HEADER, either measurement blocks or a certificate chain.
+    typedef struct tdTCG_DEVICE_SECURITY_EVENT_DATA_SUB_HEADER_SPDM_MEASUREMENT_BLOCK {
+        UINT16 SpdmVersion;
+        UINT8 SpdmMeasurementBlockCount;
+        UINT8 Reserved;
+        UINT32 SpdmMeasurementHashAlgo;
+        SPDM_MEASUREMENT_BLOCK SpdmMeasurementBlock[SpdmMeasurementBlockCount];
+    } TCG_DEVICE_SECURITY_EVENT_DATA_SUB_HEADER_SPDM_MEASUREMENT_BLOCK;
+    typedef struct tdTCG_DEVICE_SECURITY_EVENT_DATA_SUB_HEADER_SPDM_CERT_CHAIN {
+        UINT16 SpdmVersion;
+        UINT8 SpdmSlotId;
+        UINT8 Reserved;
+        UINT32 SpdmBaseHashAlgo;
+        SPDM_CERT_CHAIN SpdmCertChain;
+    } TCG_DEVICE_SECURITY_EVENT_DATA_SUB_HEADER_SPDM_CERT_CHAIN;
+*/
+#[derive(Clone, Debug)]
+pub enum SpdmSubHeader {
+    MeasurementBlocks {
+        spdm_version: u16,
+        measurement_hash_algo: u32,
+        blocks: Vec<SpdmMeasurementBlock>,
+    },
+    CertChain {
+        spdm_version: u16,
+        slot_id: u8,
+        base_hash_algo: u32,
+        cert_chain: SpdmCertChain,
+    },
+    Unknown(u32, Vec<u8>),
+}
+
+/***
+    SPDM measurement block defined in DMTF DSP0274, the DMTF measurement value
+    header is decoded when MeasurementSpecification is DMTF.
+        UINT8 Index;
+        UINT8 MeasurementSpecification;
+        UINT16 MeasurementSize;
+        UINT8 Measurement[MeasurementSize];
+*/
+#[derive(Clone, Debug)]
+pub struct SpdmMeasurementBlock {
+    pub index: u8,
+    pub measurement_specification: u8,
+    pub value_type: Option<u8>,
+    pub value: Vec<u8>,
+}
+
+pub const SPDM_MEASUREMENT_SPECIFICATION_DMTF: u8 = 0x01;
+
+/***
+    SPDM_CERT_CHAIN defined in DMTF DSP0274.
+        UINT16 Length;
+        UINT16 Reserved;
+        UINT8 RootHash[HashSize];
+        UINT8 Certificates[Length - 4 - HashSize];
+    The certificates are split into DER encoded X.509 certificates, leaf last.
+*/
+#[derive(Clone, Debug)]
+pub struct SpdmCertChain {
+    pub root_hash: Vec<u8>,
+    pub certificates: Vec<Vec<u8>>,
+}
+
+/***
+    TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_CONTEXT for PCI devices, USB and unknown
+    contexts are kept raw.
+    typedef struct tdTCG_DEVICE_SECURITY_EVENT_DATA_PCI_CONTEXT {
+        UINT16 Version;
+        UINT16 Length;
+        UINT16 VendorId;
+        UINT16 DeviceId;
+        UINT8 RevisionID;
+        UINT8 ClassCode[3];
+        UINT16 SubsystemVendorID;
+        UINT16 SubsystemID;
+    } TCG_DEVICE_SECURITY_EVENT_DATA_PCI_CONTEXT;
+*/
+#[derive(Clone, Debug)]
+pub enum DeviceContext {
+    Pci {
+        vendor_id: u16,
+        device_id: u16,
+        revision_id: u8,
+        class_code: [u8; 3],
+        subsystem_vendor_id: u16,
+        subsystem_id: u16,
+    },
+    Other(u32, Vec<u8>),
+}
+
+// hash size of the SPDM BaseHashAlgo/MeasurementHashAlgo bit
+pub fn spdm_hash_size(hash_algo: u32) -> Option<usize> {
+    match hash_algo {
+        0x01 => Some(32), // SHA-256
+        0x02 => Some(48), // SHA-384
+        0x04 => Some(64), // SHA-512
+        0x08 => Some(32), // SHA3-256
+        0x10 => Some(48), // SHA3-384
+        0x20 => Some(64), // SHA3-512
+        0x40 => Some(32), // SM3-256
+        _ => None,
+    }
+}
+
+// split concatenated DER encoded certificates
+pub fn split_der_certificates(data: &[u8]) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+    let mut certificates = Vec::new();
+    let mut index = 0;
+    while index < data.len() {
+        if data[index] != 0x30 {
+            return Err(anyhow!(
+                "[split_der_certificates] no DER SEQUENCE at offset {}",
+                index
+            ));
+        }
+        let len = der_length(&data[index..])?;
+        certificates.push(data[index..index + len].to_vec());
+        index += len;
+    }
+    Ok(certificates)
+}
+
+impl SpdmMeasurementBlock {
+    // parse a block, returns the block and its size
+    fn parse(data: &[u8]) -> Result<(SpdmMeasurementBlock, usize), anyhow::Error> {
+        check_len(data, 0, 4, "SpdmMeasurementBlock::parse")?;
+        let size = get_u16(data[2..4].to_vec()) as usize;
+        check_len(data, 4, size, "SpdmMeasurementBlock::parse")?;
+        let measurement = &data[4..4 + size];
+
+        let mut block = SpdmMeasurementBlock {
+            index: data[0],
+            measurement_specification: data[1],
+            value_type: None,
+            value: measurement.to_vec(),
+        };
+        if block.measurement_specification & SPDM_MEASUREMENT_SPECIFICATION_DMTF != 0 {
+            // DMTFSpecMeasurementValueType(1) + DMTFSpecMeasurementValueSize(2) + value
+            check_len(measurement, 0, 3, "SpdmMeasurementBlock::parse")?;
+            let value_size = get_u16(measurement[1..3].to_vec()) as usize;
+            check_len(measurement, 3, value_size, "SpdmMeasurementBlock::parse")?;
+            block.value_type = Some(measurement[0]);
+            block.value = measurement[3..3 + value_size].to_vec();
+        }
+        Ok((block, 4 + size))
+    }
+}
+
+impl SpdmSubHeader {
+    fn parse(sub_header_type: u32, data: &[u8]) -> Result<SpdmSubHeader, anyhow::Error> {
+        match sub_header_type {
+            TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_SUB_HEADER_TYPE_SPDM_MEASUREMENT_BLOCK => {
+                check_len(data, 0, 8, "SpdmSubHeader::parse")?;
+                let count = data[2];
+                let mut blocks = Vec::new();
+                let mut index = 8;
+                for _ in 0..count {
+                    let (block, size) = SpdmMeasurementBlock::parse(&data[index..])?;
+                    blocks.push(block);
+                    index += size;
+                }
+                Ok(SpdmSubHeader::MeasurementBlocks {
+                    spdm_version: get_u16(data[0..2].to_vec()),
+                    measurement_hash_algo: get_u32(data[4..8].to_vec()),
+                    blocks,
+                })
+            }
+            TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_SUB_HEADER_TYPE_SPDM_CERT_CHAIN => {
+                check_len(data, 0, 12, "SpdmSubHeader::parse")?;
+                let base_hash_algo = get_u32(data[4..8].to_vec());
+                let hash_size = match spdm_hash_size(base_hash_algo) {
+                    Some(v) => v,
+                    None => {
+                        return Err(anyhow!(
+                            "[SpdmSubHeader::parse] unsupported SPDM hash algorithm 0x{:x}",
+                            base_hash_algo
+                        ))
+                    }
+                };
+                let chain = &data[8..];
+                let chain_len = get_u16(chain[0..2].to_vec()) as usize;
+                if chain_len < 4 + hash_size || chain_len > chain.len() {
+                    return Err(anyhow!(
+                        "[SpdmSubHeader::parse] invalid SPDM certificate chain length {}",
+                        chain_len
+                    ));
+                }
+                Ok(SpdmSubHeader::CertChain {
+                    spdm_version: get_u16(data[0..2].to_vec()),
+                    slot_id: data[2],
+                    base_hash_algo,
+                    cert_chain: SpdmCertChain {
+                        root_hash: chain[4..4 + hash_size].to_vec(),
+                        certificates: split_der_certificates(&chain[4 + hash_size..chain_len])?,
+                    },
+                })
+            }
+            _ => Ok(SpdmSubHeader::Unknown(sub_header_type, data.to_vec())),
+        }
+    }
+}
+
+impl DeviceContext {
+    fn parse(device_type: u32, data: &[u8]) -> Result<DeviceContext, anyhow::Error> {
+        if device_type != TCG_DEVICE_SECURITY_EVENT_DATA_DEVICE_TYPE_PCI {
+            return Ok(DeviceContext::Other(device_type, data.to_vec()));
+        }
+        check_len(data, 0, 16, "DeviceContext::parse")?;
+        Ok(DeviceContext::Pci {
+            vendor_id: get_u16(data[4..6].to_vec()),
+            device_id: get_u16(data[6..8].to_vec()),
+            revision_id: data[8],
+            class_code: data[9..12].try_into().unwrap(),
+            subsystem_vendor_id: get_u16(data[12..14].to_vec()),
+            subsystem_id: get_u16(data[14..16].to_vec()),
+        })
+    }
+}
+
+impl DeviceSecurityEventData2 {
+    pub fn parse(data: &[u8]) -> Result<DeviceSecurityEventData2, anyhow::Error> {
+        check_len(data, 0, 52, "DeviceSecurityEventData2::parse")?;
+        if &data[0..16] != SPDM_DEVICE_SEC2_SIGNATURE {
+            return Err(anyhow!(
+                "[DeviceSecurityEventData2::parse] invalid signature {:02X?}",
+                &data[0..16]
+            ));
+        }
+        let device_type = get_u32(data[24..28].to_vec());
+        let sub_header_type = get_u32(data[28..32].to_vec());
+        let sub_header_len = get_u32(data[32..36].to_vec()) as usize;
+        let device_path_len = match usize::try_from(get_u64(data[44..52].to_vec())) {
+            Ok(v) => v,
+            Err(_) => {
+                return Err(anyhow!(
+                    "[DeviceSecurityEventData2::parse] invalid DevicePathLength"
+                ))
+            }
+        };
+        check_len(data, 52, device_path_len, "DeviceSecurityEventData2::parse")?;
+        let index = 52 + device_path_len;
+        check_len(
+            data,
+            index,
+            sub_header_len,
+            "DeviceSecurityEventData2::parse",
+        )?;
+
+        Ok(DeviceSecurityEventData2 {
+            version: get_u16(data[16..18].to_vec()),
+            auth_state: data[18],
+            length: get_u32(data[20..24].to_vec()),
+            device_type,
+            sub_header_uid: get_u64(data[36..44].to_vec()),
+            device_path: DevicePath::parse(&data[52..index])?,
+            sub_header: SpdmSubHeader::parse(
+                sub_header_type,
+                &data[index..index + sub_header_len],
+            )?,
+            device_context: DeviceContext::parse(device_type, &data[index + sub_header_len..])?,
+        })
+    }
+
+    pub fn show(&self) {
+        info!("        DeviceType        : {}", self.device_type);
+        info!("        AuthState         : {}", self.auth_state);
+        info!("        DevicePath        : {}", self.device_path);
+        if let DeviceContext::Pci {
+            vendor_id,
+            device_id,
+            ..
+        } = self.device_context
+        {
+            info!(
+                "        PCI Device        : {:04x}:{:04x}",
+                vendor_id, device_id
+            );
+        }
+        match &self.sub_header {
+            SpdmSubHeader::MeasurementBlocks { blocks, .. } => {
+                for block in blocks {
+                    info!(
+                        "        Measurement[{}]    : type {:?} {}",
+                        block.index,
+                        block.value_type,
+                        hex::encode(&block.value)
+                    );
+                }
+            }
+            SpdmSubHeader::CertChain {
+                slot_id,
+                cert_chain,
+                ..
+            } => {
+                info!(
+                    "        CertChain         : slot {} with {} certificates",
+                    slot_id,
+                    cert_chain.certificates.len()
+                );
+            }
+            SpdmSubHeader::Unknown(sub_header_type, data) => {
+                info!("        SubHeaderType     : {}", sub_header_type);
+                dump_data(data);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_uefi_spdm {
+    use super::*;
+
+    fn event(device_type: u32, sub_header_type: u32, sub_header: &[u8], context: &[u8]) -> Vec<u8> {
+        let mut data = SPDM_DEVICE_SEC2_SIGNATURE.to_vec();
+        data.extend(2_u16.to_le_bytes());
+        data.extend([0, 0]);
+        data.extend(0_u32.to_le_bytes());
+        data.extend(device_type.to_le_bytes());
+        data.extend(sub_header_type.to_le_bytes());
+        data.extend((sub_header.len() as u32).to_le_bytes());
+        data.extend(7_u64.to_le_bytes());
+        data.extend(4_u64.to_le_bytes());
+        data.extend([0x7f, 0xff, 0x04, 0x00]);
+        data.extend(sub_header);
+        data.extend(context);
+        data
+    }
+
+    fn pci_context() -> Vec<u8> {
+        let mut context = vec![0, 0, 16, 0];
+        context.extend(0x8086_u16.to_le_bytes());
+        context.extend(0x0b25_u16.to_le_bytes());
+        context.extend([1, 0, 0, 1, 0x86, 0x80, 0, 0]);
+        context
+    }
+
+    #[test]
+    //measurement blocks of a PCI device
+    fn test_measurement_blocks() {
+        let mut sub_header = vec![0x12, 0, 2, 0];
+        sub_header.extend(0x02_u32.to_le_bytes());
+        for index in 1..=2_u8 {
+            sub_header.extend([index, SPDM_MEASUREMENT_SPECIFICATION_DMTF, 7, 0]);
+            sub_header.extend([0x01, 4, 0, index, index, index, index]);
+        }
+        let data = event(1, 0, &sub_header, &pci_context());
+        let event = DeviceSecurityEventData2::parse(&data).unwrap();
+        assert_eq!(event.sub_header_uid, 7);
+        match event.sub_header {
+            SpdmSubHeader::MeasurementBlocks { blocks, .. } => {
+                assert_eq!(blocks.len(), 2);
+                assert_eq!(blocks[1].index, 2);
+                assert_eq!(blocks[1].value_type, Some(1));
+                assert_eq!(blocks[1].value, vec![2; 4]);
+            }
+            _ => panic!("unexpected sub header"),
+        }
+        match event.device_context {
+            DeviceContext::Pci {
+                vendor_id,
+                device_id,
+                ..
+            } => assert_eq!((vendor_id, device_id), (0x8086, 0x0b25)),
+            _ => panic!("unexpected device context"),
+        }
+    }
+
+    #[test]
+    //certificate chain is split by DER length
+    fn test_cert_chain() {
+        let short_cert = [0x30, 0x03, 0x02, 0x01, 0x01];
+        let mut long_cert = vec![0x30, 0x81, 0x80];
+        long_cert.extend([0x04; 0x80]);
+        let mut chain = Vec::new();
+        chain.extend(((4 + 32 + short_cert.len() + long_cert.len()) as u16).to_le_bytes());
+        chain.extend([0, 0]);
+        chain.extend([0xaa; 32]);
+        chain.extend(short_cert);
+        chain.extend(&long_cert);
+        let mut sub_header = vec![0x12, 0, 0, 0];
+        sub_header.extend(0x01_u32.to_le_bytes());
+        sub_header.extend(chain);
+
+        let data = event(1, 1, &sub_header, &pci_context());
+        match DeviceSecurityEventData2::parse(&data).unwrap().sub_header {
+            SpdmSubHeader::CertChain { cert_chain, .. } => {
+                assert_eq!(cert_chain.root_hash, vec![0xaa; 32]);
+                assert_eq!(cert_chain.certificates.len(), 2);
+                assert_eq!(cert_chain.certificates[1], long_cert);
+            }
+            _ => panic!("unexpected sub header"),
+        }
+        assert!(split_der_certificates(&long_cert[..0x80]).is_err());
+    }
+
+    #[test]
+    //invalid signature and truncated sub header are rejected
+    fn test_invalid_event() {
+        let data = event(1, 0, &[0x12, 0, 1, 0, 2, 0, 0, 0], &pci_context());
+        assert!(DeviceSecurityEventData2::parse(&data).is_err());
+        let mut data = event(0, 2, &[], &[]);
+        assert!(DeviceSecurityEventData2::parse(&data).is_ok());
+        data[0] = b's';
+        assert!(DeviceSecurityEventData2::parse(&data).is_err());
+    }
+
+    #[test]
+    //header truncated within DevicePathLength is rejected
+    fn test_truncated_header() {
+        let data = event(0, 2, &[], &[]);
+        assert_eq!(data.len(), 56);
+        for len in [0, 48, 51, 52] {
+            assert!(DeviceSecurityEventData2::parse(&data[..len]).is_err());
+        }
+    }
+}
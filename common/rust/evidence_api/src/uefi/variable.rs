@@ -0,0 +1,357 @@
+use crate::binary_blob::*;
+use crate::uefi::device_path::DevicePath;
+use crate::uefi::signature::EfiSignatureList;
+use crate::uefi::*;
+use anyhow::anyhow;
+use log::info;
+
+/***
+    UEFI_VARIABLE_DATA carried by EV_EFI_VARIABLE_DRIVER_CONFIG, EV_EFI_VARIABLE_BOOT,
+    EV_EFI_VARIABLE_BOOT2 and EV_EFI_VARIABLE_AUTHORITY events.
+    typedef struct tdUEFI_VARIABLE_DATA {
+        UEFI_GUID VariableName;
+        UINT64 UnicodeNameLength;
+        UINT64 VariableDataLength;
+        CHAR16 UnicodeName[];
+        INT8 VariableData[];
+    } UEFI_VARIABLE_DATA;
+*/
+#[derive(Clone, Debug)]
+pub struct UefiVariableData {
+    pub variable_name: EfiGuid,
+    pub unicode_name: String,
+    pub variable_data: Vec<u8>,
+}
+
+/***
+    Interpreted content of well-known UEFI variables.
+    Signature databases are decoded into their EFI_SIGNATURE_LISTs.
+*/
+#[derive(Clone, Debug)]
+pub enum UefiVariableValue {
+    BootOrder(Vec<u16>),
+    BootOption(u16, EfiLoadOption),
+    SecureBoot(bool),
+    SetupMode(bool),
+    PlatformKey(Vec<EfiSignatureList>),
+    KeyExchangeKey(Vec<EfiSignatureList>),
+    SignatureDatabase(Vec<EfiSignatureList>),
+    ForbiddenSignatureDatabase(Vec<EfiSignatureList>),
+    Raw(Vec<u8>),
+}
+
+/***
+    EFI_LOAD_OPTION stored in Boot#### variables, defined in UEFI specification 3.1.3.
+    typedef struct _EFI_LOAD_OPTION {
+        UINT32 Attributes;
+        UINT16 FilePathListLength;
+        CHAR16 Description[];
+        EFI_DEVICE_PATH_PROTOCOL FilePathList[];
+        UINT8 OptionalData[];
+    } EFI_LOAD_OPTION;
+*/
+#[derive(Clone, Debug)]
+pub struct EfiLoadOption {
+    pub attributes: u32,
+    pub description: String,
+    pub file_path_list: Vec<u8>,
+    pub optional_data: Vec<u8>,
+}
+
+pub const LOAD_OPTION_ACTIVE: u32 = 0x00000001;
+
+impl EfiLoadOption {
+    pub fn parse(data: &[u8]) -> Result<EfiLoadOption, anyhow::Error> {
+        check_len(data, 0, 6, "EfiLoadOption::parse")?;
+        let attributes = get_u32(data[0..4].to_vec());
+        let file_path_list_length = get_u16(data[4..6].to_vec()) as usize;
+        let (description, description_len) = read_utf16_nul(&data[6..])?;
+        let index = 6 + description_len;
+        check_len(data, index, file_path_list_length, "EfiLoadOption::parse")?;
+
+        Ok(EfiLoadOption {
+            attributes,
+            description,
+            file_path_list: data[index..index + file_path_list_length].to_vec(),
+            optional_data: data[index + file_path_list_length..].to_vec(),
+        })
+    }
+
+    pub fn device_path(&self) -> Result<DevicePath, anyhow::Error> {
+        DevicePath::parse(&self.file_path_list)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.attributes & LOAD_OPTION_ACTIVE != 0
+    }
+}
+
+impl UefiVariableData {
+    pub fn parse(data: &[u8]) -> Result<UefiVariableData, anyhow::Error> {
+        check_len(data, 0, 32, "UefiVariableData::parse")?;
+        let variable_name = EfiGuid::parse(&data[0..16])?;
+        let name_len = get_u64(data[16..24].to_vec());
+        let data_len = get_u64(data[24..32].to_vec());
+
+        let name_size = match usize::try_from(name_len)
+            .ok()
+            .and_then(|v| v.checked_mul(2))
+        {
+            Some(v) => v,
+            None => {
+                return Err(anyhow!(
+                    "[UefiVariableData::parse] invalid UnicodeNameLength {}",
+                    name_len
+                ))
+            }
+        };
+        let data_size = match usize::try_from(data_len) {
+            Ok(v) => v,
+            Err(_) => {
+                return Err(anyhow!(
+                    "[UefiVariableData::parse] invalid VariableDataLength {}",
+                    data_len
+                ))
+            }
+        };
+        check_len(data, 32, name_size, "UefiVariableData::parse")?;
+        check_len(data, 32 + name_size, data_size, "UefiVariableData::parse")?;
+
+        let index = 32 + name_size;
+        Ok(UefiVariableData {
+            variable_name,
+            unicode_name: utf16_to_string(&data[32..index]),
+            variable_data: data[index..index + data_size].to_vec(),
+        })
+    }
+
+    // the number #### of a Boot#### variable
+    fn boot_option_number(&self) -> Option<u16> {
+        let number = self.unicode_name.strip_prefix("Boot")?;
+        if number.len() != 4 || !number.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        u16::from_str_radix(number, 16).ok()
+    }
+
+    /***
+        Interpret the variable data of well-known variables: BootOrder, Boot####,
+        SecureBoot, SetupMode, PK, KEK, db and dbx. Other variables are kept raw.
+    */
+    pub fn value(&self) -> Result<UefiVariableValue, anyhow::Error> {
+        let data = &self.variable_data;
+        if self.variable_name == EFI_GLOBAL_VARIABLE {
+            match self.unicode_name.as_str() {
+                "BootOrder" => {
+                    let chunks = data.chunks_exact(2);
+                    if !chunks.remainder().is_empty() {
+                        return Err(anyhow!("[value] BootOrder of odd length {}", data.len()));
+                    }
+                    return Ok(UefiVariableValue::BootOrder(
+                        chunks.map(|c| u16::from_le_bytes([c[0], c[1]])).collect(),
+                    ));
+                }
+                "SecureBoot" | "SetupMode" => {
+                    if data.len() != 1 {
+                        return Err(anyhow!(
+                            "[value] {} must be 1 byte, got {} bytes",
+                            self.unicode_name,
+                            data.len()
+                        ));
+                    }
+                    let enabled = data[0] == 1;
+                    return Ok(match self.unicode_name.as_str() {
+                        "SecureBoot" => UefiVariableValue::SecureBoot(enabled),
+                        _ => UefiVariableValue::SetupMode(enabled),
+                    });
+                }
+                "PK" => {
+                    return Ok(UefiVariableValue::PlatformKey(
+                        EfiSignatureList::parse_list(data)?,
+                    ))
+                }
+                "KEK" => {
+                    return Ok(UefiVariableValue::KeyExchangeKey(
+                        EfiSignatureList::parse_list(data)?,
+                    ))
+                }
+                _ => (),
+            }
+            if let Some(number) = self.boot_option_number() {
+                return Ok(UefiVariableValue::BootOption(
+                    number,
+                    EfiLoadOption::parse(data)?,
+                ));
+            }
+        } else if self.variable_name == EFI_IMAGE_SECURITY_DATABASE_GUID {
+            match self.unicode_name.as_str() {
+                "db" => {
+                    return Ok(UefiVariableValue::SignatureDatabase(
+                        EfiSignatureList::parse_list(data)?,
+                    ))
+                }
+                "dbx" => {
+                    return Ok(UefiVariableValue::ForbiddenSignatureDatabase(
+                        EfiSignatureList::parse_list(data)?,
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(UefiVariableValue::Raw(data.clone()))
+    }
+
+    pub fn show(&self) {
+        info!("        VariableName      : {}", self.variable_name);
+        info!("        UnicodeName       : {}", self.unicode_name);
+        match self.value() {
+            Ok(UefiVariableValue::BootOrder(order)) => {
+                let order: Vec<String> = order.iter().map(|v| format!("Boot{:04X}", v)).collect();
+                info!("        BootOrder         : {}", order.join(","));
+            }
+            Ok(UefiVariableValue::BootOption(number, option)) => {
+                info!(
+                    "        Boot{:04X}          : {} (attributes {:#x})",
+                    number, option.description, option.attributes
+                );
+                if let Ok(device_path) = option.device_path() {
+                    info!("        DevicePath        : {}", device_path);
+                }
+            }
+            Ok(UefiVariableValue::SecureBoot(enabled)) => {
+                info!("        SecureBoot        : {}", enabled);
+            }
+            Ok(UefiVariableValue::SetupMode(enabled)) => {
+                info!("        SetupMode         : {}", enabled);
+            }
+            Ok(UefiVariableValue::PlatformKey(lists))
+            | Ok(UefiVariableValue::KeyExchangeKey(lists))
+            | Ok(UefiVariableValue::SignatureDatabase(lists))
+            | Ok(UefiVariableValue::ForbiddenSignatureDatabase(lists)) => {
+                for list in lists {
+                    list.show();
+                }
+            }
+            _ => {
+                info!("        VariableData:");
+                dump_data(&self.variable_data);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_uefi_variable {
+    use super::*;
+    use crate::ccel::CcelLoader;
+    use crate::tcg::*;
+
+    const SYSROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/sysroot");
+
+    pub(crate) fn variable_data(guid: EfiGuid, name: &str, data: &[u8]) -> Vec<u8> {
+        let mut raw = Vec::new();
+        raw.extend(guid.data1.to_le_bytes());
+        raw.extend(guid.data2.to_le_bytes());
+        raw.extend(guid.data3.to_le_bytes());
+        raw.extend(guid.data4);
+        raw.extend((name.encode_utf16().count() as u64).to_le_bytes());
+        raw.extend((data.len() as u64).to_le_bytes());
+        raw.extend(name.encode_utf16().flat_map(|c| c.to_le_bytes()));
+        raw.extend(data);
+        raw
+    }
+
+    #[test]
+    //BootOrder is a list of boot option numbers
+    fn test_boot_order() {
+        let raw = variable_data(EFI_GLOBAL_VARIABLE, "BootOrder", &[1, 0, 0, 0]);
+        let variable = UefiVariableData::parse(&raw).unwrap();
+        assert_eq!(variable.unicode_name, "BootOrder");
+        match variable.value().unwrap() {
+            UefiVariableValue::BootOrder(order) => assert_eq!(order, vec![1, 0]),
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    //Boot#### contains the load option
+    fn test_boot_option() {
+        let mut option = vec![1, 0, 0, 0, 4, 0];
+        option.extend("UEFI".encode_utf16().flat_map(|c| c.to_le_bytes()));
+        option.extend([0, 0, 0x7f, 0xff, 0x04, 0x00, 0xaa]);
+        let raw = variable_data(EFI_GLOBAL_VARIABLE, "Boot000A", &option);
+        match UefiVariableData::parse(&raw).unwrap().value().unwrap() {
+            UefiVariableValue::BootOption(number, option) => {
+                assert_eq!(number, 0xa);
+                assert!(option.is_active());
+                assert_eq!(option.description, "UEFI");
+                assert_eq!(option.file_path_list, vec![0x7f, 0xff, 0x04, 0x00]);
+                assert_eq!(option.optional_data, vec![0xaa]);
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+
+    #[test]
+    //dbx is only interpreted with the image security database GUID
+    fn test_signature_database_guid() {
+        let raw = variable_data(EFI_IMAGE_SECURITY_DATABASE_GUID, "dbx", &[]);
+        assert!(matches!(
+            UefiVariableData::parse(&raw).unwrap().value().unwrap(),
+            UefiVariableValue::ForbiddenSignatureDatabase(_)
+        ));
+        let raw = variable_data(EFI_IMAGE_SECURITY_DATABASE_GUID, "dbx", &[1, 2]);
+        assert!(UefiVariableData::parse(&raw).unwrap().value().is_err());
+        let raw = variable_data(EFI_GLOBAL_VARIABLE, "dbx", &[1, 2]);
+        assert!(matches!(
+            UefiVariableData::parse(&raw).unwrap().value().unwrap(),
+            UefiVariableValue::Raw(_)
+        ));
+    }
+
+    #[test]
+    //lengths beyond the event data are rejected
+    fn test_truncated_variable_data() {
+        let raw = variable_data(EFI_GLOBAL_VARIABLE, "SecureBoot", &[1]);
+        assert!(UefiVariableData::parse(&raw[..raw.len() - 1]).is_err());
+        let mut raw = raw;
+        raw[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(UefiVariableData::parse(&raw).is_err());
+    }
+
+    #[test]
+    //variable events measured by TDVF are decoded
+    fn test_tdvf_variable_events() {
+        let mut event_logs = CcelLoader::with_sysroot(SYSROOT)
+            .load_event_logs(TCG_PCCLIENT_FORMAT)
+            .unwrap();
+        let mut names = Vec::new();
+        for event in event_logs.select(None, None).unwrap() {
+            if let EventLogEntry::TcgImrEvent(event) = event {
+                if let TcgEventData::UefiVariable(variable) = event.parse_event_data().unwrap() {
+                    match variable.value().unwrap() {
+                        UefiVariableValue::SecureBoot(enabled) => assert!(!enabled),
+                        UefiVariableValue::BootOrder(order) => assert_eq!(order, vec![0, 1]),
+                        _ => (),
+                    }
+                    names.push(variable.unicode_name);
+                }
+            }
+        }
+        assert_eq!(
+            names,
+            vec![
+                "SecureBoot",
+                "PK",
+                "KEK",
+                "db",
+                "dbx",
+                "BootOrder",
+                "Boot0000",
+                "Boot0001",
+                "SbatLevel",
+                "SbatLevel"
+            ]
+        );
+    }
+}
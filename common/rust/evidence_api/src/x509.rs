@@ -0,0 +1,283 @@
+use crate::uefi::check_len;
+use anyhow::anyhow;
+use core::fmt;
+
+/***
+    Minimal decoder of DER encoded X.509 certificates defined in RFC 5280, only the
+    fields needed to identify a certificate are decoded.
+    Certificate ::= SEQUENCE {
+        tbsCertificate       TBSCertificate,
+        signatureAlgorithm   AlgorithmIdentifier,
+        signatureValue       BIT STRING }
+    TBSCertificate ::= SEQUENCE {
+        version         [0]  EXPLICIT Version DEFAULT v1,
+        serialNumber         CertificateSerialNumber,
+        signature            AlgorithmIdentifier,
+        issuer               Name,
+        validity             Validity,
+        subject              Name,
+        ... }
+*/
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct X509Certificate {
+    pub der: Vec<u8>,
+    pub tbs_certificate: Vec<u8>,
+    pub serial_number: Vec<u8>,
+    pub issuer: String,
+    pub subject: String,
+}
+
+pub const DER_INTEGER: u8 = 0x02;
+pub const DER_OBJECT_IDENTIFIER: u8 = 0x06;
+pub const DER_UTF8_STRING: u8 = 0x0c;
+pub const DER_PRINTABLE_STRING: u8 = 0x13;
+pub const DER_T61_STRING: u8 = 0x14;
+pub const DER_IA5_STRING: u8 = 0x16;
+pub const DER_BMP_STRING: u8 = 0x1e;
+pub const DER_SEQUENCE: u8 = 0x30;
+pub const DER_SET: u8 = 0x31;
+pub const DER_CONTEXT_0: u8 = 0xa0;
+
+// short names of the attribute types usually found in certificate names
+fn attribute_name(oid: &str) -> Option<&'static str> {
+    match oid {
+        "2.5.4.3" => Some("CN"),
+        "2.5.4.6" => Some("C"),
+        "2.5.4.7" => Some("L"),
+        "2.5.4.8" => Some("ST"),
+        "2.5.4.10" => Some("O"),
+        "2.5.4.11" => Some("OU"),
+        "1.2.840.113549.1.9.1" => Some("emailAddress"),
+        _ => None,
+    }
+}
+
+// header length and content length of the DER element at the beginning of the data
+fn der_header(data: &[u8]) -> Result<(usize, usize), anyhow::Error> {
+    check_len(data, 0, 2, "der_header")?;
+    let (len, header_len) = match data[1] {
+        len if len < 0x80 => (len as usize, 2),
+        0x81..=0x84 => {
+            let count = (data[1] & 0x7f) as usize;
+            check_len(data, 2, count, "der_header")?;
+            let len = data[2..2 + count]
+                .iter()
+                .fold(0_usize, |acc, v| (acc << 8) | *v as usize);
+            (len, 2 + count)
+        }
+        v => {
+            return Err(anyhow!(
+                "[der_header] unsupported DER length byte 0x{:x}",
+                v
+            ))
+        }
+    };
+    check_len(data, header_len, len, "der_header")?;
+    Ok((header_len, len))
+}
+
+// length of the DER encoded element at the beginning of the data
+pub(crate) fn der_length(data: &[u8]) -> Result<usize, anyhow::Error> {
+    let (header_len, len) = der_header(data)?;
+    Ok(header_len + len)
+}
+
+// tag, content and length of the DER encoded element at the beginning of the data
+pub(crate) fn der_element(data: &[u8]) -> Result<(u8, &[u8], usize), anyhow::Error> {
+    let (header_len, len) = der_header(data)?;
+    Ok((
+        data[0],
+        &data[header_len..header_len + len],
+        header_len + len,
+    ))
+}
+
+// read the next element of a constructed element, it must have the expected tag
+fn der_expect<'a>(data: &mut &'a [u8], tag: u8, name: &str) -> Result<&'a [u8], anyhow::Error> {
+    let (element_tag, content, len) = der_element(data)?;
+    if element_tag != tag {
+        return Err(anyhow!(
+            "[X509Certificate::parse] {} has tag 0x{:x}, expected 0x{:x}",
+            name,
+            element_tag,
+            tag
+        ));
+    }
+    *data = &data[len..];
+    Ok(content)
+}
+
+// dotted decimal form of an OBJECT IDENTIFIER
+fn oid_to_string(data: &[u8]) -> String {
+    let mut arcs: Vec<u64> = Vec::new();
+    let mut value: u64 = 0;
+    for byte in data {
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+// value of a directory string, other types are shown in hex
+fn directory_string(tag: u8, data: &[u8]) -> String {
+    match tag {
+        DER_UTF8_STRING | DER_PRINTABLE_STRING | DER_T61_STRING | DER_IA5_STRING => {
+            String::from_utf8_lossy(data).to_string()
+        }
+        DER_BMP_STRING => {
+            let chars: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&chars)
+        }
+        _ => format!("#{}", hex::encode(data)),
+    }
+}
+
+// Name as a list of attributes, e.g. CN=Microsoft Corporation UEFI CA 2011, O=Microsoft Corporation
+fn name_to_string(mut data: &[u8]) -> Result<String, anyhow::Error> {
+    let mut attributes = Vec::new();
+    while !data.is_empty() {
+        let mut set = der_expect(&mut data, DER_SET, "RelativeDistinguishedName")?;
+        while !set.is_empty() {
+            let mut attribute = der_expect(&mut set, DER_SEQUENCE, "AttributeTypeAndValue")?;
+            let oid = oid_to_string(der_expect(
+                &mut attribute,
+                DER_OBJECT_IDENTIFIER,
+                "AttributeType",
+            )?);
+            let (tag, value, _) = der_element(attribute)?;
+            attributes.push(format!(
+                "{}={}",
+                attribute_name(&oid).unwrap_or(&oid),
+                directory_string(tag, value)
+            ));
+        }
+    }
+    Ok(attributes.join(", "))
+}
+
+impl X509Certificate {
+    pub fn parse(data: &[u8]) -> Result<X509Certificate, anyhow::Error> {
+        let mut der = data;
+        let mut certificate = der_expect(&mut der, DER_SEQUENCE, "Certificate")?;
+        let der = &data[..data.len() - der.len()];
+
+        let tbs_len = der_length(certificate)?;
+        let tbs_certificate = &certificate[..tbs_len];
+        let mut tbs = der_expect(&mut certificate, DER_SEQUENCE, "TBSCertificate")?;
+        if tbs.first() == Some(&DER_CONTEXT_0) {
+            der_expect(&mut tbs, DER_CONTEXT_0, "version")?;
+        }
+        let serial_number = der_expect(&mut tbs, DER_INTEGER, "serialNumber")?;
+        der_expect(&mut tbs, DER_SEQUENCE, "signature")?;
+        let issuer = name_to_string(der_expect(&mut tbs, DER_SEQUENCE, "issuer")?)?;
+        der_expect(&mut tbs, DER_SEQUENCE, "validity")?;
+        let subject = name_to_string(der_expect(&mut tbs, DER_SEQUENCE, "subject")?)?;
+
+        Ok(X509Certificate {
+            der: der.to_vec(),
+            tbs_certificate: tbs_certificate.to_vec(),
+            serial_number: serial_number.to_vec(),
+            issuer,
+            subject,
+        })
+    }
+}
+
+impl fmt::Display for X509Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "subject: {}, issuer: {}, serial: {}",
+            self.subject,
+            self.issuer,
+            hex::encode(&self.serial_number)
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_x509 {
+    use super::*;
+
+    // DER element with short or long form length
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut data = vec![tag];
+        if content.len() < 0x80 {
+            data.push(content.len() as u8);
+        } else {
+            data.push(0x82);
+            data.extend((content.len() as u16).to_be_bytes());
+        }
+        data.extend(content);
+        data
+    }
+
+    fn name(common_name: &str) -> Vec<u8> {
+        let mut attribute = der(DER_OBJECT_IDENTIFIER, &[0x55, 0x04, 0x03]);
+        attribute.extend(der(DER_UTF8_STRING, common_name.as_bytes()));
+        let mut organization = der(DER_OBJECT_IDENTIFIER, &[0x55, 0x04, 0x0a]);
+        organization.extend(der(DER_PRINTABLE_STRING, b"Test"));
+        let mut name = der(DER_SET, &der(DER_SEQUENCE, &organization));
+        name.extend(der(DER_SET, &der(DER_SEQUENCE, &attribute)));
+        der(DER_SEQUENCE, &name)
+    }
+
+    // self signed-looking certificate with a dummy signature
+    pub(crate) fn test_certificate(common_name: &str, serial: u8) -> Vec<u8> {
+        let mut tbs = der(DER_CONTEXT_0, &der(DER_INTEGER, &[2]));
+        tbs.extend(der(DER_INTEGER, &[serial]));
+        tbs.extend(der(
+            DER_SEQUENCE,
+            &der(DER_OBJECT_IDENTIFIER, &[0x2a, 0x03]),
+        ));
+        tbs.extend(name(common_name));
+        tbs.extend(der(DER_SEQUENCE, &[]));
+        tbs.extend(name(common_name));
+        let mut certificate = der(DER_SEQUENCE, &tbs);
+        certificate.extend(der(
+            DER_SEQUENCE,
+            &der(DER_OBJECT_IDENTIFIER, &[0x2a, 0x03]),
+        ));
+        certificate.extend(der(0x03, &[0; 0x100]));
+        der(DER_SEQUENCE, &certificate)
+    }
+
+    #[test]
+    //names and serial number are decoded
+    fn test_certificate_parse() {
+        let data = test_certificate("Test UEFI CA", 0x21);
+        let certificate = X509Certificate::parse(&data).unwrap();
+        assert_eq!(certificate.subject, "O=Test, CN=Test UEFI CA");
+        assert_eq!(certificate.issuer, certificate.subject);
+        assert_eq!(certificate.serial_number, vec![0x21]);
+        assert_eq!(certificate.der, data);
+        assert_eq!(certificate.tbs_certificate[0], DER_SEQUENCE);
+        assert!(X509Certificate::parse(&data[..data.len() - 1]).is_err());
+        assert!(X509Certificate::parse(&data[4..]).is_err());
+    }
+
+    #[test]
+    //object identifiers in dotted decimal form
+    fn test_oid_to_string() {
+        assert_eq!(
+            oid_to_string(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01]),
+            "1.2.840.113549.1.9.1"
+        );
+        assert_eq!(oid_to_string(&[0x55, 0x04, 0x03]), "2.5.4.3");
+    }
+}